@@ -0,0 +1,6 @@
+Setup
+  $ . ${TESTDIR}/_helpers/setup.sh
+
+Generate Test Run
+  $ ${TURBO} generate workspace --name ui --__test-run
+  Generate test run successful
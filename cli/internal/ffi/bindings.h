@@ -19,3 +19,11 @@ struct Buffer previous_content(struct Buffer buffer);
 struct Buffer npm_transitive_closure(struct Buffer buf);
 
 struct Buffer npm_subgraph(struct Buffer buf);
+
+struct Buffer lockfile_parse(struct Buffer buf);
+
+struct Buffer lockfile_transitive_closure(struct Buffer buf);
+
+struct Buffer lockfile_subgraph(struct Buffer buf);
+
+struct Buffer lockfile_encode(struct Buffer buf);
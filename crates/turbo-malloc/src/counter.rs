@@ -19,10 +19,45 @@ struct ThreadLocalCounter {
     /// means the global counter is always equal or greater than the real
     /// value.
     buffer: usize,
+    /// The counters that the allocations of this thread are currently
+    /// attributed to, see [with_allocation_counters].
+    allocation_counters: Option<AllocationCounters>,
+}
+
+/// Bytes allocated and freed while the counters were attributed to, see
+/// [crate::TurboMalloc::with_allocation_counters].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationCounters {
+    pub allocated: usize,
+    pub freed: usize,
+    /// The maximum of `allocated - freed` at any point in time.
+    pub peak: usize,
+}
+
+impl AllocationCounters {
+    fn add(&mut self, size: usize) {
+        self.allocated += size;
+        self.peak = self.peak.max(self.allocated.saturating_sub(self.freed));
+    }
+
+    fn remove(&mut self, size: usize) {
+        self.freed += size;
+    }
+
+    /// Adds the `other` counters, e. g. of another execution of the same
+    /// work. The peak is the maximum of both peaks.
+    pub fn merge(&mut self, other: &AllocationCounters) {
+        self.allocated += other.allocated;
+        self.freed += other.freed;
+        self.peak = self.peak.max(other.peak);
+    }
 }
 
 impl ThreadLocalCounter {
     fn add(&mut self, size: usize) {
+        if let Some(counters) = &mut self.allocation_counters {
+            counters.add(size);
+        }
         if self.buffer >= size {
             self.buffer -= size;
         } else {
@@ -33,6 +68,9 @@ impl ThreadLocalCounter {
     }
 
     fn remove(&mut self, size: usize) {
+        if let Some(counters) = &mut self.allocation_counters {
+            counters.remove(size);
+        }
         self.buffer += size;
         if self.buffer > MAX_BUFFER {
             let offset = self.buffer - TARGET_BUFFER;
@@ -50,19 +88,22 @@ impl ThreadLocalCounter {
 }
 
 thread_local! {
-  static LOCAL_COUNTER: UnsafeCell<ThreadLocalCounter> = UnsafeCell::new(ThreadLocalCounter { buffer: 0 });
+  static LOCAL_COUNTER: UnsafeCell<ThreadLocalCounter> = UnsafeCell::new(ThreadLocalCounter {
+    buffer: 0,
+    allocation_counters: None,
+  });
 }
 
 pub fn get() -> usize {
     ALLOCATED.load(Ordering::Relaxed)
 }
 
-fn with_local_counter(f: impl FnOnce(&mut ThreadLocalCounter)) {
+fn with_local_counter<T>(f: impl FnOnce(&mut ThreadLocalCounter) -> T) -> T {
     LOCAL_COUNTER.with(|local| {
         let ptr = local.get();
         // SAFETY: This is a thread local.
         let mut local = unsafe { NonNull::new_unchecked(ptr) };
-        f(unsafe { local.as_mut() });
+        f(unsafe { local.as_mut() })
     })
 }
 
@@ -82,6 +123,43 @@ pub fn flush() {
     with_local_counter(|local| local.unload());
 }
 
+/// Attributes the allocations of the current thread to `counters` while `f`
+/// runs. The previous counters are restored afterwards, even when `f` panics.
+pub fn with_allocation_counters<T>(counters: &mut AllocationCounters, f: impl FnOnce() -> T) -> T {
+    struct Restore<'a> {
+        counters: &'a mut AllocationCounters,
+        previous: Option<AllocationCounters>,
+    }
+
+    impl Drop for Restore<'_> {
+        fn drop(&mut self) {
+            let current = with_local_counter(|local| {
+                std::mem::replace(&mut local.allocation_counters, self.previous.take())
+            });
+            if let Some(current) = current {
+                *self.counters = current;
+            }
+        }
+    }
+
+    let previous = with_local_counter(|local| local.allocation_counters.replace(*counters));
+    let _restore = Restore { counters, previous };
+    f()
+}
+
+/// Returns the counters that the allocations of the current thread are
+/// attributed to, and resets them. Returns zeros when the allocations are
+/// not attributed.
+pub fn take_allocation_counters() -> AllocationCounters {
+    with_local_counter(|local| {
+        local
+            .allocation_counters
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,4 +190,36 @@ mod tests {
         expected -= MAX_BUFFER + 100;
         assert_eq!(get(), expected);
     }
+
+    #[test]
+    fn attribution() {
+        let mut outer = AllocationCounters::default();
+        let mut inner = AllocationCounters::default();
+        with_allocation_counters(&mut outer, || {
+            add(300);
+            remove(100);
+            with_allocation_counters(&mut inner, || {
+                add(50);
+            });
+            add(200);
+        });
+        // Not attributed anymore
+        add(1000);
+        assert_eq!(
+            outer,
+            AllocationCounters {
+                allocated: 500,
+                freed: 100,
+                peak: 400,
+            }
+        );
+        assert_eq!(inner.allocated, 50);
+
+        with_allocation_counters(&mut outer, || {
+            assert_eq!(take_allocation_counters().allocated, 500);
+            add(10);
+        });
+        assert_eq!(outer.allocated, 10);
+        assert_eq!(outer.peak, 10);
+    }
 }
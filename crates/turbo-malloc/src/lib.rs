@@ -2,7 +2,8 @@ mod counter;
 
 use std::alloc::{GlobalAlloc, Layout};
 
-use self::counter::{add, flush, get, remove};
+pub use self::counter::AllocationCounters;
+use self::counter::{add, flush, get, remove, take_allocation_counters, with_allocation_counters};
 
 /// Turbo's preferred global allocator. This is a new type instead of a type
 /// alias because you can't use type aliases to instantiate unit types (E0423).
@@ -16,6 +17,22 @@ impl TurboMalloc {
     pub fn thread_stop() {
         flush();
     }
+
+    /// Attributes the allocations and deallocations of the current thread to
+    /// `counters` while `f` runs. Nested calls attribute to the innermost
+    /// counters only.
+    pub fn with_allocation_counters<T>(
+        counters: &mut AllocationCounters,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        with_allocation_counters(counters, f)
+    }
+
+    /// Returns and resets the counters of the innermost
+    /// [TurboMalloc::with_allocation_counters] call on the current thread.
+    pub fn take_allocation_counters() -> AllocationCounters {
+        take_allocation_counters()
+    }
 }
 
 #[cfg(all(
@@ -38,7 +55,7 @@ unsafe impl GlobalAlloc for TurboMalloc {
 
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
         let ret = mimalloc::MiMalloc.alloc_zeroed(layout);
-        if ret.is_null() {
+        if !ret.is_null() {
             add(layout.size());
         }
         ret
@@ -78,7 +95,7 @@ unsafe impl GlobalAlloc for TurboMalloc {
 
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
         let ret = std::alloc::System.alloc_zeroed(layout);
-        if ret.is_null() {
+        if !ret.is_null() {
             add(layout.size());
         }
         ret
@@ -0,0 +1,18 @@
+use std::alloc::{GlobalAlloc, Layout};
+
+use turbo_malloc::TurboMalloc;
+
+#[test]
+fn counts_zeroed_allocations() {
+    let layout = Layout::from_size_align(1024 * 1024, 8).unwrap();
+    TurboMalloc::thread_stop();
+    let before = TurboMalloc::memory_usage();
+
+    let ptr = unsafe { TurboMalloc.alloc_zeroed(layout) };
+    assert!(!ptr.is_null());
+    assert!(TurboMalloc::memory_usage() >= before + layout.size());
+
+    unsafe { TurboMalloc.dealloc(ptr, layout) };
+    TurboMalloc::thread_stop();
+    assert_eq!(TurboMalloc::memory_usage(), before);
+}
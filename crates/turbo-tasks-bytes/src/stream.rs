@@ -1,11 +1,12 @@
 use std::{
+    collections::HashMap,
     fmt,
     pin::Pin,
     sync::{Arc, Mutex},
-    task::{Context as TaskContext, Poll},
+    task::{Context as TaskContext, Poll, Waker},
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use futures::{Stream as StreamTrait, StreamExt, TryStreamExt};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -14,6 +15,10 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 /// A Stream implements both a reader (which implements the Stream trait), and a
 /// writer (which can be sent to another thread). As new values are written, any
 /// pending readers will be woken up to receive the new value.
+///
+/// By default, every value is kept so that it can be replayed to readers that
+/// are created later. A bounded stream (see [Stream::new_bounded]) instead
+/// only keeps the values that haven't been read by all of its readers yet.
 #[derive(Clone, Debug)]
 pub struct Stream<T: Clone> {
     inner: Arc<Mutex<StreamState<T>>>,
@@ -23,6 +28,78 @@ pub struct Stream<T: Clone> {
 struct StreamState<T> {
     source: Option<Pin<Box<dyn StreamTrait<Item = T> + Send>>>,
     pulled: Vec<T>,
+    /// The number of values that have been dropped from the start of
+    /// `pulled`. Always 0 for unbounded streams.
+    dropped: usize,
+    bound: Option<Bound>,
+    /// Readers that wait for a value to be pulled or for a bounded stream to
+    /// have space again.
+    wakers: Vec<Waker>,
+    /// All readers of a bounded stream have been dropped before it was
+    /// finished, so the source has been dropped early.
+    cancelled: bool,
+}
+
+/// The state of a bounded stream.
+struct Bound {
+    /// The maximum number of values that are kept, see [Stream::new_bounded].
+    capacity: usize,
+    /// The index of the next value of every registered reader.
+    readers: HashMap<usize, usize>,
+    next_reader_id: usize,
+}
+
+impl<T> StreamState<T> {
+    fn new(pulled: Vec<T>, source: Option<Pin<Box<dyn StreamTrait<Item = T> + Send>>>) -> Self {
+        StreamState {
+            source,
+            pulled,
+            dropped: 0,
+            bound: None,
+            wakers: Vec::new(),
+            cancelled: false,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.source.is_none() && !self.cancelled
+    }
+
+    fn wait(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Moves the reader `id` of a bounded stream to `index`.
+    fn advance(&mut self, id: usize, index: usize) {
+        if let Some(bound) = &mut self.bound {
+            bound.readers.insert(id, index);
+            self.drop_consumed();
+        }
+    }
+
+    /// Drops the values that all readers of a bounded stream have read.
+    fn drop_consumed(&mut self) {
+        let Some(bound) = &self.bound else {
+            return;
+        };
+        let Some(&min) = bound.readers.values().min() else {
+            return;
+        };
+        if min > self.dropped {
+            self.pulled.drain(..min - self.dropped);
+            self.dropped = min;
+            // Readers that were blocked by the capacity can continue
+            self.wake_all();
+        }
+    }
 }
 
 impl<T: Clone> Stream<T> {
@@ -30,10 +107,7 @@ impl<T: Clone> Stream<T> {
     /// values.
     pub fn new_closed(pulled: Vec<T>) -> Self {
         Self {
-            inner: Arc::new(Mutex::new(StreamState {
-                source: None,
-                pulled,
-            })),
+            inner: Arc::new(Mutex::new(StreamState::new(pulled, None))),
         }
     }
 
@@ -43,39 +117,109 @@ impl<T: Clone> Stream<T> {
         source: Box<dyn StreamTrait<Item = T> + Send + 'static>,
     ) -> Self {
         Self {
-            inner: Arc::new(Mutex::new(StreamState {
-                source: Some(Box::into_pin(source)),
+            inner: Arc::new(Mutex::new(StreamState::new(
                 pulled,
-            })),
+                Some(Box::into_pin(source)),
+            ))),
+        }
+    }
+
+    /// Creates a new Stream, which will lazily pull from the source stream,
+    /// but keeps at most `capacity` values in memory.
+    ///
+    /// Values are dropped once all readers have read them, so readers that are
+    /// created later only see the values that are still kept, see
+    /// [Stream::is_replayable]. When a reader is `capacity` values ahead of the
+    /// slowest reader, it waits for it to catch up. When all readers are
+    /// dropped before the stream is finished, the source is dropped too and the
+    /// stream can't be read anymore, see [Stream::try_read].
+    pub fn new_bounded(
+        capacity: usize,
+        source: Box<dyn StreamTrait<Item = T> + Send + 'static>,
+    ) -> Self {
+        assert!(
+            capacity > 0,
+            "a bounded stream needs to keep at least one value"
+        );
+        let mut state = StreamState::new(vec![], Some(Box::into_pin(source)));
+        state.bound = Some(Bound {
+            capacity,
+            readers: HashMap::new(),
+            next_reader_id: 0,
+        });
+        Self {
+            inner: Arc::new(Mutex::new(state)),
         }
     }
 
     /// Returns a [StreamTrait] implementation to poll values out of our Stream.
+    ///
+    /// A reader of a cancelled stream doesn't yield any values, use
+    /// [Stream::try_read] to tell a cancelled stream from an empty one.
     pub fn read(&self) -> StreamRead<T> {
-        StreamRead {
+        self.try_read().unwrap_or_else(|_| StreamRead {
             source: self.clone(),
             index: 0,
+            id: None,
+        })
+    }
+
+    /// Returns a [StreamTrait] implementation to poll values out of our
+    /// Stream, or an error when a bounded stream has been cancelled because
+    /// all of its readers were dropped before it was finished. The remaining
+    /// values of a cancelled stream are lost, so reading it would silently
+    /// return a truncated stream.
+    pub fn try_read(&self) -> Result<StreamRead<T>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.cancelled {
+            bail!(
+                "the stream has been cancelled, as all of its readers were dropped before it was \
+                 finished"
+            );
         }
+        let index = inner.dropped;
+        let id = inner.bound.as_mut().map(|bound| {
+            let id = bound.next_reader_id;
+            bound.next_reader_id += 1;
+            bound.readers.insert(id, index);
+            id
+        });
+        drop(inner);
+        Ok(StreamRead {
+            source: self.clone(),
+            index,
+            id,
+        })
+    }
+
+    /// Returns true when a new reader would read all values of the stream,
+    /// i.e. no values have been dropped by a bounded stream.
+    pub fn is_replayable(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.dropped == 0 && !inner.cancelled
     }
 
-    pub async fn into_single(&self) -> SingleValue<T> {
-        let mut stream = self.read();
+    /// Converts the Stream into a single value when possible. Fails when the
+    /// stream has been cancelled, see [Stream::try_read].
+    pub async fn into_single(&self) -> Result<SingleValue<T>> {
+        let mut stream = self.try_read()?;
         let Some(first) = stream.next().await else {
-            return SingleValue::None;
+            return Ok(SingleValue::None);
         };
 
         if stream.next().await.is_some() {
-            return SingleValue::Multiple;
+            return Ok(SingleValue::Multiple);
         }
 
-        SingleValue::Single(first)
+        Ok(SingleValue::Single(first))
     }
 }
 
-impl<T: Clone, E: Clone> Stream<Result<T, E>> {
-    /// Converts a TryStream into a single value when possible.
+impl<T: Clone, E: Clone + From<anyhow::Error>> Stream<Result<T, E>> {
+    /// Converts a TryStream into a single value when possible. Fails when the
+    /// stream has been cancelled, see [Stream::try_read].
     pub async fn try_into_single(&self) -> Result<SingleValue<T>, E> {
-        let mut stream = self.read();
+        let mut stream = self.try_read()?;
         let Some(first) = stream.try_next().await? else {
             return Ok(SingleValue::None);
         };
@@ -119,19 +263,11 @@ impl<T: Clone + PartialEq> PartialEq for Stream<T> {
             let left = self.inner.lock().unwrap();
             let right = other.inner.lock().unwrap();
 
-            match (&*left, &*right) {
-                (
-                    StreamState {
-                        pulled: a,
-                        source: None,
-                    },
-                    StreamState {
-                        pulled: b,
-                        source: None,
-                    },
-                ) => a == b,
-                _ => false,
-            }
+            left.is_closed()
+                && right.is_closed()
+                && left.dropped == 0
+                && right.dropped == 0
+                && left.pulled == right.pulled
         }
     }
 }
@@ -141,12 +277,14 @@ impl<T: Clone + Serialize> Serialize for Stream<T> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         use serde::ser::Error;
         let lock = self.inner.lock().map_err(Error::custom)?;
-        match &*lock {
-            StreamState {
-                pulled,
-                source: None,
-            } => pulled.serialize(serializer),
-            _ => Err(Error::custom("cannot serialize open stream")),
+        if lock.cancelled {
+            Err(Error::custom("cannot serialize cancelled stream"))
+        } else if lock.source.is_some() {
+            Err(Error::custom("cannot serialize open stream"))
+        } else if lock.dropped > 0 {
+            Err(Error::custom("cannot serialize partially dropped stream"))
+        } else {
+            lock.pulled.serialize(serializer)
         }
     }
 }
@@ -162,6 +300,8 @@ impl<T: Clone + fmt::Debug> fmt::Debug for StreamState<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("StreamState")
             .field("pulled", &self.pulled)
+            .field("dropped", &self.dropped)
+            .field("cancelled", &self.cancelled)
             .finish()
     }
 }
@@ -171,6 +311,8 @@ impl<T: Clone + fmt::Debug> fmt::Debug for StreamState<T> {
 pub struct StreamRead<T: Clone> {
     index: usize,
     source: Stream<T>,
+    /// The id of the reader in a bounded stream.
+    id: Option<usize>,
 }
 
 impl<T: Clone> StreamTrait for StreamRead<T> {
@@ -178,21 +320,40 @@ impl<T: Clone> StreamTrait for StreamRead<T> {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
-        let index = this.index;
         let mut inner = this.source.inner.lock().unwrap();
+        let inner = &mut *inner;
 
-        if let Some(v) = inner.pulled.get(index) {
+        if inner.cancelled {
+            // The remaining values are lost, see [Stream::read].
+            return Poll::Ready(None);
+        }
+
+        if let Some(v) = inner.pulled.get(this.index - inner.dropped) {
             // If the current reader can be satisfied by a value we've already pulled, then
             // just do that.
+            let v = v.clone();
             this.index += 1;
-            return Poll::Ready(Some(v.clone()));
+            if let Some(id) = this.id {
+                inner.advance(id, this.index);
+            }
+            return Poll::Ready(Some(v));
         };
 
-        let Some(source) = &mut inner.source else {
+        if inner.source.is_none() {
             // If the source has been closed, there's nothing left to pull.
             return Poll::Ready(None);
-        };
+        }
+
+        if let Some(bound) = &inner.bound {
+            if inner.pulled.len() >= bound.capacity {
+                // This reader is too far ahead of the slowest reader, which will wake us
+                // when it has caught up.
+                inner.wait(cx.waker());
+                return Poll::Pending;
+            }
+        }
 
+        let source = inner.source.as_mut().unwrap();
         match source.poll_next_unpin(cx) {
             // If the source stream is ready to give us a new value, we can immediately store that
             // and return it to the caller. Any other readers will be able to read the value from
@@ -200,17 +361,159 @@ impl<T: Clone> StreamTrait for StreamRead<T> {
             Poll::Ready(Some(v)) => {
                 this.index += 1;
                 inner.pulled.push(v.clone());
+                // Other readers that are waiting for the source can read the value now
+                inner.wake_all();
+                if let Some(id) = this.id {
+                    inner.advance(id, this.index);
+                }
                 Poll::Ready(Some(v))
             }
             // If the source stream is finished, then we can transition to the closed state
             // to drop the source stream.
             Poll::Ready(None) => {
                 inner.source.take();
+                inner.wake_all();
                 Poll::Ready(None)
             }
             // Else, we need to wait for the source stream to give us a new value. The
-            // source stream will be responsible for waking the TaskContext.
-            Poll::Pending => Poll::Pending,
+            // source stream will be responsible for waking the TaskContext, which then wakes the
+            // other waiting readers.
+            Poll::Pending => {
+                inner.wait(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T: Clone> Drop for StreamRead<T> {
+    fn drop(&mut self) {
+        let Some(id) = self.id else {
+            return;
+        };
+        let mut inner = self.source.inner.lock().unwrap();
+        let Some(bound) = &mut inner.bound else {
+            return;
+        };
+        bound.readers.remove(&id);
+        if !bound.readers.is_empty() {
+            inner.drop_consumed();
+            return;
+        }
+        // Nobody is interested in the remaining values anymore
+        let source = inner.source.take();
+        if source.is_some() {
+            inner.cancelled = true;
+        }
+        inner.wake_all();
+        drop(inner);
+        drop(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{channel::mpsc, future::join, stream, FutureExt, StreamExt};
+    use turbo_tasks::util::SharedError;
+
+    use super::Stream;
+
+    #[tokio::test]
+    async fn replays_to_late_readers() {
+        let stream = Stream::from(stream::iter(vec![1, 2, 3]));
+        assert_eq!(stream.read().collect::<Vec<_>>().await, vec![1, 2, 3]);
+        assert_eq!(stream.read().collect::<Vec<_>>().await, vec![1, 2, 3]);
+        assert!(stream.is_replayable());
+    }
+
+    #[tokio::test]
+    async fn bounded_drops_consumed_values() {
+        let stream = Stream::new_bounded(2, Box::new(stream::iter(1..=5)));
+        let mut first = stream.read();
+        assert_eq!(first.next().await, Some(1));
+        assert_eq!(first.next().await, Some(2));
+        assert!(!stream.is_replayable());
+
+        // Late readers start at the oldest value that is still kept
+        let late = stream.read();
+        let (first, late) = join(first.collect::<Vec<_>>(), late.collect::<Vec<_>>()).await;
+        assert_eq!(first, vec![3, 4, 5]);
+        assert_eq!(late, vec![3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn bounded_applies_backpressure() {
+        let stream = Stream::new_bounded(2, Box::new(stream::iter(1..=4)));
+        let mut fast = stream.read();
+        let mut slow = stream.read();
+        assert_eq!(fast.next().await, Some(1));
+        assert_eq!(fast.next().await, Some(2));
+        // Both values are kept for the slow reader
+        assert_eq!(fast.next().now_or_never(), None);
+
+        assert_eq!(slow.next().await, Some(1));
+        assert_eq!(fast.next().await, Some(3));
+        assert_eq!(slow.collect::<Vec<_>>().await, vec![2, 3, 4]);
+        assert_eq!(fast.collect::<Vec<_>>().await, vec![4]);
+    }
+
+    #[tokio::test]
+    async fn bounded_cancels_source() {
+        let (sender, receiver) = mpsc::unbounded();
+        let stream = Stream::new_bounded(4, Box::new(receiver));
+        let mut first = stream.read();
+        let second = stream.read();
+        sender.unbounded_send(1).unwrap();
+        assert_eq!(first.next().await, Some(1));
+        drop(first);
+        assert!(!sender.is_closed());
+        drop(second);
+        assert!(sender.is_closed());
+        assert_ne!(stream, Stream::new_closed(vec![]));
+        // The values that were not read are lost
+        assert!(stream.try_read().is_err());
+
+        // Unbounded streams keep pulling for later readers
+        let (sender, receiver) = mpsc::unbounded();
+        let stream = Stream::from(receiver);
+        sender.unbounded_send(1).unwrap();
+        assert_eq!(stream.read().next().await, Some(1));
+        assert!(!sender.is_closed());
+    }
+
+    #[tokio::test]
+    async fn reading_cancelled_stream_fails() {
+        let (sender, receiver) = mpsc::unbounded();
+        let stream = Stream::new_bounded(4, Box::new(receiver));
+        let mut first = stream.read();
+        sender.unbounded_send(1).unwrap();
+        sender.unbounded_send(2).unwrap();
+        assert_eq!(first.next().await, Some(1));
+        drop(first);
+
+        assert!(stream.try_read().is_err());
+        assert!(stream.into_single().await.is_err());
+        // Readers don't return the values that are still kept
+        assert_eq!(stream.read().collect::<Vec<_>>().await, Vec::<u32>::new());
+
+        let (_sender, receiver) = mpsc::unbounded::<Result<u32, SharedError>>();
+        let stream = Stream::new_bounded(4, Box::new(receiver));
+        drop(stream.read());
+        assert!(stream.try_into_single().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn wakes_all_waiting_readers() {
+        let (sender, receiver) = mpsc::unbounded();
+        let stream = Stream::from(receiver);
+        let readers = [stream.read(), stream.read()]
+            .map(|reader| tokio::spawn(async move { reader.collect::<Vec<_>>().await }));
+        tokio::task::yield_now().await;
+        sender.unbounded_send(1).unwrap();
+        sender.unbounded_send(2).unwrap();
+        drop(sender);
+        for reader in readers {
+            assert_eq!(reader.await.unwrap(), vec![1, 2]);
         }
     }
 }
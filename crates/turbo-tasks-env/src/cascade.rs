@@ -0,0 +1,427 @@
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{primitives::OptionStringVc, trace::TraceRawVcs, ValueToString};
+use turbo_tasks_fs::{FileContent, FileSystemPathOptionVc, FileSystemPathVc};
+
+use crate::{EnvMapVc, ProcessEnv, ProcessEnvVc};
+
+/// The dotenv files in `dir` for `mode`, in order of precedence, following
+/// https://nextjs.org/docs/basic-features/environment-variables#environment-variable-load-order
+pub fn dotenv_files(dir: FileSystemPathVc, mode: &str) -> Vec<FileSystemPathVc> {
+    let mut files = vec![dir.join(&format!(".env.{mode}.local"))];
+    // Tests should produce the same results for everyone
+    if mode != "test" {
+        files.push(dir.join(".env.local"));
+    }
+    files.push(dir.join(&format!(".env.{mode}")));
+    files.push(dir.join(".env"));
+    files
+}
+
+/// A value of a [DotenvCascade].
+#[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
+pub struct DotenvValue {
+    /// The value with all references expanded.
+    pub value: String,
+    /// The dotenv file that defines the value, or `None` when it comes from
+    /// the prior env.
+    pub origin: Option<FileSystemPathVc>,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct DotenvValues(IndexMap<String, DotenvValue>);
+
+/// Layers the variables of multiple dotenv files on top of a prior env. Each
+/// variable is taken from the prior env or the first file that defines it.
+///
+/// Values that aren't single quoted can reference other variables with
+/// `$VAR`, `${VAR}`, `${VAR-default}` (when `VAR` is not defined) or
+/// `${VAR:-default}` (when `VAR` is not defined or empty). A variable that
+/// references itself sees the value of the files with lower precedence, e.g.
+/// `PATH=${PATH}:./bin`.
+///
+/// [ProcessEnv::read] only changes when the value of the variable changes, so
+/// editing a dotenv file only invalidates the tasks that read the changed
+/// variables.
+#[turbo_tasks::value]
+pub struct DotenvCascade {
+    prior: ProcessEnvVc,
+    files: Vec<FileSystemPathVc>,
+}
+
+#[turbo_tasks::value_impl]
+impl DotenvCascadeVc {
+    /// Layers `files`, in order of precedence, on top of `prior`.
+    #[turbo_tasks::function]
+    pub fn new(prior: ProcessEnvVc, files: Vec<FileSystemPathVc>) -> Self {
+        DotenvCascade { prior, files }.cell()
+    }
+
+    /// Layers the dotenv files of `mode` in `dir` on top of `prior`, see
+    /// [dotenv_files].
+    #[turbo_tasks::function]
+    pub fn for_mode(prior: ProcessEnvVc, dir: FileSystemPathVc, mode: &str) -> Self {
+        Self::new(prior, dotenv_files(dir, mode))
+    }
+
+    /// All variables with their expanded values and origins.
+    #[turbo_tasks::function]
+    pub async fn values(self) -> Result<DotenvValuesVc> {
+        let this = self.await?;
+        let prior = this.prior.read_all().await?;
+        let mut layers = vec![Layer {
+            origin: None,
+            entries: prior
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.clone(),
+                        DotenvEntry {
+                            value: value.clone(),
+                            expand: false,
+                        },
+                    )
+                })
+                .collect(),
+        }];
+        for &path in this.files.iter() {
+            layers.push(Layer {
+                origin: Some(path),
+                entries: parse_dotenv(path).await?.clone_value(),
+            });
+        }
+
+        Ok(DotenvValuesVc::cell(resolve_layers(&layers)))
+    }
+
+    /// The dotenv file that defines the variable `name`, or `None` when it
+    /// comes from the prior env or is not defined.
+    #[turbo_tasks::function]
+    pub async fn origin(self, name: &str) -> Result<FileSystemPathOptionVc> {
+        Ok(FileSystemPathOptionVc::cell(
+            self.values()
+                .await?
+                .get(name)
+                .and_then(|value| value.origin),
+        ))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ProcessEnv for DotenvCascade {
+    #[turbo_tasks::function]
+    async fn read_all(self_vc: DotenvCascadeVc) -> Result<EnvMapVc> {
+        Ok(EnvMapVc::cell(
+            self_vc
+                .values()
+                .await?
+                .iter()
+                .map(|(name, value)| (name.clone(), value.value.clone()))
+                .collect(),
+        ))
+    }
+
+    #[turbo_tasks::function]
+    async fn read(self_vc: DotenvCascadeVc, name: &str) -> Result<OptionStringVc> {
+        let values = self_vc.values().await?;
+        let value = values.get(name).or_else(|| {
+            values
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value)
+        });
+        Ok(OptionStringVc::cell(value.map(|value| value.value.clone())))
+    }
+}
+
+/// A variable defined in a dotenv file, before references are expanded.
+#[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
+struct DotenvEntry {
+    value: String,
+    /// Values of the prior env are taken literally.
+    expand: bool,
+}
+
+#[turbo_tasks::value(transparent)]
+struct DotenvEntries(IndexMap<String, DotenvEntry>);
+
+/// Parses a dotenv file. A missing file defines no variables.
+#[turbo_tasks::function]
+async fn parse_dotenv(path: FileSystemPathVc) -> Result<DotenvEntriesVc> {
+    let FileContent::Content(file) = &*path.read().await? else {
+        return Ok(DotenvEntriesVc::cell(IndexMap::new()));
+    };
+    let content = file.content().to_str()?;
+    match parse(&content) {
+        Ok(entries) => Ok(DotenvEntriesVc::cell(entries)),
+        Err(err) => Err(err).context(format!(
+            "unable to read {} for env vars",
+            path.to_string().await?
+        )),
+    }
+}
+
+/// Marks a `$` that starts a reference. dotenvy would expand references while
+/// parsing, using the process env instead of the layers.
+const REFERENCE: char = '\u{e000}';
+/// Marks a `$` that is taken literally, e.g. in single quotes.
+const LITERAL: char = '\u{e001}';
+
+/// Parses a dotenv file with dotenvy. References are marked with
+/// [REFERENCE] and expanded afterwards, see [Expander].
+fn parse(content: &str) -> Result<IndexMap<String, DotenvEntry>> {
+    let mut entries = IndexMap::new();
+    for entry in dotenvy::from_read_iter(mark_references(content).as_bytes()) {
+        let (name, value) = entry?;
+        entries.insert(
+            name,
+            DotenvEntry {
+                value: value.replace(LITERAL, "$"),
+                expand: true,
+            },
+        );
+    }
+    Ok(entries)
+}
+
+/// Replaces every `$` with [REFERENCE] or [LITERAL], following the quoting
+/// rules of dotenvy. The backslash of an escaped `$` is removed.
+fn mark_references(content: &str) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut quote = None;
+    let mut is_escaped = false;
+    let mut is_comment = false;
+    let mut previous = '\n';
+    for c in content.chars() {
+        if is_comment {
+            is_comment = c != '\n';
+            result.push(c);
+            previous = c;
+            continue;
+        }
+        match c {
+            '$' if quote == Some('\'') => result.push(LITERAL),
+            '$' if is_escaped => {
+                result.pop();
+                result.push(LITERAL);
+            }
+            '$' => result.push(REFERENCE),
+            '#' if quote.is_none() && previous.is_whitespace() => {
+                is_comment = true;
+                result.push(c);
+            }
+            '"' | '\'' if quote.is_none() && !is_escaped => {
+                quote = Some(c);
+                result.push(c);
+            }
+            _ if quote == Some(c) && !is_escaped => {
+                quote = None;
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+        // Single quoted values have no escapes
+        is_escaped = c == '\\' && !is_escaped && quote != Some('\'');
+        previous = c;
+    }
+    result
+}
+
+struct Layer {
+    origin: Option<FileSystemPathVc>,
+    entries: IndexMap<String, DotenvEntry>,
+}
+
+/// Takes every variable from the first layer that defines it and expands its
+/// references.
+fn resolve_layers(layers: &[Layer]) -> IndexMap<String, DotenvValue> {
+    let mut expander = Expander {
+        layers,
+        resolving: Vec::new(),
+    };
+    let mut values = IndexMap::new();
+    for (index, layer) in layers.iter().enumerate() {
+        for name in layer.entries.keys() {
+            if values.contains_key(name) {
+                continue;
+            }
+            let value = expander.resolve(name, index).unwrap_or_default();
+            values.insert(
+                name.clone(),
+                DotenvValue {
+                    value,
+                    origin: layer.origin,
+                },
+            );
+        }
+    }
+    values
+}
+
+struct Expander<'a> {
+    layers: &'a [Layer],
+    /// The variables that are currently being expanded, to break cycles.
+    resolving: Vec<(String, usize)>,
+}
+
+impl<'a> Expander<'a> {
+    /// Expands the variable `name` from the first layer, starting at `from`,
+    /// that defines it.
+    fn resolve(&mut self, name: &str, from: usize) -> Option<String> {
+        let (layer, entry) =
+            self.layers[from..]
+                .iter()
+                .enumerate()
+                .find_map(|(index, layer)| {
+                    layer.entries.get(name).map(|entry| (from + index, entry))
+                })?;
+        if !entry.expand {
+            return Some(entry.value.clone());
+        }
+        if self.resolving.iter().any(|(n, l)| n == name && *l == layer) {
+            return None;
+        }
+        self.resolving.push((name.to_string(), layer));
+        let value = self.expand(&entry.value, name, layer);
+        self.resolving.pop();
+        Some(value)
+    }
+
+    /// Expands the references in `template`, which is the value of the
+    /// variable `name` in `layer`.
+    fn expand(&mut self, template: &str, name: &str, layer: usize) -> String {
+        let mut result = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(index) = rest.find(REFERENCE) {
+            result.push_str(&rest[..index]);
+            rest = &rest[index..];
+            let after = &rest[REFERENCE.len_utf8()..];
+            let (reference, after) = if let Some(braced) = after.strip_prefix('{') {
+                match find_closing_brace(braced) {
+                    Some(end) => (&braced[..end], &braced[end + 1..]),
+                    None => {
+                        result.push('$');
+                        rest = after;
+                        continue;
+                    }
+                }
+            } else {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                if end == 0 {
+                    result.push('$');
+                    rest = after;
+                    continue;
+                }
+                after.split_at(end)
+            };
+            rest = after;
+
+            // The bool is true for `:-`, which also applies to empty values
+            let (reference_name, default) = match reference.find('-') {
+                Some(index) if reference[..index].ends_with(':') => (
+                    &reference[..index - 1],
+                    Some((&reference[index + 1..], true)),
+                ),
+                Some(index) => (&reference[..index], Some((&reference[index + 1..], false))),
+                None => (reference, None),
+            };
+            // A reference to the variable itself refers to the lower layers
+            let from = if reference_name == name { layer + 1 } else { 0 };
+            let value = self.resolve(reference_name, from);
+            let value = match (value, default) {
+                (Some(value), Some((_, true))) if value.is_empty() => None,
+                (value, _) => value,
+            };
+            match (value, default) {
+                (Some(value), _) => result.push_str(&value),
+                (None, Some((default, _))) => result.push_str(&self.expand(default, name, layer)),
+                (None, None) => {}
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+fn find_closing_brace(value: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(index),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::{parse, resolve_layers, DotenvEntry, Layer, REFERENCE};
+
+    fn expand(files: &[&str]) -> IndexMap<String, String> {
+        let layers = files
+            .iter()
+            .map(|content| Layer {
+                origin: None,
+                entries: parse(content).unwrap(),
+            })
+            .collect::<Vec<_>>();
+        resolve_layers(&layers)
+            .into_iter()
+            .map(|(name, value)| (name, value.value))
+            .collect()
+    }
+
+    #[test]
+    fn parses() {
+        let entries = parse(
+            "# it's a comment\nexport A=1\nB = \"two words\" # $A's \
+             comment\nC='${A}'\nD=\"line\\nbreak \\$A\"\nE=\"multi\nline\"\nF=${A}$B\n",
+        )
+        .unwrap();
+        let entry = |value: &str| DotenvEntry {
+            value: value.to_string(),
+            expand: true,
+        };
+        assert_eq!(entries["A"], entry("1"));
+        assert_eq!(entries["B"], entry("two words"));
+        assert_eq!(entries["C"], entry("${A}"));
+        assert_eq!(entries["D"], entry("line\nbreak $A"));
+        assert_eq!(entries["E"], entry("multi\nline"));
+        assert_eq!(
+            entries["F"],
+            entry(&format!("{REFERENCE}{{A}}{REFERENCE}B"))
+        );
+        assert!(parse("A").is_err());
+        assert!(parse("A=\"unterminated").is_err());
+    }
+
+    #[test]
+    fn expands() {
+        let values = expand(&[
+            "URL=http://${HOST}:$PORT/\nPATH_=${PATH_}:local\nLITERAL='$HOST'\nESCAPED=\\$HOST",
+            "HOST=localhost\nPORT=3000\nPATH_=base\nEMPTY=",
+            "A=${MISSING:-fallback}\nB=${EMPTY:-fallback}\nC=${EMPTY-fallback}\nD=${MISSING:\
+             -${HOST}}",
+            "SELF=${SELF}x\nCYCLE_A=${CYCLE_B}a\nCYCLE_B=${CYCLE_A}b",
+        ]);
+        assert_eq!(values["URL"], "http://localhost:3000/");
+        assert_eq!(values["PATH_"], "base:local");
+        assert_eq!(values["LITERAL"], "$HOST");
+        assert_eq!(values["ESCAPED"], "$HOST");
+        assert_eq!(values["A"], "fallback");
+        assert_eq!(values["B"], "fallback");
+        assert_eq!(values["C"], "");
+        assert_eq!(values["D"], "localhost");
+        assert_eq!(values["SELF"], "x");
+        assert_eq!(values["CYCLE_A"], "ba");
+        assert_eq!(values["CYCLE_B"], "ab");
+    }
+}
@@ -1,9 +1,11 @@
 #![feature(min_specialization)]
 
+mod cascade;
 mod command_line;
 mod custom;
 mod dotenv;
 mod filter;
+mod schema;
 
 use std::{env, sync::Mutex};
 
@@ -12,8 +14,12 @@ use indexmap::IndexMap;
 use turbo_tasks::primitives::OptionStringVc;
 
 pub use self::{
-    command_line::CommandLineProcessEnvVc, custom::CustomProcessEnvVc, dotenv::DotenvProcessEnvVc,
+    cascade::{dotenv_files, DotenvCascadeVc, DotenvValue, DotenvValues, DotenvValuesVc},
+    command_line::CommandLineProcessEnvVc,
+    custom::CustomProcessEnvVc,
+    dotenv::DotenvProcessEnvVc,
     filter::FilterProcessEnvVc,
+    schema::{EnvSchema, EnvSchemaVc, EnvVarError, EnvVarSchema, EnvVarType},
 };
 
 #[turbo_tasks::value(transparent)]
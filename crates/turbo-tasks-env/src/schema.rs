@@ -0,0 +1,268 @@
+use std::fmt::{self, Display, Formatter};
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use turbo_tasks::{trace::TraceRawVcs, ValueToString};
+use turbo_tasks_fs::{FileContent, FileSystemPathVc};
+use url::Url;
+
+/// The type that the value of an env variable must have.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, TraceRawVcs, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvVarType {
+    #[default]
+    String,
+    /// A finite decimal number, e.g. `3000` or `-0.5`.
+    Number,
+    /// `true`, `false`, `1` or `0`.
+    Bool,
+    /// An absolute URL.
+    Url,
+    /// One of the `values` of the variable.
+    Enum,
+}
+
+/// Declares a single env variable.
+#[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
+pub struct EnvVarSchema {
+    #[serde(rename = "type", default)]
+    pub ty: EnvVarType,
+    /// The allowed values of an [EnvVarType::Enum] variable.
+    #[serde(default)]
+    pub values: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// The value of the variable when it's not defined. Numbers and booleans
+    /// are converted to strings.
+    #[serde(default, deserialize_with = "deserialize_default")]
+    pub default: Option<String>,
+}
+
+fn deserialize_default<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        String(String),
+        Number(serde_json::Number),
+        Bool(bool),
+    }
+
+    Ok(
+        Option::<Scalar>::deserialize(deserializer)?.map(|value| match value {
+            Scalar::String(value) => value,
+            Scalar::Number(value) => value.to_string(),
+            Scalar::Bool(value) => value.to_string(),
+        }),
+    )
+}
+
+impl EnvVarSchema {
+    /// Checks if `value` has the type of the variable.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self.ty {
+            EnvVarType::String => true,
+            EnvVarType::Number => value.trim().parse::<f64>().map_or(false, f64::is_finite),
+            EnvVarType::Bool => matches!(value, "true" | "false" | "1" | "0"),
+            EnvVarType::Url => Url::parse(value).is_ok(),
+            EnvVarType::Enum => self.values.iter().any(|allowed| allowed == value),
+        }
+    }
+
+    /// Describes the values that are accepted, e.g. `a number`.
+    pub fn expected(&self) -> String {
+        match self.ty {
+            EnvVarType::String => "a string".to_string(),
+            EnvVarType::Number => "a number".to_string(),
+            EnvVarType::Bool => "one of true, false, 1 or 0".to_string(),
+            EnvVarType::Url => "an absolute URL".to_string(),
+            EnvVarType::Enum => format!("one of {}", self.values.join(", ")),
+        }
+    }
+}
+
+/// A problem with the value of an env variable.
+#[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
+pub enum EnvVarError {
+    /// A required variable is not defined and has no default.
+    Missing,
+    /// The value doesn't have the declared type.
+    Invalid { value: String, expected: String },
+}
+
+impl Display for EnvVarError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvVarError::Missing => write!(f, "is required, but not defined"),
+            EnvVarError::Invalid { value, expected } => {
+                write!(f, "must be {expected}, but is \"{value}\"")
+            }
+        }
+    }
+}
+
+/// Declares the env variables of a project, read from a JSON file like
+///
+/// ```json
+/// {
+///   "variables": {
+///     "API_URL": { "type": "url", "required": true },
+///     "PORT": { "type": "number", "default": 3000 },
+///     "LOG_LEVEL": { "type": "enum", "values": ["debug", "info"] }
+///   }
+/// }
+/// ```
+#[turbo_tasks::value]
+#[derive(Debug, Default)]
+pub struct EnvSchema {
+    #[turbo_tasks(trace_ignore)]
+    pub variables: IndexMap<String, EnvVarSchema>,
+}
+
+#[turbo_tasks::value_impl]
+impl EnvSchemaVc {
+    /// Reads the schema from a JSON file. A missing file declares no
+    /// variables.
+    #[turbo_tasks::function]
+    pub async fn read(path: FileSystemPathVc) -> Result<Self> {
+        let path_str = path.to_string().await?;
+        let schema = match &*path.read().await? {
+            FileContent::Content(file) => EnvSchema::parse(&file.content().to_str()?)
+                .with_context(|| format!("unable to parse env schema {}", path_str))?,
+            FileContent::NotFound => EnvSchema::default(),
+        };
+        Ok(schema.cell())
+    }
+}
+
+impl EnvSchema {
+    pub fn parse(content: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct SchemaFile {
+            #[serde(default)]
+            variables: IndexMap<String, EnvVarSchema>,
+        }
+
+        let SchemaFile { variables } = serde_json::from_str(content)?;
+        for (name, variable) in variables.iter() {
+            if variable.ty == EnvVarType::Enum && variable.values.is_empty() {
+                bail!("enum variable {name} has no values");
+            }
+        }
+        Ok(EnvSchema { variables })
+    }
+
+    /// Validates `env` against the declared variables. Returns `env` with the
+    /// defaults of the undefined variables added, and the problems found.
+    /// Variables are looked up ignoring their casing, like in
+    /// [crate::ProcessEnv::read].
+    pub fn validate(
+        &self,
+        env: &IndexMap<String, String>,
+    ) -> (IndexMap<String, String>, Vec<(String, EnvVarError)>) {
+        let mut validated = env.clone();
+        let mut errors = Vec::new();
+        for (name, variable) in self.variables.iter() {
+            let value = env
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value);
+            match (value, &variable.default) {
+                (Some(value), _) => {
+                    if !variable.accepts(value) {
+                        errors.push((
+                            name.clone(),
+                            EnvVarError::Invalid {
+                                value: value.clone(),
+                                expected: variable.expected(),
+                            },
+                        ));
+                    }
+                }
+                (None, Some(default)) => {
+                    validated.insert(name.clone(), default.clone());
+                }
+                (None, None) => {
+                    if variable.required {
+                        errors.push((name.clone(), EnvVarError::Missing));
+                    }
+                }
+            }
+        }
+        (validated, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+
+    use super::{EnvSchema, EnvVarError};
+
+    const SCHEMA: &str = r#"{
+        "variables": {
+            "API_URL": { "type": "url", "required": true },
+            "PORT": { "type": "number", "default": 3000 },
+            "DEBUG": { "type": "bool" },
+            "LOG_LEVEL": { "type": "enum", "values": ["debug", "info"], "default": "info" },
+            "NAME": {}
+        }
+    }"#;
+
+    #[test]
+    fn applies_defaults() {
+        let schema = EnvSchema::parse(SCHEMA).unwrap();
+        let (env, errors) = schema.validate(&indexmap! {
+            "api_url".to_string() => "https://example.com".to_string(),
+            "DEBUG".to_string() => "1".to_string(),
+        });
+        assert_eq!(errors, vec![]);
+        assert_eq!(env["PORT"], "3000");
+        assert_eq!(env["LOG_LEVEL"], "info");
+        assert!(!env.contains_key("NAME"));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let schema = EnvSchema::parse(SCHEMA).unwrap();
+        let (_, errors) = schema.validate(&indexmap! {
+            "PORT".to_string() => "eighty".to_string(),
+            "DEBUG".to_string() => "yes".to_string(),
+            "LOG_LEVEL".to_string() => "trace".to_string(),
+        });
+        assert_eq!(
+            errors,
+            vec![
+                ("API_URL".to_string(), EnvVarError::Missing),
+                (
+                    "PORT".to_string(),
+                    EnvVarError::Invalid {
+                        value: "eighty".to_string(),
+                        expected: "a number".to_string(),
+                    }
+                ),
+                (
+                    "DEBUG".to_string(),
+                    EnvVarError::Invalid {
+                        value: "yes".to_string(),
+                        expected: "one of true, false, 1 or 0".to_string(),
+                    }
+                ),
+                (
+                    "LOG_LEVEL".to_string(),
+                    EnvVarError::Invalid {
+                        value: "trace".to_string(),
+                        expected: "one of debug, info".to_string(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_enums_without_values() {
+        assert!(EnvSchema::parse(r#"{ "variables": { "A": { "type": "enum" } } }"#).is_err());
+    }
+}
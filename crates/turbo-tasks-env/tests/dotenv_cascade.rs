@@ -0,0 +1,126 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use indexmap::indexmap;
+use turbo_tasks::{
+    primitives::{OptionStringVc, StringVc},
+    TurboTasks,
+};
+use turbo_tasks_env::{DotenvCascadeVc, EnvMapVc, ProcessEnv, ProcessEnvVc};
+use turbo_tasks_fs::{memory::MemoryFileSystemVc, FileSystem, FileSystemVc};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static READS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn layers_and_expands() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    turbo_tasks_env::register();
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let fs = tt
+        .run_once(async {
+            MemoryFileSystemVc::new("memory".to_string())
+                .resolve()
+                .await
+        })
+        .await
+        .unwrap();
+    let memory = tt.run_once(async move { fs.await }).await.unwrap();
+    memory
+        .write_file(
+            ".env",
+            "HOST=localhost\nPORT=3000\nURL=http://${HOST}:${PORT}\nGREETING=hello\n",
+        )
+        .unwrap();
+    memory
+        .write_file(".env.development", "PORT=4000\n")
+        .unwrap();
+    memory
+        .write_file(".env.local", "GREETING=\"${GREETING} world\"\n")
+        .unwrap();
+    memory
+        .write_file(
+            ".env.development.local",
+            "NAME=${USER:-anonymous}\nLITERAL='${PORT}'\n",
+        )
+        .unwrap();
+    // Not read in the development mode
+    memory.write_file(".env.test", "PORT=5000\n").unwrap();
+
+    let env = tt
+        .run_once(async move {
+            let prior = EnvMapVc::cell(indexmap! {
+                "HOST".to_string() => "example.com".to_string(),
+            })
+            .as_process_env();
+            let root = FileSystemVc::from(fs).root();
+            DotenvCascadeVc::for_mode(prior, root, "development")
+                .resolve()
+                .await
+        })
+        .await
+        .unwrap();
+
+    let read = |name: &'static str| {
+        tt.run_once(async move {
+            let value = read_counted(env.as_process_env(), name)
+                .strongly_consistent()
+                .await?;
+            Ok(value.clone_value())
+        })
+    };
+    assert_eq!(read("URL").await.unwrap(), "http://example.com:4000");
+    assert_eq!(read("GREETING").await.unwrap(), "hello world");
+    assert_eq!(read("NAME").await.unwrap(), "anonymous");
+    assert_eq!(read("LITERAL").await.unwrap(), "${PORT}");
+    assert_eq!(READS.load(Ordering::SeqCst), 4);
+
+    let origin = |name: &'static str| {
+        tt.run_once(async move {
+            let origin = env.origin(name).strongly_consistent().await?;
+            Ok(match *origin {
+                Some(path) => Some(path.await?.path.clone()),
+                None => None,
+            })
+        })
+    };
+    assert_eq!(
+        origin("PORT").await.unwrap(),
+        Some(".env.development".to_string())
+    );
+    assert_eq!(origin("HOST").await.unwrap(), None);
+
+    // Changing a variable only invalidates the reads of the variables that
+    // depend on it
+    memory
+        .write_file(
+            ".env.development.local",
+            "NAME=${USER:-somebody}\nLITERAL='${PORT}'\n",
+        )
+        .unwrap();
+    assert_eq!(read("URL").await.unwrap(), "http://example.com:4000");
+    assert_eq!(READS.load(Ordering::SeqCst), 4);
+    assert_eq!(read("NAME").await.unwrap(), "somebody");
+    assert_eq!(READS.load(Ordering::SeqCst), 5);
+
+    memory
+        .write_file(".env.development", "PORT=4001\n")
+        .unwrap();
+    assert_eq!(read("URL").await.unwrap(), "http://example.com:4001");
+    assert_eq!(READS.load(Ordering::SeqCst), 6);
+}
+
+#[turbo_tasks::function]
+async fn read_counted(env: ProcessEnvVc, name: &str) -> Result<StringVc> {
+    READS.fetch_add(1, Ordering::SeqCst);
+    let value: OptionStringVc = env.read(name);
+    Ok(StringVc::cell(
+        value.await?.clone_value().unwrap_or_default(),
+    ))
+}
@@ -0,0 +1,227 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use turbo_tasks_hash::Xxh3Hash64Hasher;
+
+/// Seconds since the unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(header: Option<&str>) -> Self {
+        let mut cache_control = CacheControl::default();
+        for directive in header.unwrap_or_default().split(',') {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => cache_control.no_store = true,
+                "no-cache" => cache_control.no_cache = true,
+                "max-age" => cache_control.max_age = value.and_then(|value| value.parse().ok()),
+                _ => {}
+            }
+        }
+        cache_control
+    }
+}
+
+/// A response stored in the [HttpCache].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedResponse {
+    pub url: String,
+    pub status: u16,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    /// When the response has been received or revalidated, see [now].
+    pub stored_at: u64,
+    #[serde(skip)]
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn cache_control(&self) -> CacheControl {
+        CacheControl::parse(self.header("cache-control"))
+    }
+
+    /// Fresh responses can be used without asking the server.
+    pub fn is_fresh(&self, now: u64) -> bool {
+        let cache_control = self.cache_control();
+        !cache_control.no_cache
+            && cache_control.max_age.map_or(false, |max_age| {
+                now < self.stored_at.saturating_add(max_age)
+            })
+    }
+
+    /// The headers of a conditional request, so that the server can respond
+    /// with `304 Not Modified` instead of sending the body again.
+    pub fn revalidation_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = self.header("etag") {
+            headers.push(("If-None-Match", etag.to_string()));
+        }
+        if let Some(last_modified) = self.header("last-modified") {
+            headers.push(("If-Modified-Since", last_modified.to_string()));
+        }
+        headers
+    }
+
+    /// Updates the response with the headers of a `304 Not Modified`
+    /// response.
+    pub fn revalidated(&mut self, headers: Vec<(String, String)>, now: u64) {
+        for (name, value) in headers {
+            match self.headers.iter_mut().find(|(header, _)| *header == name) {
+                Some((_, old_value)) => *old_value = value,
+                None => self.headers.push((name, value)),
+            }
+        }
+        self.stored_at = now;
+    }
+}
+
+/// Stores responses on disk, so that they can be reused across restarts and
+/// without network access.
+pub(crate) struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        HttpCache { dir: dir.into() }
+    }
+
+    /// Returns the paths of the metadata and the body of a cached response.
+    fn paths(&self, url: &str, user_agent: &Option<String>) -> (PathBuf, PathBuf) {
+        let mut hasher = Xxh3Hash64Hasher::new();
+        hasher.write_ref(&url);
+        hasher.write_ref(user_agent);
+        let key = format!("{:016x}", hasher.finish());
+        (
+            self.dir.join(format!("{key}.json")),
+            self.dir.join(format!("{key}.body")),
+        )
+    }
+
+    pub async fn get(&self, url: &str, user_agent: &Option<String>) -> Option<CachedResponse> {
+        let (meta_path, body_path) = self.paths(url, user_agent);
+        let meta = fs::read(meta_path).await.ok()?;
+        let mut response: CachedResponse = serde_json::from_slice(&meta).ok()?;
+        // Guard against hash collisions
+        if response.url != url {
+            return None;
+        }
+        response.body = fs::read(body_path).await.ok()?;
+        Some(response)
+    }
+
+    /// Stores the response, unless its `Cache-Control` header forbids that.
+    pub async fn put(&self, response: &CachedResponse, user_agent: &Option<String>) -> Result<()> {
+        let (meta_path, body_path) = self.paths(&response.url, user_agent);
+        if response.cache_control().no_store {
+            let _ = fs::remove_file(meta_path).await;
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir).await?;
+        // The body is written first, the metadata makes the entry visible
+        write_atomic(&body_path, &response.body).await?;
+        write_atomic(&meta_path, &serde_json::to_vec(response)?).await?;
+        Ok(())
+    }
+}
+
+/// Writes to a temporary file first, so that concurrent readers never see a
+/// partially written file.
+async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temp_path, content).await?;
+    fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheControl, CachedResponse};
+
+    fn response(headers: &[(&str, &str)]) -> CachedResponse {
+        CachedResponse {
+            url: "https://example.com/".to_string(),
+            status: 200,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            stored_at: 1000,
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_cache_control() {
+        assert_eq!(
+            CacheControl::parse(Some("public, Max-Age=\"60\", no-cache")),
+            CacheControl {
+                no_store: false,
+                no_cache: true,
+                max_age: Some(60),
+            }
+        );
+        assert_eq!(CacheControl::parse(None), CacheControl::default());
+    }
+
+    #[test]
+    fn freshness() {
+        let response = response(&[("cache-control", "max-age=60")]);
+        assert!(response.is_fresh(1059));
+        assert!(!response.is_fresh(1060));
+        assert!(!self::response(&[]).is_fresh(1000));
+        assert!(!self::response(&[("cache-control", "max-age=60, no-cache")]).is_fresh(1000));
+    }
+
+    #[test]
+    fn revalidation() {
+        let mut response = response(&[
+            ("etag", "\"abc\""),
+            ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            ("cache-control", "max-age=60"),
+        ]);
+        assert_eq!(
+            response.revalidation_headers(),
+            vec![
+                ("If-None-Match", "\"abc\"".to_string()),
+                (
+                    "If-Modified-Since",
+                    "Wed, 21 Oct 2015 07:28:00 GMT".to_string()
+                ),
+            ]
+        );
+        response.revalidated(
+            vec![("cache-control".to_string(), "max-age=120".to_string())],
+            2000,
+        );
+        assert!(response.is_fresh(2119));
+        assert_eq!(response.headers.len(), 3);
+    }
+}
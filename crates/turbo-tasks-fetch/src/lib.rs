@@ -1,8 +1,12 @@
 #![feature(min_specialization)]
 
+mod cache;
+
 use anyhow::Result;
+use cache::{now, CachedResponse, HttpCache};
+use reqwest::StatusCode;
 use turbo_tasks::primitives::{OptionStringVc, StringVc};
-use turbo_tasks_fs::FileSystemPathVc;
+use turbo_tasks_fs::{FileContent, FileSystemPathVc};
 use turbopack_core::issue::{Issue, IssueSeverityVc, IssueVc};
 
 pub fn register() {
@@ -19,9 +23,21 @@ pub struct FetchResult(Result<HttpResponseVc, FetchErrorVc>);
 #[derive(Debug)]
 pub struct HttpResponse {
     pub status: u16,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
     pub body: HttpResponseBodyVc,
 }
 
+impl HttpResponse {
+    /// Returns the first value of the header `name`.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
 #[turbo_tasks::value(shared)]
 #[derive(Debug)]
 pub struct HttpResponseBody(pub Vec<u8>);
@@ -35,25 +51,113 @@ impl HttpResponseBodyVc {
     }
 }
 
+/// Configures how [fetch_with_options] requests resources.
+#[turbo_tasks::value(shared)]
+#[derive(Debug, Default)]
+pub struct FetchOptions {
+    /// A directory where responses are stored. Stored responses are reused
+    /// while they are fresh according to their `Cache-Control` header and
+    /// revalidated with their `ETag` or `Last-Modified` header otherwise.
+    pub cache_dir: Option<String>,
+    /// Never uses the network. Responses are served from the fixtures or the
+    /// cache, even when they are stale.
+    pub offline: bool,
+    /// Serves URLs starting with a prefix from the files in a directory, e.g.
+    /// to run tests without network access.
+    pub fixtures: Vec<(String, FileSystemPathVc)>,
+}
+
 #[turbo_tasks::function]
-pub async fn fetch(url: StringVc, user_agent: OptionStringVc) -> Result<FetchResultVc> {
+pub fn fetch(url: StringVc, user_agent: OptionStringVc) -> FetchResultVc {
+    fetch_with_options(url, user_agent, FetchOptions::default().cell())
+}
+
+#[turbo_tasks::function]
+pub async fn fetch_with_options(
+    url: StringVc,
+    user_agent: OptionStringVc,
+    options: FetchOptionsVc,
+) -> Result<FetchResultVc> {
     let url = &*url.await?;
     let user_agent = &*user_agent.await?;
+    let options = &*options.await?;
+
+    for (prefix, dir) in options.fixtures.iter() {
+        if let Some(path) = url.strip_prefix(prefix) {
+            return read_fixture(url, path, *dir).await;
+        }
+    }
+
+    let cache = options.cache_dir.as_ref().map(HttpCache::new);
+    let cached = match &cache {
+        Some(cache) => cache.get(url, user_agent).await,
+        None => None,
+    };
+    if let Some(cached) = &cached {
+        if options.offline || cached.is_fresh(now()) {
+            return Ok(cached_response(cached));
+        }
+    }
+    if options.offline {
+        return Ok(FetchResultVc::cell(Err(FetchError {
+            url: StringVc::cell(url.to_owned()),
+            kind: FetchErrorKind::Offline.into(),
+            detail: StringVc::cell("offline mode is enabled".to_string()),
+        }
+        .cell())));
+    }
+
     let client = reqwest::Client::new();
 
     let mut builder = client.get(url);
     if let Some(user_agent) = user_agent {
         builder = builder.header("User-Agent", user_agent);
     }
+    if let Some(cached) = &cached {
+        for (name, value) in cached.revalidation_headers() {
+            builder = builder.header(name, value);
+        }
+    }
 
     let response = builder.send().await.and_then(|r| r.error_for_status());
     match response {
         Ok(response) => {
-            let status = response.status().as_u16();
+            let status = response.status();
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.as_str().to_owned(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            if let (Some(cache), Some(mut cached)) = (&cache, cached) {
+                if status == StatusCode::NOT_MODIFIED {
+                    cached.revalidated(headers, now());
+                    // A failure to update the cache only means that the next
+                    // request revalidates again
+                    let _ = cache.put(&cached, user_agent).await;
+                    return Ok(cached_response(&cached));
+                }
+            }
             let body = response.bytes().await?.to_vec();
+            if let Some(cache) = &cache {
+                let response = CachedResponse {
+                    url: url.to_owned(),
+                    status: status.as_u16(),
+                    headers,
+                    stored_at: now(),
+                    body,
+                };
+                let _ = cache.put(&response, user_agent).await;
+                return Ok(cached_response(&response));
+            }
 
             Ok(FetchResultVc::cell(Ok(HttpResponse {
-                status,
+                status: status.as_u16(),
+                headers,
                 body: HttpResponseBodyVc::cell(HttpResponseBody(body)),
             }
             .cell())))
@@ -65,12 +169,46 @@ pub async fn fetch(url: StringVc, user_agent: OptionStringVc) -> Result<FetchRes
     }
 }
 
+fn cached_response(cached: &CachedResponse) -> FetchResultVc {
+    FetchResultVc::cell(Ok(HttpResponse {
+        status: cached.status,
+        headers: cached.headers.clone(),
+        body: HttpResponseBodyVc::cell(HttpResponseBody(cached.body.clone())),
+    }
+    .cell()))
+}
+
+/// Responds with the file at `path` in the fixtures directory `dir`, or with
+/// a 404 error when it doesn't exist.
+async fn read_fixture(url: &str, path: &str, dir: FileSystemPathVc) -> Result<FetchResultVc> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    Ok(match &*dir.join(path).read().await? {
+        FileContent::Content(file) => FetchResultVc::cell(Ok(HttpResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: HttpResponseBodyVc::cell(HttpResponseBody(
+                file.content().to_bytes()?.into_owned(),
+            )),
+        }
+        .cell())),
+        FileContent::NotFound => FetchResultVc::cell(Err(FetchError {
+            url: StringVc::cell(url.to_owned()),
+            kind: FetchErrorKind::Status(404).into(),
+            detail: StringVc::cell(format!("fixture {} doesn't exist", path)),
+        }
+        .cell())),
+    })
+}
+
 #[derive(Debug)]
 #[turbo_tasks::value(shared)]
 pub enum FetchErrorKind {
     Connect,
     Timeout,
     Status(u16),
+    /// The resource is not cached and the network can't be used in offline
+    /// mode.
+    Offline,
     Other,
 }
 
@@ -169,6 +307,11 @@ impl Issue for FetchIssue {
                 )
             }
             FetchErrorKind::Timeout => format!("Connection timed out when requesting {}", url),
+            FetchErrorKind::Offline => format!(
+                "{} is not cached and can't be requested in offline mode. Request it once while \
+                 online to store it in the cache.",
+                url
+            ),
             FetchErrorKind::Other => format!("There was an issue requesting {}", url),
         }))
     }
@@ -1,7 +1,7 @@
 #![cfg(test)]
 
 use turbo_tasks::primitives::{OptionStringVc, StringVc};
-use turbo_tasks_fetch::{fetch, register, FetchErrorKind};
+use turbo_tasks_fetch::{fetch, fetch_with_options, register, FetchErrorKind, FetchOptions};
 use turbo_tasks_fs::{DiskFileSystemVc, FileSystem, FileSystemPathVc, FileSystemVc};
 use turbo_tasks_testing::{register, run};
 use turbopack_core::issue::{Issue, IssueSeverity};
@@ -141,6 +141,154 @@ async fn errors_on_404() {
     }
 }
 
+#[tokio::test]
+async fn exposes_headers() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.path("/foo.woff");
+            then.status(200)
+                .header("Content-Type", "font/woff")
+                .body("responsebody");
+        });
+
+        let result = &*fetch(StringVc::cell(server.url("/foo.woff")), OptionStringVc::cell(None)).await?;
+        let Ok(response) = result else {
+            panic!()
+        };
+        let response = response.await?;
+        assert_eq!(response.header("content-type"), Some("font/woff"));
+        assert_eq!(response.header("Content-Type"), Some("font/woff"));
+    }
+}
+
+#[tokio::test]
+async fn serves_fixtures() {
+    let fixtures = tempfile::TempDir::new().unwrap();
+    std::fs::write(fixtures.path().join("foo.woff"), "fixture").unwrap();
+    let root = fixtures.path().to_string_lossy().to_string();
+
+    run! {
+        register();
+
+        let dir = FileSystemVc::from(DiskFileSystemVc::new("fixtures".to_owned(), root.clone())).root();
+        let options = FetchOptions {
+            fixtures: vec![("https://fonts.example/".to_owned(), dir)],
+            ..Default::default()
+        }
+        .cell();
+
+        let result = &*fetch_with_options(StringVc::cell("https://fonts.example/foo.woff?v=1".to_owned()), OptionStringVc::cell(None), options).await?;
+        let Ok(response) = result else {
+            panic!()
+        };
+        let response = response.await?;
+        assert_eq!(response.status, 200);
+        assert_eq!(*response.body.to_string().await?, "fixture");
+
+        let result = &*fetch_with_options(StringVc::cell("https://fonts.example/missing.woff".to_owned()), OptionStringVc::cell(None), options).await?;
+        let Err(err_vc) = result else {
+            panic!()
+        };
+        assert_eq!(*err_vc.await?.kind.await?, FetchErrorKind::Status(404));
+    }
+}
+
+#[tokio::test]
+async fn errors_when_offline_and_not_cached() {
+    let cache = tempfile::TempDir::new().unwrap();
+    let cache_dir = cache.path().to_string_lossy().to_string();
+
+    run! {
+        register();
+
+        let url = "https://doesnotexist/foo.woff";
+        let options = FetchOptions {
+            cache_dir: Some(cache_dir.clone()),
+            offline: true,
+            ..Default::default()
+        }
+        .cell();
+        let result = &*fetch_with_options(StringVc::cell(url.to_owned()), OptionStringVc::cell(None), options).await?;
+        let Err(err_vc) = result else {
+            panic!()
+        };
+        assert_eq!(*err_vc.await?.kind.await?, FetchErrorKind::Offline);
+
+        let issue = err_vc.to_issue(IssueSeverity::Error.into(), get_issue_context());
+        assert_eq!(*issue.description().await?, "https://doesnotexist/foo.woff is not cached and can't be requested in offline mode. Request it once while online to store it in the cache.");
+    }
+}
+
+#[tokio::test]
+async fn reuses_cached_responses() {
+    let cache = tempfile::TempDir::new().unwrap();
+    let cache_dir = cache.path().to_string_lossy().to_string();
+    let server = httpmock::MockServer::start();
+    let fresh_mock = server.mock(|when, then| {
+        when.path("/fresh.woff");
+        then.status(200)
+            .header("Cache-Control", "max-age=3600")
+            .body("fresh");
+    });
+    let revalidated_mock = server.mock(|when, then| {
+        when.path("/etag.woff").header("If-None-Match", "\"v1\"");
+        then.status(304);
+    });
+    let etag_mock = server.mock(|when, then| {
+        when.path("/etag.woff").header_missing("If-None-Match");
+        then.status(200).header("ETag", "\"v1\"").body("etag");
+    });
+
+    // Every `run!` starts with empty turbo tasks caches, like a restart
+    for _ in 0..2 {
+        let cache_dir = cache_dir.clone();
+        let server = &server;
+        run! {
+            register();
+
+            let options = FetchOptions {
+                cache_dir: Some(cache_dir.clone()),
+                ..Default::default()
+            }
+            .cell();
+            for (path, body) in [("/fresh.woff", "fresh"), ("/etag.woff", "etag")] {
+                let result = &*fetch_with_options(StringVc::cell(server.url(path)), OptionStringVc::cell(None), options).await?;
+                let Ok(response) = result else {
+                    panic!()
+                };
+                let response = response.await?;
+                assert_eq!(response.status, 200);
+                assert_eq!(*response.body.to_string().await?, body);
+            }
+        }
+    }
+    fresh_mock.assert_hits(1);
+    etag_mock.assert_hits(1);
+    revalidated_mock.assert_hits(1);
+
+    // Stale responses are served in offline mode
+    run! {
+        register();
+
+        let options = FetchOptions {
+            cache_dir: Some(cache_dir.clone()),
+            offline: true,
+            ..Default::default()
+        }
+        .cell();
+        let result = &*fetch_with_options(StringVc::cell(server.url("/etag.woff")), OptionStringVc::cell(None), options).await?;
+        let Ok(response) = result else {
+            panic!()
+        };
+        assert_eq!(*response.await?.body.to_string().await?, "etag");
+    }
+    etag_mock.assert_hits(1);
+    revalidated_mock.assert_hits(1);
+}
+
 fn get_issue_context() -> FileSystemPathVc {
     std::convert::Into::<FileSystemVc>::into(DiskFileSystemVc::new(
         "root".to_owned(),
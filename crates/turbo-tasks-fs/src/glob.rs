@@ -18,8 +18,12 @@ enum GlobPart {
     /// `/`: Matches the path separator
     PathSeparator,
 
-    /// `[abc]`: Matches any char of the list
-    FileChar(Vec<char>),
+    /// `[abc]`, `[a-z]`: Matches a single filename char of the ranges, or any
+    /// other filename char when negated with `[!abc]`
+    FileChar {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
 
     /// `abc`: Matches literal filename
     File(String),
@@ -40,18 +44,58 @@ enum GlobPart {
 // Note: a/**/b does match a/b, so we need some special logic about path
 // separators
 
+/// A glob pattern. A leading `!` negates the pattern, so that it matches all
+/// paths that the rest of the pattern doesn't match.
 #[turbo_tasks::value]
 #[derive(Debug, Clone)]
 pub struct Glob {
     expression: Vec<GlobPart>,
+    negated: bool,
 }
 
 impl Glob {
+    /// Checks if the glob matches the path. A path ending with `/` is matched
+    /// partially, i. e. checks if the glob can match paths inside of that
+    /// directory.
     pub fn execute(&self, path: &str) -> bool {
         let match_partial = path.ends_with('/');
-        self.iter_matches(path, true, match_partial)
+        if self.negated && match_partial {
+            // A directory can contain paths that are not matched by the
+            // pattern, unless the pattern matches everything inside of it
+            return !self.matches_all_inside(path);
+        }
+        let matches = self
+            .iter_matches(path, true, match_partial)
             .next()
-            .is_some()
+            .is_some();
+        if self.negated {
+            !matches
+        } else {
+            matches
+        }
+    }
+
+    /// Checks if the pattern ends with `**` and the pattern before that
+    /// matches the directory `dir` or one of its parents, e.g.
+    /// `**/node_modules` of `**/node_modules/**` for `node_modules/`. The
+    /// pattern matches all paths inside of that directory then.
+    fn matches_all_inside(&self, dir: &str) -> bool {
+        let Some(GlobPart::AnyDirectories) = self.expression.last() else {
+            return false;
+        };
+        let mut len = self.expression.len() - 1;
+        if len > 0 && self.expression[len - 1] == GlobPart::PathSeparator {
+            len -= 1;
+        }
+        let dir = dir.trim_end_matches('/');
+        dir.match_indices('/')
+            .map(|(index, _)| &dir[..index])
+            .chain(std::iter::once(dir))
+            .any(|dir| {
+                let mut matches = self.iter_matches(dir, true, false);
+                matches.len = len;
+                matches.any(|(remainder, _)| remainder.is_empty())
+            })
     }
 
     fn iter_matches<'a>(
@@ -63,6 +107,7 @@ impl Glob {
         GlobMatchesIterator {
             current: path,
             glob: self,
+            len: self.expression.len(),
             match_partial,
             is_path_separator_equivalent: previous_part_is_path_separator_equivalent,
             stack: Vec::new(),
@@ -72,6 +117,11 @@ impl Glob {
 
     pub fn parse(input: &str) -> Result<Glob> {
         let mut current = input;
+        let mut negated = false;
+        while let Some(remainder) = current.strip_prefix('!') {
+            negated = !negated;
+            current = remainder;
+        }
         let mut expression = Vec::new();
 
         while !current.is_empty() {
@@ -81,13 +131,18 @@ impl Glob {
             current = remainder;
         }
 
-        Ok(Glob { expression })
+        Ok(Glob {
+            expression,
+            negated,
+        })
     }
 }
 
 struct GlobMatchesIterator<'a> {
     current: &'a str,
     glob: &'a Glob,
+    /// The number of parts of the glob expression to match.
+    len: usize,
     match_partial: bool,
     is_path_separator_equivalent: bool,
     stack: Vec<GlobPartMatchesIterator<'a>>,
@@ -99,7 +154,7 @@ impl<'a> Iterator for GlobMatchesIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            if let Some(part) = self.glob.expression.get(self.index) {
+            if let Some(part) = self.glob.expression[..self.len].get(self.index) {
                 let iter = if let Some(iter) = self.stack.get_mut(self.index) {
                     iter
                 } else {
@@ -174,8 +229,7 @@ impl GlobPart {
             ('*', Some('*')) => Ok((GlobPart::AnyDirectories, &input[2..])),
             ('*', _) => Ok((GlobPart::AnyFile, &input[1..])),
             ('?', _) => Ok((GlobPart::AnyFileChar, &input[1..])),
-            ('[', Some('[')) => todo!("glob char classes are not implemented yet"),
-            ('[', _) => todo!("glob char sequences are not implemented yet"),
+            ('[', _) => GlobPart::parse_file_char(&input[1..]),
             ('{', Some(_)) => {
                 let mut current = &input[1..];
                 let mut alternatives = Vec::new();
@@ -189,12 +243,14 @@ impl GlobPart {
                         Some(',') => {
                             alternatives.push(Glob {
                                 expression: take(&mut expression),
+                                negated: false,
                             });
                             current = &current[1..];
                         }
                         Some('}') => {
                             alternatives.push(Glob {
                                 expression: take(&mut expression),
+                                negated: false,
                             });
                             current = &current[1..];
                             break;
@@ -220,6 +276,8 @@ impl GlobPart {
                         is_escaped = false;
                     } else if c == '\\' {
                         is_escaped = true;
+                        index += 1;
+                        continue;
                     } else if c == '/'
                         || c == '*'
                         || c == '?'
@@ -230,12 +288,55 @@ impl GlobPart {
                         break;
                     }
                     literal.push(c);
-                    index += 1;
+                    index += c.len_utf8();
                 }
                 Ok((GlobPart::File(literal), &input[index..]))
             }
         }
     }
+
+    /// Parses a `[...]` set of chars, `input` starts after the `[`.
+    fn parse_file_char(input: &str) -> Result<(GlobPart, &str)> {
+        if input.starts_with("[:") {
+            bail!("glob char classes are not implemented yet");
+        }
+        let (negated, mut current) = match input.strip_prefix(['!', '^']) {
+            Some(remainder) => (true, remainder),
+            None => (false, input),
+        };
+        let mut ranges = Vec::new();
+        loop {
+            let mut chars = current.chars();
+            let start = match chars.next() {
+                None => bail!("Unterminated glob char set"),
+                // A `]` at the start is a literal
+                Some(']') if !ranges.is_empty() => {
+                    return Ok((GlobPart::FileChar { ranges, negated }, chars.as_str()));
+                }
+                Some('\\') => chars.next().context("Unterminated glob char set")?,
+                Some(c) => c,
+            };
+            current = chars.as_str();
+            let range_end = current
+                .strip_prefix('-')
+                .filter(|remainder| !remainder.is_empty() && !remainder.starts_with(']'));
+            let Some(range_end) = range_end else {
+                ranges.push((start, start));
+                continue;
+            };
+            let mut chars = range_end.chars();
+            let end = match chars.next() {
+                Some('\\') => chars.next().context("Unterminated glob char set")?,
+                Some(c) => c,
+                None => unreachable!(),
+            };
+            if end < start {
+                bail!("Invalid glob char range {start}-{end}");
+            }
+            ranges.push((start, end));
+            current = chars.as_str();
+        }
+    }
 }
 
 struct GlobPartMatchesIterator<'a> {
@@ -290,7 +391,16 @@ impl<'a> Iterator for GlobPartMatchesIterator<'a> {
                     None
                 }
             }
-            GlobPart::AnyFileChar => todo!(),
+            GlobPart::AnyFileChar => {
+                if self.index > 0 {
+                    return None;
+                }
+                self.index = 1;
+                match self.path.chars().next() {
+                    Some(c) if c != '/' => Some((&self.path[c.len_utf8()..], false)),
+                    _ => None,
+                }
+            }
             GlobPart::PathSeparator => {
                 if self.index == 0 {
                     self.index = 1;
@@ -305,16 +415,21 @@ impl<'a> Iterator for GlobPartMatchesIterator<'a> {
                     None
                 }
             }
-            GlobPart::FileChar(chars) => loop {
-                if let Some(c) = chars.get(self.index) {
-                    self.index += 1;
-                    if self.path.starts_with(*c) {
-                        return Some((&self.path[1..], false));
-                    }
-                } else {
+            GlobPart::FileChar { ranges, negated } => {
+                if self.index > 0 {
                     return None;
                 }
-            },
+                self.index = 1;
+                let c = self.path.chars().next()?;
+                let in_ranges = ranges
+                    .iter()
+                    .any(|(start, end)| (*start..=*end).contains(&c));
+                if c == '/' || in_ranges == *negated {
+                    None
+                } else {
+                    Some((&self.path[c.len_utf8()..], false))
+                }
+            }
             GlobPart::File(name) => {
                 if self.index == 0 && self.path.starts_with(name) {
                     self.index += 1;
@@ -409,6 +524,14 @@ mod tests {
     #[case::alternatives_nested2("{a,b/c,d/e/{f,g/h}}", "b/c")]
     #[case::alternatives_nested3("{a,b/c,d/e/{f,g/h}}", "d/e/f")]
     #[case::alternatives_nested4("{a,b/c,d/e/{f,g/h}}", "d/e/g/h")]
+    #[case::any_char("file.?s", "file.js")]
+    #[case::char_set("file.[jt]s", "file.ts")]
+    #[case::char_range("[a-c].js", "b.js")]
+    #[case::char_set_negated("[!a]?.js", "bc.js")]
+    #[case::escaped("\\{a\\}.js", "{a}.js")]
+    #[case::negated("!**/*.js", "dir/file.ts")]
+    #[case::negated_partial("!**/node_modules/**", "src/")]
+    #[case::negated_partial_other_suffix("!**/node_modules/*.js", "node_modules/")]
     fn glob_match(#[case] glob: &str, #[case] path: &str) {
         let glob = Glob::parse(glob).unwrap();
 
@@ -416,4 +539,24 @@ mod tests {
 
         assert!(glob.execute(path));
     }
+
+    #[rstest]
+    #[case::file("file.js", "file.ts")]
+    #[case::any_char("?.js", "ab.js")]
+    #[case::any_char_separator("dir?file.js", "dir/file.js")]
+    #[case::char_set("file.[jt]s", "file.cs")]
+    #[case::char_set_negated("file.[!j]s", "file.js")]
+    #[case::negated("!**/*.js", "dir/file.js")]
+    #[case::negated_twice("!!**/*.js", "dir/file.ts")]
+    #[case::negated_partial("!**/node_modules/**", "node_modules/")]
+    #[case::negated_partial_nested("!**/node_modules/**", "apps/app/node_modules/")]
+    #[case::negated_partial_inside("!**/node_modules/**", "node_modules/next/")]
+    #[case::negated_partial_dir("!dist/**", "dist/")]
+    fn glob_not_match(#[case] glob: &str, #[case] path: &str) {
+        let glob = Glob::parse(glob).unwrap();
+
+        println!("{glob:?} {path}");
+
+        assert!(!glob.execute(path));
+    }
 }
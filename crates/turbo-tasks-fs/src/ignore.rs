@@ -0,0 +1,185 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::trace::TraceRawVcs;
+
+use crate::{glob::Glob, FileContent, FileSystemEntryType, FileSystemPathVc};
+
+/// The ignore files that are read in every directory, in order of
+/// precedence.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
+struct IgnoreRule {
+    /// The directory of the ignore file, patterns are relative to it.
+    base: String,
+    glob: Glob,
+    /// `!pattern`: Includes paths again that have been ignored by previous
+    /// rules
+    negated: bool,
+    /// `pattern/`: Only matches directories
+    dir_only: bool,
+}
+
+impl IgnoreRule {
+    fn parse(base: &str, line: &str) -> Option<IgnoreRule> {
+        let mut line = line.strip_suffix('\r').unwrap_or(line);
+        // Trailing spaces are ignored unless they are escaped
+        while line.ends_with(' ') && !line.ends_with("\\ ") {
+            line = &line[..line.len() - 1];
+        }
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        // A separator at the start or in the middle anchors the pattern to the
+        // directory of the ignore file, otherwise it matches at any depth.
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        if pattern.is_empty() {
+            return None;
+        }
+        let mut glob = String::new();
+        if !anchored {
+            glob.push_str("**/");
+        }
+        // Braces have no special meaning in ignore files
+        let mut is_escaped = false;
+        for c in pattern.chars() {
+            if !is_escaped && c == '{' {
+                glob.push('\\');
+            }
+            is_escaped = !is_escaped && c == '\\';
+            glob.push(c);
+        }
+        Some(IgnoreRule {
+            base: base.to_string(),
+            glob: Glob::parse(&glob).ok()?,
+            negated,
+            dir_only,
+        })
+    }
+}
+
+/// The rules of all ignore files that apply to a directory. Later rules take
+/// precedence over earlier ones.
+#[turbo_tasks::value]
+#[derive(Debug, Default)]
+pub(crate) struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    fn extend_from_file(&mut self, base: &str, content: &str) {
+        self.rules.extend(
+            content
+                .lines()
+                .filter_map(|line| IgnoreRule::parse(base, line)),
+        );
+    }
+
+    /// Checks if `path`, relative to the root of the filesystem, is ignored.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        for rule in self.rules.iter().rev() {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let relative_path = if rule.base.is_empty() {
+                path
+            } else if let Some(relative_path) = path
+                .strip_prefix(&rule.base)
+                .and_then(|path| path.strip_prefix('/'))
+            {
+                relative_path
+            } else {
+                continue;
+            };
+            if rule.glob.execute(relative_path) {
+                return !rule.negated;
+            }
+        }
+        false
+    }
+}
+
+/// Reads the rules of the ignore files in `directory` and its parents, up to
+/// the root of the repository that contains it.
+#[turbo_tasks::function]
+pub(crate) async fn ignore_rules(directory: FileSystemPathVc) -> Result<IgnoreRulesVc> {
+    let dir = directory.await?;
+    let mut rules = IgnoreRules::default();
+    // Ignore files outside of the repository don't apply to it
+    let is_repository_root = !matches!(
+        &*directory.join(".git").get_type().await?,
+        FileSystemEntryType::NotFound
+    );
+    if !dir.is_root() && !is_repository_root {
+        rules.rules.extend(
+            ignore_rules(directory.parent())
+                .await?
+                .rules
+                .iter()
+                .cloned(),
+        );
+    }
+    for name in IGNORE_FILES {
+        if let FileContent::Content(file) = &*directory.join(name).read().await? {
+            if let Ok(content) = file.content().to_str() {
+                rules.extend_from_file(&dir.path, &content);
+            }
+        }
+    }
+    Ok(rules.cell())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::IgnoreRules;
+
+    fn rules(files: &[(&str, &str)]) -> IgnoreRules {
+        let mut rules = IgnoreRules::default();
+        for (base, content) in files {
+            rules.extend_from_file(base, content);
+        }
+        rules
+    }
+
+    #[rstest]
+    #[case::name("node_modules", "node_modules", true)]
+    #[case::nested_name("node_modules", "a/b/node_modules", true)]
+    #[case::extension("*.log", "dir/debug.log", true)]
+    #[case::anchored("/build", "build", true)]
+    #[case::anchored_nested("/build", "src/build", false)]
+    #[case::path("src/generated", "src/generated", true)]
+    #[case::path_nested("src/generated", "lib/src/generated", false)]
+    #[case::dir_only("out/", "out", true)]
+    #[case::dir_only_file("out/", "dir/out.txt", false)]
+    #[case::comment("# comment", "# comment", false)]
+    #[case::escaped_hash("\\#file", "#file", true)]
+    #[case::negated("*.log\n!keep.log", "keep.log", false)]
+    #[case::negated_other("*.log\n!keep.log", "other.log", true)]
+    #[case::braces("{a,b}.txt", "{a,b}.txt", true)]
+    fn is_ignored(#[case] content: &str, #[case] path: &str, #[case] expected: bool) {
+        let rules = rules(&[("", content)]);
+        let is_dir = !path.contains('.');
+        assert_eq!(rules.is_ignored(path, is_dir), expected);
+    }
+
+    #[test]
+    fn hierarchy() {
+        let rules = rules(&[("", "*.log\n/dist"), ("packages/a", "!debug.log\ndist")]);
+        assert!(rules.is_ignored("packages/b/debug.log", false));
+        assert!(!rules.is_ignored("packages/a/debug.log", false));
+        assert!(!rules.is_ignored("packages/dist", true));
+        assert!(rules.is_ignored("packages/a/src/dist", true));
+        assert!(!rules.is_ignored("packages/ab/dist", true));
+    }
+}
@@ -9,15 +9,20 @@
 pub mod attach;
 pub mod embed;
 pub mod glob;
+mod ignore;
 mod invalidation;
 mod invalidator_map;
 pub mod json;
+pub mod memory;
 mod mutex_map;
+pub mod overlay;
+mod poll_watcher;
 mod read_glob;
 mod retry;
 pub mod rope;
 pub mod source_context;
 pub mod util;
+mod write_batch;
 
 use std::{
     borrow::Cow,
@@ -29,7 +34,7 @@ use std::{
     mem::take,
     path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::{
-        mpsc::{channel, RecvError, TryRecvError},
+        mpsc::{channel, RecvError, Sender, TryRecvError},
         Arc, Mutex,
     },
     time::Duration,
@@ -44,7 +49,7 @@ use invalidator_map::InvalidatorMap;
 use jsonc_parser::{parse_to_serde_value, ParseOptions};
 use mime::Mime;
 use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
-use read_glob::read_glob;
+use read_glob::{read_glob, read_glob_with_ignore_files};
 pub use read_glob::{ReadGlobResult, ReadGlobResultVc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -54,15 +59,18 @@ use tokio::{
 };
 use turbo_tasks::{
     mark_stateful,
-    primitives::{BoolVc, StringReadRef, StringVc},
+    primitives::{BoolVc, OptionStringVc, StringReadRef, StringVc},
     spawn_thread,
     trace::TraceRawVcs,
-    CompletionVc, InvalidationReason, Invalidator, ValueToString, ValueToStringVc,
+    turbo_tasks, CompletionVc, InvalidationReason, Invalidator, ValueToString, ValueToStringVc,
 };
-use turbo_tasks_hash::hash_xxh3_hash64;
+use turbo_tasks_hash::{hash_xxh3_hash64, SriAlgorithm, SriHasher};
 use util::{extract_disk_access, join_path, normalize_path, sys_to_unix, unix_to_sys};
+pub use write_batch::{WriteBatch, WriteBatchVc};
 
-use self::{invalidation::WatchStart, json::UnparseableJson, mutex_map::MutexMap};
+use self::{
+    invalidation::WatchStart, json::UnparseableJson, mutex_map::MutexMap, poll_watcher::PollWatcher,
+};
 use crate::{
     attach::AttachedFileSystemVc,
     invalidation::WatchChange,
@@ -85,13 +93,116 @@ pub trait FileSystem: ValueToString {
     fn metadata(&self, fs_path: FileSystemPathVc) -> FileMetaVc;
 }
 
+/// The interval at which [WatchMode::Hybrid] polls after falling back.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Selects how a [DiskFileSystem] detects changes on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchMode {
+    /// Uses the native API of the OS and fails when a path can't be watched.
+    Native,
+    /// Compares the metadata of the watched files every `interval`. Works where
+    /// the native API doesn't, e.g. on network drives or in some containers.
+    Polling { interval: Duration },
+    /// Uses the native API and switches to polling every `poll_interval` when
+    /// that fails, e.g. because the inotify watch limit has been reached.
+    Hybrid { poll_interval: Duration },
+}
+
+impl Default for WatchMode {
+    fn default() -> Self {
+        WatchMode::Hybrid {
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+}
+
+enum WatcherBackend {
+    Native(RecommendedWatcher),
+    Polling(PollWatcher),
+    /// Fails to watch `path` with the OS error `errno` and ignores all other
+    /// paths, to test the handling of native watch errors.
+    #[cfg(test)]
+    Failing {
+        path: PathBuf,
+        errno: i32,
+    },
+}
+
+impl WatcherBackend {
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> notify::Result<()> {
+        match self {
+            WatcherBackend::Native(watcher) => watcher.watch(path, recursive_mode),
+            WatcherBackend::Polling(watcher) => watcher
+                .watch(path, recursive_mode)
+                .map_err(notify::Error::Io),
+            #[cfg(test)]
+            WatcherBackend::Failing {
+                path: failing_path,
+                errno,
+            } => {
+                if path == failing_path {
+                    Err(notify::Error::Io(io::Error::from_raw_os_error(*errno)))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Watches `dir_path`, or its closest parent that can be watched when
+    /// `dir_path` doesn't exist. Other errors, e.g. when the inotify watch
+    /// limit has been reached, are returned, as watching a parent wouldn't
+    /// report changes in `dir_path`.
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    fn watch_dir(&mut self, dir_path: &Path, root_path: &Path) -> Result<()> {
+        let mut path = dir_path;
+        while let Err(err) = self.watch(path, RecursiveMode::NonRecursive) {
+            let not_found = match &err {
+                notify::Error::PathNotFound => true,
+                notify::Error::Io(err) => err.kind() == ErrorKind::NotFound,
+                _ => false,
+            };
+            if !not_found {
+                return Err(err).context(format!("Unable to watch {}", path.display()));
+            }
+            if path == root_path {
+                return Err(err).context(format!(
+                    "Unable to watch {} (tried up to {})",
+                    dir_path.display(),
+                    path.display()
+                ));
+            }
+            let Some(parent_path) = path.parent() else {
+                return Err(err).context(format!("Unable to watch {} (tried up to {})", dir_path.display(), path.display()));
+            };
+            path = parent_path;
+        }
+        Ok(())
+    }
+}
+
+struct ActiveWatcher {
+    backend: WatcherBackend,
+    mode: WatchMode,
+    /// Delivers the events of a polling watcher when falling back.
+    tx: Sender<DebouncedEvent>,
+}
+
+#[derive(Default)]
+struct WatchFallback {
+    reason: Option<String>,
+    invalidators: HashSet<Invalidator>,
+}
+
 #[derive(Default)]
 struct DiskWatcher {
-    watcher: Mutex<Option<RecommendedWatcher>>,
+    watcher: Mutex<Option<ActiveWatcher>>,
     /// Keeps track of which directories are currently watched. This is only
     /// used on a OS that doesn't support recursive watching.
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     watching: dashmap::DashSet<PathBuf>,
+    fallback: Mutex<WatchFallback>,
 }
 
 impl DiskWatcher {
@@ -116,31 +227,79 @@ impl DiskWatcher {
         Ok(())
     }
 
+    /// Watches the directories below `dir_path` again after they have been
+    /// replaced, as native watches follow the replaced directories.
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    fn restore_watching_below(&self, dir_path: &Path, root_path: &Path) {
+        let paths = self
+            .watching
+            .iter()
+            .filter(|path| path.starts_with(dir_path))
+            .map(|path| path.clone())
+            .collect::<Vec<_>>();
+        let mut watcher = self.watcher.lock().unwrap();
+        for path in paths {
+            let _ = self.start_watching(&mut watcher, &path, root_path);
+        }
+    }
+
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     fn start_watching(
         &self,
-        watcher: &mut std::sync::MutexGuard<Option<RecommendedWatcher>>,
+        watcher: &mut std::sync::MutexGuard<Option<ActiveWatcher>>,
         dir_path: &Path,
         root_path: &Path,
     ) -> Result<()> {
         if let Some(watcher) = watcher.as_mut() {
-            let mut path = dir_path;
-            while let Err(err) = watcher.watch(path, RecursiveMode::NonRecursive) {
-                if path == root_path {
-                    return Err(err).context(format!(
-                        "Unable to watch {} (tried up to {})",
-                        dir_path.display(),
-                        path.display()
-                    ));
+            if let Err(err) = watcher.backend.watch_dir(dir_path, root_path) {
+                if matches!(watcher.backend, WatcherBackend::Polling(_)) {
+                    return Err(err);
                 }
-                let Some(parent_path) = path.parent() else {
-                    return Err(err).context(format!("Unable to watch {} (tried up to {})", dir_path.display(), path.display()));
-                };
-                path = parent_path;
+                watcher.backend =
+                    self.fall_back_to_polling(watcher.mode, &watcher.tx, root_path, err)?;
             }
         }
         Ok(())
     }
+
+    /// Adds all paths that need to be watched to `backend`.
+    fn watch_all(&self, backend: &mut WatcherBackend, root_path: &Path) -> Result<()> {
+        // All files and directories at the root and below will be monitored for
+        // changes.
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
+        backend.watch(root_path, RecursiveMode::Recursive)?;
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        for dir_path in self.watching.iter() {
+            backend.watch_dir(&dir_path, root_path)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a polling watcher to replace a native watcher that failed with
+    /// `err`. Only [WatchMode::Hybrid] falls back, other modes return the
+    /// error. The polling watcher delivers its events to the same channel, so
+    /// invalidation works the same way.
+    fn fall_back_to_polling(
+        &self,
+        mode: WatchMode,
+        tx: &Sender<DebouncedEvent>,
+        root_path: &Path,
+        err: anyhow::Error,
+    ) -> Result<WatcherBackend> {
+        let WatchMode::Hybrid { poll_interval } = mode else {
+            return Err(err);
+        };
+        let mut backend = WatcherBackend::Polling(PollWatcher::new(tx.clone(), poll_interval)?);
+        // The native watcher keeps reporting changes until the polling watcher
+        // has taken its snapshots.
+        self.watch_all(&mut backend, root_path)?;
+        let mut fallback = self.fallback.lock().unwrap();
+        fallback.reason = Some(format!("{:#}", err));
+        for invalidator in take(&mut fallback.invalidators) {
+            invalidator.invalidate();
+        }
+        Ok(backend)
+    }
 }
 
 #[turbo_tasks::value(cell = "new", eq = "manual")]
@@ -211,14 +370,18 @@ impl DiskFileSystem {
     }
 
     pub fn start_watching(&self) -> Result<()> {
-        self.start_watching_internal(false)
+        self.start_watching_with_mode(WatchMode::default(), false)
     }
 
     pub fn start_watching_with_invalidation_reason(&self) -> Result<()> {
-        self.start_watching_internal(true)
+        self.start_watching_with_mode(WatchMode::default(), true)
     }
 
-    fn start_watching_internal(&self, report_invalidation_reason: bool) -> Result<()> {
+    pub fn start_watching_with_mode(
+        &self,
+        mode: WatchMode,
+        report_invalidation_reason: bool,
+    ) -> Result<()> {
         let mut watcher_guard = self.watcher.watcher.lock().unwrap();
         if watcher_guard.is_some() {
             return Ok(());
@@ -234,16 +397,29 @@ impl DiskFileSystem {
         // Create a channel to receive the events.
         let (tx, rx) = channel();
         // Create a watcher object, delivering debounced events.
-        // The notification back-end is selected based on the platform.
-        let mut watcher = watcher(tx, Duration::from_millis(1))?;
-        // Add a path to be watched. All files and directories at that path and
-        // below will be monitored for changes.
-        #[cfg(any(target_os = "macos", target_os = "windows"))]
-        watcher.watch(&root_path, RecursiveMode::Recursive)?;
-        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-        for dir_path in self.watcher.watching.iter() {
-            watcher.watch(&*dir_path, RecursiveMode::NonRecursive)?;
-        }
+        let backend = match mode {
+            WatchMode::Polling { interval } => {
+                let mut backend = WatcherBackend::Polling(PollWatcher::new(tx.clone(), interval)?);
+                self.watcher.watch_all(&mut backend, &root_path)?;
+                backend
+            }
+            WatchMode::Native | WatchMode::Hybrid { .. } => {
+                // The notification back-end is selected based on the platform.
+                let native = watcher(tx.clone(), Duration::from_millis(1))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|watcher| {
+                        let mut backend = WatcherBackend::Native(watcher);
+                        self.watcher.watch_all(&mut backend, &root_path)?;
+                        Ok(backend)
+                    });
+                match native {
+                    Ok(backend) => backend,
+                    Err(err) => self
+                        .watcher
+                        .fall_back_to_polling(mode, &tx, &root_path, err)?,
+                }
+            }
+        };
 
         // We need to invalidate all reads that happened before watching
         // Best is to start_watching before starting to read
@@ -270,7 +446,7 @@ impl DiskFileSystem {
             });
         }
 
-        watcher_guard.replace(watcher);
+        watcher_guard.replace(ActiveWatcher { backend, mode, tx });
         drop(watcher_guard);
 
         #[cfg(not(any(target_os = "macos", target_os = "windows")))]
@@ -495,6 +671,17 @@ impl DiskFileSystemVc {
 
         Ok(Self::cell(instance))
     }
+
+    /// Returns why watching has switched to polling, e.g. because the inotify
+    /// watch limit has been reached. That should be reported to the user, as
+    /// polling is slower and uses more CPU.
+    #[turbo_tasks::function]
+    pub async fn watch_fallback(self) -> Result<OptionStringVc> {
+        let this = self.await?;
+        let mut fallback = this.watcher.fallback.lock().unwrap();
+        fallback.invalidators.insert(turbo_tasks::get_invalidator());
+        Ok(OptionStringVc::cell(fallback.reason.clone()))
+    }
 }
 
 impl Debug for DiskFileSystem {
@@ -510,6 +697,19 @@ impl FileSystem for DiskFileSystem {
         let full_path = self.to_sys_path(fs_path).await?;
         self.register_invalidator(&full_path)?;
 
+        // Recordings identify files by the name of the file system, so they can
+        // be replayed with a different root
+        let tt = turbo_tasks();
+        let recorded_path = format!("{}/{}", self.name, fs_path.await?.path);
+        if let Some(content) = tt.replayed_file_read(&recorded_path) {
+            tt.record_file_read(&recorded_path, content.clone());
+            return Ok(match content {
+                Some(content) => FileContent::new(File::from_bytes(content)),
+                None => FileContent::NotFound,
+            }
+            .cell());
+        }
+
         let _lock = self.mutex_map.lock(full_path.clone()).await;
         let content = match retry_future(|| File::from_path(full_path.clone())).await {
             Ok(file) => FileContent::new(file),
@@ -518,6 +718,13 @@ impl FileSystem for DiskFileSystem {
                 bail!(anyhow!(e).context(format!("reading file {}", full_path.display())))
             }
         };
+        if tt.is_recording() {
+            let recorded = match &content {
+                FileContent::Content(file) => Some(file.content().to_bytes()?.into_owned()),
+                FileContent::NotFound => None,
+            };
+            tt.record_file_read(&recorded_path, recorded);
+        }
         Ok(content.cell())
     }
 
@@ -1060,6 +1267,17 @@ impl FileSystemPathVc {
         read_glob(self, glob, include_dot_files)
     }
 
+    /// Like [FileSystemPathVc::read_glob], but skips paths that are excluded
+    /// by `.gitignore` and `.ignore` files.
+    #[turbo_tasks::function]
+    pub async fn read_glob_with_ignore_files(
+        self,
+        glob: GlobVc,
+        include_dot_files: bool,
+    ) -> ReadGlobResultVc {
+        read_glob_with_ignore_files(self, glob, include_dot_files)
+    }
+
     #[turbo_tasks::function]
     pub fn root(self) -> Self {
         self.fs().root()
@@ -1763,6 +1981,20 @@ impl FileContentVc {
         let this = self.await?;
         Ok(this.lines().into())
     }
+
+    /// Returns the subresource integrity metadata (`sha384-...`) of the
+    /// content, or `None` when the file doesn't exist.
+    #[turbo_tasks::function]
+    pub async fn integrity(self) -> Result<OptionStringVc> {
+        Ok(OptionStringVc::cell(match &*self.await? {
+            FileContent::Content(file) => {
+                let mut hasher = SriHasher::new(SriAlgorithm::Sha384);
+                file.content().hash_content(&mut hasher);
+                Some(hasher.integrity())
+            }
+            FileContent::NotFound => None,
+        }))
+    }
 }
 
 /// A file's content interpreted as a JSON value.
@@ -1957,3 +2189,76 @@ pub fn register() {
     turbo_tasks::register();
     include!(concat!(env!("OUT_DIR"), "/register.rs"));
 }
+
+#[cfg(test)]
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod tests {
+    use std::{path::Path, sync::mpsc::channel, time::Duration};
+
+    use super::{ActiveWatcher, DiskWatcher, WatchMode, WatcherBackend};
+
+    const ENOENT: i32 = 2;
+    const ENOSPC: i32 = 28;
+
+    fn hybrid_watcher(failing_path: &Path, errno: i32) -> DiskWatcher {
+        let watcher = DiskWatcher::default();
+        let (tx, _) = channel();
+        *watcher.watcher.lock().unwrap() = Some(ActiveWatcher {
+            backend: WatcherBackend::Failing {
+                path: failing_path.to_path_buf(),
+                errno,
+            },
+            mode: WatchMode::Hybrid {
+                poll_interval: Duration::from_millis(10),
+            },
+            tx,
+        });
+        watcher
+    }
+
+    fn is_polling(watcher: &DiskWatcher) -> bool {
+        matches!(
+            watcher.watcher.lock().unwrap().as_ref().unwrap().backend,
+            WatcherBackend::Polling(_)
+        )
+    }
+
+    #[test]
+    fn hybrid_falls_back_to_polling_when_watching_fails() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+
+        // The parent could be watched, but wouldn't report changes in the
+        // directory
+        let watcher = hybrid_watcher(&dir, ENOSPC);
+        watcher.ensure_watching(&dir, root.path()).unwrap();
+        assert!(is_polling(&watcher));
+        let reason = watcher.fallback.lock().unwrap().reason.clone().unwrap();
+        assert!(reason.contains(&dir.display().to_string()), "{}", reason);
+    }
+
+    #[test]
+    fn missing_directories_are_watched_through_their_parent() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("missing");
+
+        let watcher = hybrid_watcher(&dir, ENOENT);
+        watcher.ensure_watching(&dir, root.path()).unwrap();
+        assert!(!is_polling(&watcher));
+        assert_eq!(watcher.fallback.lock().unwrap().reason, None);
+    }
+
+    #[test]
+    fn native_mode_returns_watch_errors() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+
+        let watcher = hybrid_watcher(&dir, ENOSPC);
+        watcher.watcher.lock().unwrap().as_mut().unwrap().mode = WatchMode::Native;
+        assert!(watcher.ensure_watching(&dir, root.path()).is_err());
+        assert!(!is_polling(&watcher));
+        assert_eq!(watcher.fallback.lock().unwrap().reason, None);
+    }
+}
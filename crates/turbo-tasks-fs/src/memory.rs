@@ -0,0 +1,554 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, bail, Result};
+use auto_hash_map::AutoMap;
+use turbo_tasks::{
+    mark_stateful, primitives::StringVc, CompletionVc, Invalidator, ValueToString, ValueToStringVc,
+};
+
+use crate::{
+    invalidation::WatchChange,
+    invalidator_map::InvalidatorMap,
+    util::{join_path, normalize_path},
+    DirectoryContentVc, DirectoryEntry, File, FileContent, FileContentVc, FileMeta, FileMetaVc,
+    FileSystem, FileSystemPathVc, FileSystemVc, LinkContent, LinkContentVc, LinkType,
+};
+
+/// Maximum number of symlinks followed while resolving a single path.
+const MAX_SYMLINKS: usize = 40;
+
+#[derive(Clone)]
+enum MemoryEntry {
+    File(File),
+    Directory,
+    /// The target of an absolute symlink is relative to the root of the
+    /// filesystem.
+    Symlink {
+        target: String,
+        absolute: bool,
+    },
+}
+
+/// A writable [FileSystem] that keeps all files in memory.
+///
+/// Files can be changed from outside of turbo-tasks with
+/// [MemoryFileSystem::write_file], [MemoryFileSystem::write_symlink],
+/// [MemoryFileSystem::create_dir] and [MemoryFileSystem::remove]. Like the
+/// watcher of the [crate::DiskFileSystem], these invalidate all tasks that have
+/// read the changed paths.
+#[turbo_tasks::value(cell = "new", eq = "manual", serialization = "none")]
+pub struct MemoryFileSystem {
+    pub name: String,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    entries: Arc<Mutex<BTreeMap<String, MemoryEntry>>>,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    invalidator_map: Arc<InvalidatorMap>,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    dir_invalidator_map: Arc<InvalidatorMap>,
+}
+
+#[turbo_tasks::value_impl]
+impl MemoryFileSystemVc {
+    #[turbo_tasks::function]
+    pub fn new(name: String) -> Self {
+        mark_stateful();
+        Self::cell(MemoryFileSystem {
+            name,
+            entries: Arc::new(Mutex::new(BTreeMap::from([(
+                String::new(),
+                MemoryEntry::Directory,
+            )]))),
+            invalidator_map: Arc::new(InvalidatorMap::new()),
+            dir_invalidator_map: Arc::new(InvalidatorMap::new()),
+        })
+    }
+}
+
+impl Debug for MemoryFileSystem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "name: {}", self.name)
+    }
+}
+
+/// Returns true when `path` is equal to `dir` or inside of it.
+fn is_inside_or_equal(path: &str, dir: &str) -> bool {
+    dir.is_empty()
+        || path == dir
+        || (path.starts_with(dir) && path.as_bytes().get(dir.len()) == Some(&b'/'))
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+/// Resolves all symlinks in `path`. The last segment is only resolved when
+/// `follow_last` is set. Returns the resolved path and the paths of all
+/// symlinks that have been followed.
+fn resolve(
+    entries: &BTreeMap<String, MemoryEntry>,
+    path: &str,
+    follow_last: bool,
+) -> Result<(String, Vec<String>)> {
+    let mut pending = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect::<VecDeque<_>>();
+    let mut current = String::new();
+    let mut links = Vec::new();
+    while let Some(segment) = pending.pop_front() {
+        let next = join_path(&current, &segment).unwrap();
+        match entries.get(&next) {
+            Some(MemoryEntry::Symlink { target, absolute })
+                if follow_last || !pending.is_empty() =>
+            {
+                if links.len() == MAX_SYMLINKS {
+                    bail!("too many levels of symbolic links in {}", path);
+                }
+                let base = if *absolute { "" } else { current.as_str() };
+                let Some(target) = join_path(base, target) else {
+                    bail!("symlink {} leaves the filesystem root", next);
+                };
+                for segment in target.split('/').rev().filter(|s| !s.is_empty()) {
+                    pending.push_front(segment.to_string());
+                }
+                links.push(next);
+                current = String::new();
+            }
+            _ => current = next,
+        }
+    }
+    Ok((current, links))
+}
+
+#[derive(Default)]
+struct Changes {
+    /// Paths whose content has changed.
+    modified: Vec<String>,
+    /// Paths that have been created, removed or replaced by a different kind
+    /// of entry, which also changes the content of their parent directory.
+    added_or_removed: Vec<String>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.added_or_removed.is_empty()
+    }
+
+    fn paths(&self) -> impl Iterator<Item = &str> {
+        self.modified
+            .iter()
+            .chain(self.added_or_removed.iter())
+            .map(|path| path.as_str())
+    }
+}
+
+/// Creates all missing parent directories of `path`.
+fn create_parents(
+    entries: &mut BTreeMap<String, MemoryEntry>,
+    path: &str,
+    changes: &mut Changes,
+) -> Result<()> {
+    let mut dir = String::new();
+    for segment in parent(path).split('/').filter(|s| !s.is_empty()) {
+        dir = join_path(&dir, segment).unwrap();
+        match entries.get(&dir) {
+            None => {
+                entries.insert(dir.clone(), MemoryEntry::Directory);
+                changes.added_or_removed.push(dir.clone());
+            }
+            Some(MemoryEntry::Directory) => {}
+            Some(_) => bail!("{} is not a directory", dir),
+        }
+    }
+    Ok(())
+}
+
+impl MemoryFileSystem {
+    fn normalize(path: &str) -> Result<String> {
+        normalize_path(path).ok_or_else(|| anyhow!("{} leaves the filesystem root", path))
+    }
+
+    /// Resolves `path` and registers the current task as dependent on the
+    /// path, all followed symlinks and the resolved path. Has to be called
+    /// within a turbo-tasks function.
+    fn resolve_tracked(
+        &self,
+        entries: &BTreeMap<String, MemoryEntry>,
+        path: &str,
+        follow_last: bool,
+    ) -> Result<String> {
+        self.invalidator_map
+            .insert(path.to_string(), turbo_tasks::get_invalidator());
+        let (resolved, links) = resolve(entries, path, follow_last)?;
+        for link in links {
+            self.invalidator_map
+                .insert(link, turbo_tasks::get_invalidator());
+        }
+        if resolved != path {
+            self.invalidator_map
+                .insert(resolved.clone(), turbo_tasks::get_invalidator());
+        }
+        Ok(resolved)
+    }
+
+    /// Applies `update` to the entries and invalidates all tasks that read
+    /// one of the paths that `update` reports as changed.
+    fn update(
+        &self,
+        update: impl FnOnce(&mut BTreeMap<String, MemoryEntry>, &mut Changes) -> Result<()>,
+    ) -> Result<()> {
+        let mut changes = Changes::default();
+        // Paths might have been changed before the update failed
+        let result = update(&mut self.entries.lock().unwrap(), &mut changes);
+        if !changes.is_empty() {
+            self.invalidate(&changes);
+        }
+        result
+    }
+
+    fn invalidate(&self, changes: &Changes) {
+        let invalidate = |path: &str, invalidator: Invalidator| {
+            invalidator.invalidate_with_reason(WatchChange {
+                path: format!("[{}]/{}", self.name, path),
+            })
+        };
+        {
+            let mut invalidator_map = self.invalidator_map.lock().unwrap();
+            for path in changes.paths() {
+                for (_, invalidators) in
+                    invalidator_map.drain_filter(|key, _| is_inside_or_equal(key, path))
+                {
+                    invalidators.into_iter().for_each(|i| invalidate(path, i));
+                }
+            }
+        }
+        {
+            let mut dir_invalidator_map = self.dir_invalidator_map.lock().unwrap();
+            let parents = changes
+                .added_or_removed
+                .iter()
+                .map(|path| parent(path))
+                .collect::<HashSet<_>>();
+            for path in parents {
+                if let Some(invalidators) = dir_invalidator_map.remove(path) {
+                    invalidators.into_iter().for_each(|i| invalidate(path, i));
+                }
+            }
+            for path in changes.paths() {
+                for (_, invalidators) in
+                    dir_invalidator_map.drain_filter(|key, _| is_inside_or_equal(key, path))
+                {
+                    invalidators.into_iter().for_each(|i| invalidate(path, i));
+                }
+            }
+        }
+    }
+
+    fn write_entry(&self, path: &str, content: FileContent) -> Result<bool> {
+        let mut written = false;
+        self.update(|entries, changes| {
+            let (path, _) = resolve(entries, path, true)?;
+            let unchanged = match (entries.get(&path), &content) {
+                (Some(MemoryEntry::Directory), _) => bail!("{} is a directory", path),
+                (Some(MemoryEntry::File(old)), FileContent::Content(file)) => old == file,
+                (None, FileContent::NotFound) => true,
+                _ => false,
+            };
+            if unchanged {
+                return Ok(());
+            }
+            let modified = matches!(
+                (entries.get(&path), &content),
+                (Some(MemoryEntry::File(_)), FileContent::Content(_))
+            );
+            match content {
+                FileContent::Content(file) => {
+                    create_parents(entries, &path, changes)?;
+                    entries.insert(path.clone(), MemoryEntry::File(file));
+                }
+                FileContent::NotFound => {
+                    entries.remove(&path);
+                }
+            }
+            if modified {
+                changes.modified.push(path);
+            } else {
+                changes.added_or_removed.push(path);
+            }
+            written = true;
+            Ok(())
+        })?;
+        Ok(written)
+    }
+
+    fn write_link_entry(&self, path: &str, link: Option<MemoryEntry>) -> Result<bool> {
+        let mut written = false;
+        self.update(|entries, changes| {
+            let (path, _) = resolve(entries, path, false)?;
+            let modified = matches!(
+                (entries.get(&path), &link),
+                (Some(MemoryEntry::Symlink { .. }), Some(_))
+            );
+            let unchanged = match (entries.get(&path), &link) {
+                (Some(MemoryEntry::Directory), _) => bail!("{} is a directory", path),
+                (
+                    Some(MemoryEntry::Symlink {
+                        target: old_target,
+                        absolute: old_absolute,
+                    }),
+                    Some(MemoryEntry::Symlink { target, absolute }),
+                ) => old_target == target && old_absolute == absolute,
+                (None, None) => true,
+                _ => false,
+            };
+            if unchanged {
+                return Ok(());
+            }
+            match link {
+                Some(link) => {
+                    create_parents(entries, &path, changes)?;
+                    entries.insert(path.clone(), link);
+                }
+                None => {
+                    entries.remove(&path);
+                }
+            }
+            if modified {
+                changes.modified.push(path);
+            } else {
+                changes.added_or_removed.push(path);
+            }
+            written = true;
+            Ok(())
+        })?;
+        Ok(written)
+    }
+
+    /// Writes a file, creating missing parent directories.
+    pub fn write_file(&self, path: &str, content: impl Into<File>) -> Result<()> {
+        self.write_entry(
+            &Self::normalize(path)?,
+            FileContent::Content(content.into()),
+        )?;
+        Ok(())
+    }
+
+    /// Creates a symlink to `target`, creating missing parent directories.
+    /// A `target` starting with `/` is relative to the root of the
+    /// filesystem.
+    pub fn write_symlink(&self, path: &str, target: &str) -> Result<()> {
+        let link = match target.strip_prefix('/') {
+            Some(target) => MemoryEntry::Symlink {
+                target: Self::normalize(target)?,
+                absolute: true,
+            },
+            None => MemoryEntry::Symlink {
+                target: target.to_string(),
+                absolute: false,
+            },
+        };
+        self.write_link_entry(&Self::normalize(path)?, Some(link))?;
+        Ok(())
+    }
+
+    /// Creates a directory and all missing parent directories.
+    pub fn create_dir(&self, path: &str) -> Result<()> {
+        let path = Self::normalize(path)?;
+        self.update(|entries, changes| {
+            let (path, _) = resolve(entries, &path, true)?;
+            match entries.get(&path) {
+                Some(MemoryEntry::Directory) => {}
+                Some(_) => bail!("{} already exists", path),
+                None => {
+                    create_parents(entries, &path, changes)?;
+                    entries.insert(path.clone(), MemoryEntry::Directory);
+                    changes.added_or_removed.push(path);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Removes a file, symlink or directory including all its contents.
+    /// Removing a path that doesn't exist is not an error.
+    pub fn remove(&self, path: &str) -> Result<()> {
+        let path = Self::normalize(path)?;
+        if path.is_empty() {
+            bail!("the root of the filesystem can't be removed");
+        }
+        self.update(|entries, changes| {
+            let (path, _) = resolve(entries, &path, false)?;
+            if entries.remove(&path).is_some() {
+                entries.retain(|key, _| !is_inside_or_equal(key, &path));
+                changes.added_or_removed.push(path);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for MemoryFileSystem {
+    #[turbo_tasks::function]
+    async fn read(&self, fs_path: FileSystemPathVc) -> Result<FileContentVc> {
+        let path = &fs_path.await?.path;
+        let entries = self.entries.lock().unwrap();
+        let path = self.resolve_tracked(&entries, path, true)?;
+        Ok(match entries.get(&path) {
+            Some(MemoryEntry::File(file)) => FileContent::Content(file.clone()),
+            _ => FileContent::NotFound,
+        }
+        .cell())
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(&self, fs_path: FileSystemPathVc) -> Result<LinkContentVc> {
+        let path = &fs_path.await?.path;
+        let entries = self.entries.lock().unwrap();
+        let path = self.resolve_tracked(&entries, path, false)?;
+        let Some(MemoryEntry::Symlink { target, absolute }) = entries.get(&path) else {
+            return Ok(LinkContent::NotFound.cell());
+        };
+        let base = if *absolute { "" } else { parent(&path) };
+        let Some(target_path) = join_path(base, target) else {
+            return Ok(LinkContent::Invalid.cell());
+        };
+        let target_path = self.resolve_tracked(&entries, &target_path, true)?;
+        let mut link_type = LinkType::UNSET;
+        if *absolute {
+            link_type |= LinkType::ABSOLUTE;
+        }
+        if matches!(entries.get(&target_path), Some(MemoryEntry::Directory)) {
+            link_type |= LinkType::DIRECTORY;
+        }
+        Ok(LinkContent::Link {
+            target: target.clone(),
+            link_type,
+        }
+        .cell())
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(&self, fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
+        let path = fs_path.await?.path.clone();
+        let items = {
+            let entries = self.entries.lock().unwrap();
+            self.dir_invalidator_map
+                .insert(path.clone(), turbo_tasks::get_invalidator());
+            let (dir, links) = resolve(&entries, &path, true)?;
+            for link in links {
+                self.invalidator_map
+                    .insert(link, turbo_tasks::get_invalidator());
+            }
+            if dir != path {
+                self.dir_invalidator_map
+                    .insert(dir.clone(), turbo_tasks::get_invalidator());
+            }
+            if !matches!(entries.get(&dir), Some(MemoryEntry::Directory)) {
+                return Ok(DirectoryContentVc::not_found());
+            }
+            let prefix = if dir.is_empty() {
+                String::new()
+            } else {
+                format!("{dir}/")
+            };
+            entries
+                .range(prefix.clone()..)
+                .take_while(|(key, _)| key.starts_with(&prefix))
+                .filter_map(|(key, entry)| {
+                    let name = &key[prefix.len()..];
+                    (!name.is_empty() && !name.contains('/'))
+                        .then(|| (name.to_string(), entry.clone()))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let entries = items
+            .into_iter()
+            .map(|(name, entry)| {
+                let entry_path = fs_path.join(&name);
+                let entry = match entry {
+                    MemoryEntry::File(_) => DirectoryEntry::File(entry_path),
+                    MemoryEntry::Directory => DirectoryEntry::Directory(entry_path),
+                    MemoryEntry::Symlink { .. } => DirectoryEntry::Symlink(entry_path),
+                };
+                (name, entry)
+            })
+            .collect::<AutoMap<_, _>>();
+        Ok(DirectoryContentVc::new(entries))
+    }
+
+    #[turbo_tasks::function]
+    async fn track(&self, fs_path: FileSystemPathVc) -> Result<CompletionVc> {
+        let path = &fs_path.await?.path;
+        let entries = self.entries.lock().unwrap();
+        self.resolve_tracked(&entries, path, true)?;
+        Ok(CompletionVc::new())
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        let path = &fs_path.await?.path;
+        let content = content.await?;
+
+        // Track the file, so that we will rewrite it if it ever changes.
+        fs_path.track().await?;
+
+        if self.write_entry(path, (*content).clone())? {
+            Ok(CompletionVc::new())
+        } else {
+            Ok(CompletionVc::unchanged())
+        }
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        let path = &fs_path.await?.path;
+        let link = match &*target.await? {
+            LinkContent::Link { target, link_type } => Some(MemoryEntry::Symlink {
+                target: target.clone(),
+                absolute: link_type.contains(LinkType::ABSOLUTE),
+            }),
+            LinkContent::Invalid => bail!("invalid symlink target: {}", path),
+            LinkContent::NotFound => None,
+        };
+        if self.write_link_entry(path, link)? {
+            Ok(CompletionVc::new())
+        } else {
+            Ok(CompletionVc::unchanged())
+        }
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
+        let path = &fs_path.await?.path;
+        let entries = self.entries.lock().unwrap();
+        let resolved = self.resolve_tracked(&entries, path, true)?;
+        match entries.get(&resolved) {
+            Some(MemoryEntry::File(file)) => Ok(file.meta.clone().cell()),
+            Some(_) => Ok(FileMeta::default().cell()),
+            None => bail!("path {} not found, can't read metadata", path),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for MemoryFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> StringVc {
+        StringVc::cell(self.name.clone())
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use auto_hash_map::AutoMap;
+use turbo_tasks::{primitives::StringVc, CompletionVc, ValueToString, ValueToStringVc};
+
+use crate::{
+    DirectoryContent, DirectoryContentVc, DirectoryEntry, File, FileContent, FileContentVc,
+    FileMetaVc, FileSystem, FileSystemEntryType, FileSystemPathVc, FileSystemVc, LinkContent,
+    LinkContentVc,
+};
+
+/// Prefix of the files that hide an entry of the same name in lower layers.
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// A [FileSystem] that layers an ordered list of [FileSystem]s over each
+/// other, e.g. generated files over a source tree.
+///
+/// Reads return the entry of the first layer that contains the path and
+/// directories list the entries of all layers. Writes only go to the first
+/// layer. Deleting a path that exists in lower layers writes a whiteout file
+/// (`.wh.<name>`) next to it in the first layer, which hides the path and
+/// everything below it in all lower layers.
+#[turbo_tasks::value]
+pub struct OverlayFileSystem {
+    name: String,
+    /// Ordered from top to bottom.
+    layers: Vec<FileSystemVc>,
+}
+
+#[turbo_tasks::value_impl]
+impl OverlayFileSystemVc {
+    #[turbo_tasks::function]
+    pub fn new(name: String, layers: Vec<FileSystemVc>) -> Result<Self> {
+        if layers.is_empty() {
+            bail!("overlay filesystem {} needs at least one layer", name);
+        }
+        Ok(OverlayFileSystem { name, layers }.cell())
+    }
+}
+
+/// Returns the whiteout path for `path`, or None for the root.
+fn whiteout_path(path: &str) -> Option<String> {
+    if path.is_empty() {
+        return None;
+    }
+    Some(match path.rsplit_once('/') {
+        Some((parent, name)) => format!("{parent}/{WHITEOUT_PREFIX}{name}"),
+        None => format!("{WHITEOUT_PREFIX}{path}"),
+    })
+}
+
+/// Checks if `path` or one of its parent directories has been deleted in
+/// `layer`.
+async fn is_whiteout(layer: FileSystemVc, path: &str) -> Result<bool> {
+    let mut end = 0;
+    while end < path.len() {
+        end = path[end..].find('/').map_or(path.len(), |i| end + i);
+        let whiteout = whiteout_path(&path[..end]).unwrap();
+        if matches!(
+            &*layer.root().join(&whiteout).get_type().await?,
+            FileSystemEntryType::File
+        ) {
+            return Ok(true);
+        }
+        end += 1;
+    }
+    Ok(false)
+}
+
+/// Finds the first of `layers` that contains `path`.
+async fn find_in_layers(layers: &[FileSystemVc], path: &str) -> Result<Option<FileSystemPathVc>> {
+    for layer in layers {
+        let layer_path = layer.root().join(path);
+        if !matches!(
+            &*layer_path.get_type().await?,
+            FileSystemEntryType::NotFound
+        ) {
+            return Ok(Some(layer_path));
+        }
+        if is_whiteout(*layer, path).await? {
+            return Ok(None);
+        }
+    }
+    Ok(None)
+}
+
+impl OverlayFileSystem {
+    async fn find(&self, fs_path: FileSystemPathVc) -> Result<Option<FileSystemPathVc>> {
+        find_in_layers(&self.layers, &fs_path.await?.path).await
+    }
+
+    /// Removes the whiteout of `path` from the top layer, so that the path
+    /// can be written.
+    async fn remove_whiteout(&self, path: &str) -> Result<()> {
+        if let Some(whiteout) = whiteout_path(path) {
+            self.layers[0]
+                .root()
+                .join(&whiteout)
+                .write(FileContent::NotFound.cell())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Hides `path` in the lower layers, when they contain it.
+    async fn add_whiteout(&self, path: &str) -> Result<()> {
+        let Some(whiteout) = whiteout_path(path) else {
+            bail!("the root of overlay filesystem {} can't be removed", self.name);
+        };
+        if find_in_layers(&self.layers[1..], path).await?.is_some() {
+            self.layers[0]
+                .root()
+                .join(&whiteout)
+                .write(File::from("").into())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for OverlayFileSystem {
+    #[turbo_tasks::function]
+    async fn read(&self, fs_path: FileSystemPathVc) -> Result<FileContentVc> {
+        Ok(match self.find(fs_path).await? {
+            Some(layer_path) => layer_path.read(),
+            None => FileContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(&self, fs_path: FileSystemPathVc) -> Result<LinkContentVc> {
+        Ok(match self.find(fs_path).await? {
+            Some(layer_path) => layer_path.read_link(),
+            None => LinkContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(&self, fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
+        let path = &fs_path.await?.path;
+        let mut found = false;
+        let mut entries = AutoMap::new();
+        let mut hidden = HashSet::new();
+        for layer in self.layers.iter() {
+            if let DirectoryContent::Entries(layer_entries) =
+                &*layer.root().join(path).read_dir().await?
+            {
+                found = true;
+                for (name, entry) in layer_entries.iter() {
+                    if let Some(name) = name.strip_prefix(WHITEOUT_PREFIX) {
+                        hidden.insert(name.to_string());
+                        continue;
+                    }
+                    if hidden.contains(name) || entries.contains_key(name) {
+                        continue;
+                    }
+                    let entry_path = fs_path.join(name);
+                    let entry = match entry {
+                        DirectoryEntry::File(_) => DirectoryEntry::File(entry_path),
+                        DirectoryEntry::Directory(_) => DirectoryEntry::Directory(entry_path),
+                        DirectoryEntry::Symlink(_) => DirectoryEntry::Symlink(entry_path),
+                        DirectoryEntry::Other(_) => DirectoryEntry::Other(entry_path),
+                        DirectoryEntry::Error => DirectoryEntry::Error,
+                    };
+                    entries.insert(name.clone(), entry);
+                }
+            }
+            if is_whiteout(*layer, path).await? {
+                break;
+            }
+        }
+        if !found {
+            return Ok(DirectoryContentVc::not_found());
+        }
+        Ok(DirectoryContentVc::new(entries))
+    }
+
+    #[turbo_tasks::function]
+    async fn track(&self, fs_path: FileSystemPathVc) -> Result<CompletionVc> {
+        let path = &fs_path.await?.path;
+        for layer in self.layers.iter() {
+            layer.root().join(path).track().await?;
+        }
+        Ok(CompletionVc::new())
+    }
+
+    #[turbo_tasks::function]
+    async fn write(
+        &self,
+        fs_path: FileSystemPathVc,
+        content: FileContentVc,
+    ) -> Result<CompletionVc> {
+        let path = &fs_path.await?.path;
+        let completion = self.layers[0].root().join(path).write(content);
+        completion.await?;
+        match &*content.await? {
+            FileContent::Content(_) => self.remove_whiteout(path).await?,
+            FileContent::NotFound => self.add_whiteout(path).await?,
+        }
+        Ok(completion)
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        fs_path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        let path = &fs_path.await?.path;
+        let completion = self.layers[0].root().join(path).write_link(target);
+        completion.await?;
+        match &*target.await? {
+            LinkContent::Link { .. } => self.remove_whiteout(path).await?,
+            LinkContent::NotFound => self.add_whiteout(path).await?,
+            LinkContent::Invalid => {}
+        }
+        Ok(completion)
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPathVc) -> Result<FileMetaVc> {
+        match self.find(fs_path).await? {
+            Some(layer_path) => Ok(layer_path.metadata()),
+            None => bail!(
+                "path {} not found, can't read metadata",
+                fs_path.to_string().await?
+            ),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for OverlayFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> StringVc {
+        StringVc::cell(self.name.clone())
+    }
+}
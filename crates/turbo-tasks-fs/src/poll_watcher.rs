@@ -0,0 +1,132 @@
+use std::{
+    collections::HashMap,
+    fs::{self, FileType, Metadata},
+    io,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc, Mutex, Weak},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use notify::{DebouncedEvent, RecursiveMode};
+
+/// The metadata that is compared to detect changes of an entry.
+#[derive(Clone, PartialEq, Eq)]
+struct Stat {
+    file_type: FileType,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Stat {
+    fn new(metadata: &Metadata) -> Self {
+        // The native watcher doesn't report changes inside of child directories
+        // for non-recursive watches, so directories only compare their type.
+        if metadata.is_dir() {
+            return Stat {
+                file_type: metadata.file_type(),
+                len: 0,
+                modified: None,
+            };
+        }
+        Stat {
+            file_type: metadata.file_type(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+struct Watch {
+    recursive: bool,
+    entries: HashMap<PathBuf, Stat>,
+}
+
+type Watches = Mutex<HashMap<PathBuf, Watch>>;
+
+/// A watcher that detects changes by comparing the metadata of the entries of
+/// all watched directories at an interval. It reports the same events as the
+/// native watcher, so it can be used where the native API is not available,
+/// e.g. on network drives or when the inotify watch limit is reached.
+pub(crate) struct PollWatcher {
+    watches: Arc<Watches>,
+}
+
+impl PollWatcher {
+    pub fn new(tx: Sender<DebouncedEvent>, interval: Duration) -> io::Result<Self> {
+        let watches = Arc::new(Mutex::new(HashMap::new()));
+        let weak_watches = Arc::downgrade(&watches);
+        thread::Builder::new()
+            .name("poll watcher".to_string())
+            .spawn(move || poll(weak_watches, tx, interval))?;
+        Ok(PollWatcher { watches })
+    }
+
+    /// Takes a snapshot of the entries of `path`. Changes are reported relative
+    /// to that snapshot.
+    pub fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> io::Result<()> {
+        fs::metadata(path)?;
+        let recursive = matches!(recursive_mode, RecursiveMode::Recursive);
+        let mut entries = HashMap::new();
+        scan(path, recursive, &mut entries);
+        self.watches
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), Watch { recursive, entries });
+        Ok(())
+    }
+}
+
+fn scan(dir: &Path, recursive: bool, entries: &mut HashMap<PathBuf, Stat>) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in read_dir.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let path = entry.path();
+        if recursive && metadata.is_dir() {
+            scan(&path, recursive, entries);
+        }
+        entries.insert(path, Stat::new(&metadata));
+    }
+}
+
+/// Compares the watched directories every `interval` until the watcher is
+/// dropped or the receiver disconnects.
+fn poll(watches: Weak<Watches>, tx: Sender<DebouncedEvent>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        let Some(watches) = watches.upgrade() else {
+            return;
+        };
+        let mut watches = watches.lock().unwrap();
+        for (path, watch) in watches.iter_mut() {
+            let mut entries = HashMap::with_capacity(watch.entries.len());
+            scan(path, watch.recursive, &mut entries);
+            let mut events = Vec::new();
+            for (path, stat) in entries.iter() {
+                match watch.entries.remove(path) {
+                    None => events.push(DebouncedEvent::Create(path.clone())),
+                    Some(old_stat) if old_stat != *stat => {
+                        events.push(DebouncedEvent::Write(path.clone()))
+                    }
+                    Some(_) => {}
+                }
+            }
+            events.extend(
+                watch
+                    .entries
+                    .drain()
+                    .map(|(path, _)| DebouncedEvent::Remove(path)),
+            );
+            watch.entries = entries;
+            for event in events {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
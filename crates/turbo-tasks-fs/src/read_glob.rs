@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 
-use crate::{glob::GlobVc, DirectoryContent, DirectoryEntry, FileSystemPathVc};
+use crate::{
+    glob::GlobVc, ignore::ignore_rules, DirectoryContent, DirectoryEntry, FileSystemPathVc,
+};
 
 #[turbo_tasks::value]
 #[derive(Default, Debug)]
@@ -21,7 +23,23 @@ pub async fn read_glob(
     glob: GlobVc,
     include_dot_files: bool,
 ) -> Result<ReadGlobResultVc> {
-    read_glob_internal("", directory, glob, include_dot_files).await
+    read_glob_internal("", directory, glob, include_dot_files, false).await
+}
+
+/// Reads matches of a glob pattern, skipping paths that are excluded by
+/// `.gitignore` and `.ignore` files in the directory, in its subdirectories or
+/// in its parents up to the root of the repository. `.git` directories are
+/// always skipped.
+///
+/// DETERMINISM: Result is in random order. Either sort result or do not depend
+/// on the order.
+#[turbo_tasks::function]
+pub async fn read_glob_with_ignore_files(
+    directory: FileSystemPathVc,
+    glob: GlobVc,
+    include_dot_files: bool,
+) -> Result<ReadGlobResultVc> {
+    read_glob_internal("", directory, glob, include_dot_files, true).await
 }
 
 #[turbo_tasks::function]
@@ -30,8 +48,16 @@ async fn read_glob_inner(
     directory: FileSystemPathVc,
     glob: GlobVc,
     include_dot_files: bool,
+    respect_ignore_files: bool,
 ) -> Result<ReadGlobResultVc> {
-    read_glob_internal(&prefix, directory, glob, include_dot_files).await
+    read_glob_internal(
+        &prefix,
+        directory,
+        glob,
+        include_dot_files,
+        respect_ignore_files,
+    )
+    .await
 }
 
 async fn read_glob_internal(
@@ -39,13 +65,31 @@ async fn read_glob_internal(
     directory: FileSystemPathVc,
     glob: GlobVc,
     include_dot_files: bool,
+    respect_ignore_files: bool,
 ) -> Result<ReadGlobResultVc> {
     let dir = directory.read_dir().await?;
     let mut result = ReadGlobResult::default();
     let glob_value = glob.await?;
+    let ignore = if respect_ignore_files {
+        Some((directory.await?, ignore_rules(directory).await?))
+    } else {
+        None
+    };
     match &*dir {
         DirectoryContent::Entries(entries) => {
             for item in entries.iter() {
+                if let Some((directory, rules)) = &ignore {
+                    let (segment, entry) = item;
+                    let is_dir = matches!(entry, DirectoryEntry::Directory(_));
+                    let path = if directory.path.is_empty() {
+                        segment.clone()
+                    } else {
+                        format!("{}/{segment}", directory.path)
+                    };
+                    if (is_dir && segment == ".git") || rules.is_ignored(&path, is_dir) {
+                        continue;
+                    }
+                }
                 match item {
                     (segment, DirectoryEntry::Directory(path)) => {
                         let full_path = format!("{prefix}{segment}");
@@ -58,7 +102,13 @@ async fn read_glob_internal(
                         if glob_value.execute(&full_path_prefix) {
                             result.inner.insert(
                                 full_path,
-                                read_glob_inner(full_path_prefix, *path, glob, include_dot_files),
+                                read_glob_inner(
+                                    full_path_prefix,
+                                    *path,
+                                    glob,
+                                    include_dot_files,
+                                    respect_ignore_files,
+                                ),
                             );
                         }
                     }
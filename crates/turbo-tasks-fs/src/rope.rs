@@ -114,6 +114,15 @@ impl Rope {
     pub fn to_bytes(&self) -> Result<Cow<'_, [u8]>> {
         self.data.to_bytes()
     }
+
+    /// Writes all bytes into `hasher` without copying them into a contiguous
+    /// buffer. Unlike [DeterministicHash], only the bytes are hashed, so the
+    /// result equals hashing [Rope::to_bytes].
+    pub fn hash_content<H: DeterministicHasher>(&self, hasher: &mut H) {
+        for bytes in self.read() {
+            hasher.write_bytes(&bytes);
+        }
+    }
 }
 
 impl<T: Into<Bytes>> From<T> for Rope {
@@ -721,6 +730,7 @@ mod test {
     };
 
     use anyhow::Result;
+    use turbo_tasks_hash::{hash_sha256, Sha256Hasher};
 
     use super::{InnerRope, Rope, RopeBuilder, RopeElem};
 
@@ -982,4 +992,14 @@ mod test {
         assert_eq!(rope.to_bytes()?, Cow::Borrowed::<[u8]>(&[0x61, 0x62, 0x63]));
         Ok(())
     }
+
+    #[test]
+    fn hash_content() -> Result<()> {
+        let shared = Rope::from("def");
+        let rope = Rope::new(vec!["abc".into(), vec![shared.into(), "ghi".into()].into()]);
+        let mut hasher = Sha256Hasher::new();
+        rope.hash_content(&mut hasher);
+        assert_eq!(hasher.digest(), hash_sha256(&rope.to_bytes()?));
+        Ok(())
+    }
 }
@@ -0,0 +1,333 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{bail, Context, Result};
+use turbo_tasks::{CompletionVc, ReadRef, ValueToString};
+
+use crate::{
+    path_to_key, retry::retry_blocking, util::unix_to_sys, DiskFileSystem, DiskFileSystemVc,
+    FileComparison, FileContent, FileContentVc, FileSystemPathVc, FileSystemVc,
+};
+
+/// Files that are written together, see [DiskFileSystemVc::write_dir_atomic].
+#[turbo_tasks::value(transparent)]
+pub struct WriteBatch(Vec<(FileSystemPathVc, FileContentVc)>);
+
+/// Distinguishes the staging directories of concurrent batches.
+static NEXT_STAGING_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[turbo_tasks::value_impl]
+impl DiskFileSystemVc {
+    /// Replaces the contents of `dir` with the files of `batch`, so that
+    /// readers never observe a partially written directory.
+    ///
+    /// The files are staged in a temporary directory next to `dir` and synced
+    /// to disk before the staging directory is renamed to `dir`. Files that
+    /// are not part of the batch are removed. Files whose content didn't
+    /// change are linked into the staging directory instead of being
+    /// rewritten, so their modification times are preserved. When nothing
+    /// changed, `dir` isn't touched at all.
+    ///
+    /// On Linux an existing `dir` is exchanged with the staging directory in
+    /// a single step. Elsewhere it's moved aside before the staging directory
+    /// takes its place, so it's briefly missing, but never partially written.
+    #[turbo_tasks::function]
+    pub async fn write_dir_atomic(
+        self,
+        dir: FileSystemPathVc,
+        batch: WriteBatchVc,
+    ) -> Result<CompletionVc> {
+        let this = self.await?;
+        let dir_value = dir.await?;
+        if dir_value.fs.resolve().await? != FileSystemVc::from(self) {
+            bail!(
+                "{} is not a directory of {}",
+                dir.to_string().await?,
+                this.name
+            );
+        }
+        let full_dir = this.to_sys_path(dir).await?;
+
+        let mut files = Vec::new();
+        for (path, content) in batch.await?.iter() {
+            let path_value = path.await?;
+            let Some(relative_path) = dir_value.get_path_to(&path_value).filter(|p| !p.is_empty()) else {
+                bail!(
+                    "{} is not inside of {}",
+                    path.to_string().await?,
+                    dir.to_string().await?
+                );
+            };
+            // Track the file, so that we will rewrite it if it ever changes.
+            path.track().await?;
+            let content = content.await?;
+            if let FileContent::Content(_) = &*content {
+                files.push((PathBuf::from(&*unix_to_sys(relative_path)), content));
+            }
+        }
+
+        let _lock = this.mutex_map.lock(full_dir.clone()).await;
+
+        let mut staged = Vec::with_capacity(files.len());
+        let mut changed = false;
+        for (relative_path, content) in files {
+            let compare = content
+                .streaming_compare(full_dir.join(&relative_path))
+                .await?;
+            changed |= compare != FileComparison::Equal;
+            staged.push(StagedFile {
+                relative_path,
+                content,
+                unchanged: compare == FileComparison::Equal,
+            });
+        }
+        if !changed {
+            let existing = retry_blocking(&full_dir, list_files).await?;
+            let written = staged
+                .iter()
+                .map(|file| file.relative_path.clone())
+                .collect::<HashSet<_>>();
+            if existing == written {
+                return Ok(CompletionVc::unchanged());
+            }
+        }
+
+        let created = !full_dir.exists();
+        let target = full_dir.clone();
+        tokio::task::spawn_blocking(move || swap_dir(&target, &staged))
+            .await?
+            .with_context(|| format!("failed to write {} atomically", full_dir.display()))?;
+        this.dir_swapped(&full_dir, created);
+
+        Ok(CompletionVc::new())
+    }
+}
+
+impl DiskFileSystem {
+    /// Readers of the replaced directory need to be invalidated. The watcher
+    /// can't be relied on, as native watches follow the renamed directories.
+    fn dir_swapped(&self, full_dir: &Path, created: bool) {
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        self.watcher
+            .restore_watching_below(full_dir, self.root_path());
+        for map in [&self.invalidator_map, &self.dir_invalidator_map] {
+            for (_, invalidators) in map
+                .lock()
+                .unwrap()
+                .drain_filter(|path, _| Path::new(path).starts_with(full_dir))
+            {
+                invalidators.into_iter().for_each(|i| i.invalidate());
+            }
+        }
+        if created {
+            if let Some(parent) = full_dir.parent() {
+                if let Some(invalidators) = self
+                    .dir_invalidator_map
+                    .lock()
+                    .unwrap()
+                    .remove(&path_to_key(parent))
+                {
+                    invalidators.into_iter().for_each(|i| i.invalidate());
+                }
+            }
+        }
+    }
+}
+
+struct StagedFile {
+    relative_path: PathBuf,
+    content: ReadRef<FileContent>,
+    unchanged: bool,
+}
+
+/// Lists the paths of all files in `dir` relative to it.
+fn list_files(dir: &Path) -> io::Result<HashSet<PathBuf>> {
+    fn list(dir: &Path, relative_dir: &Path, files: &mut HashSet<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let relative_path = relative_dir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                list(&entry.path(), &relative_path, files)?;
+            } else {
+                files.insert(relative_path);
+            }
+        }
+        Ok(())
+    }
+    let mut files = HashSet::new();
+    match list(dir, Path::new(""), &mut files) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        result => result?,
+    }
+    Ok(files)
+}
+
+fn sibling_path(dir: &Path, kind: &str) -> Result<PathBuf> {
+    let (Some(parent), Some(name)) = (dir.parent(), dir.file_name()) else {
+        bail!("{} has no parent directory", dir.display());
+    };
+    Ok(parent.join(format!(
+        ".{}.{}-{}-{}",
+        name.to_string_lossy(),
+        kind,
+        process::id(),
+        NEXT_STAGING_ID.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+/// Syncs a directory, so that the entries created in it are persisted. That
+/// isn't possible on Windows, where renames are persisted by the filesystem.
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(not(target_os = "windows"))]
+    fs::File::open(dir)?.sync_all()?;
+    #[cfg(target_os = "windows")]
+    let _ = dir;
+    Ok(())
+}
+
+fn stage_file(file: &StagedFile, old_dir: &Path, staging: &Path) -> Result<()> {
+    let FileContent::Content(content) = &*file.content else {
+        return Ok(());
+    };
+    let path = staging.join(&file.relative_path);
+    if file.unchanged && fs::hard_link(old_dir.join(&file.relative_path), &path).is_ok() {
+        return Ok(());
+    }
+    let mut f = fs::File::create(&path)?;
+    io::copy(&mut content.read(), &mut f)?;
+    #[cfg(target_family = "unix")]
+    f.set_permissions(content.meta.permissions.into())?;
+    f.sync_all()?;
+    Ok(())
+}
+
+fn stage(dir: &Path, staging: &Path, files: &[StagedFile]) -> Result<()> {
+    let mut dirs = vec![staging.to_path_buf()];
+    fs::create_dir_all(staging)?;
+    for file in files {
+        if let Some(parent) = file.relative_path.parent() {
+            let parent = staging.join(parent);
+            if !parent.exists() {
+                fs::create_dir_all(&parent)?;
+                dirs.extend(
+                    parent
+                        .ancestors()
+                        .take_while(|ancestor| *ancestor != staging)
+                        .map(Path::to_path_buf),
+                );
+            }
+        }
+        stage_file(file, dir, staging)
+            .with_context(|| format!("failed to stage {}", file.relative_path.display()))?;
+    }
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        sync_dir(&dir)?;
+    }
+    Ok(())
+}
+
+/// Stages `files` next to `dir` and moves them in place of `dir`.
+fn swap_dir(dir: &Path, files: &[StagedFile]) -> Result<()> {
+    let staging = sibling_path(dir, "staging")?;
+    if let Err(err) = stage(dir, &staging, files) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(err);
+    }
+    let parent = dir.parent().unwrap();
+    if dir.exists() {
+        let old = match replace_dir(dir, &staging) {
+            Ok(old) => old,
+            Err(err) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(err);
+            }
+        };
+        sync_dir(parent)?;
+        fs::remove_dir_all(old)?;
+    } else {
+        fs::create_dir_all(parent)?;
+        fs::rename(&staging, dir)?;
+        sync_dir(parent)?;
+    }
+    Ok(())
+}
+
+/// Exchanges `dir` with `staging` atomically, so `staging` contains the
+/// previous contents of `dir` afterwards. Falls back to [move_dir_aside] when
+/// the kernel or the file system doesn't support exchanging, e.g. on kernels
+/// before 3.15 or some network file systems.
+#[cfg(target_os = "linux")]
+fn replace_dir(dir: &Path, staging: &Path) -> Result<PathBuf> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let from = CString::new(staging.as_os_str().as_bytes())?;
+    let to = CString::new(dir.as_os_str().as_bytes())?;
+    // SAFETY: Both paths are valid, nul terminated strings that outlive the
+    // call. The glibc wrapper for renameat2 is too recent to rely on.
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            from.as_ptr(),
+            libc::AT_FDCWD,
+            to.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    if result != 0 {
+        let err = io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::EINVAL | libc::ENOSYS) => move_dir_aside(dir, staging),
+            _ => Err(err.into()),
+        };
+    }
+    Ok(staging.to_path_buf())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn replace_dir(dir: &Path, staging: &Path) -> Result<PathBuf> {
+    move_dir_aside(dir, staging)
+}
+
+/// Moves `dir` aside and `staging` in its place. Returns where the previous
+/// contents of `dir` have been moved to.
+fn move_dir_aside(dir: &Path, staging: &Path) -> Result<PathBuf> {
+    let old = sibling_path(dir, "old")?;
+    fs::rename(dir, &old)?;
+    if let Err(err) = fs::rename(staging, dir) {
+        let _ = fs::rename(&old, dir);
+        return Err(err.into());
+    }
+    Ok(old)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::move_dir_aside;
+
+    #[test]
+    fn moving_aside_replaces_the_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("dir");
+        let staging = root.path().join("staging");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("old.txt"), "old").unwrap();
+        fs::create_dir(&staging).unwrap();
+        fs::write(staging.join("new.txt"), "new").unwrap();
+
+        let old = move_dir_aside(&dir, &staging).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("new.txt")).unwrap(), "new");
+        assert!(!dir.join("old.txt").exists());
+        assert_eq!(fs::read_to_string(old.join("old.txt")).unwrap(), "old");
+        assert!(!staging.exists());
+    }
+}
@@ -0,0 +1,147 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::{StringVc, StringsVc},
+    TurboTasks,
+};
+use turbo_tasks_fs::{
+    memory::MemoryFileSystemVc, DirectoryContent, File, FileContent, FileSystem, FileSystemPathVc,
+    LinkContent, LinkType,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static READS: AtomicUsize = AtomicUsize::new(0);
+static LISTINGS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn changes_invalidate_reads() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let fs = tt
+        .run_once(async {
+            MemoryFileSystemVc::new("memory".to_string())
+                .resolve()
+                .await
+        })
+        .await
+        .unwrap();
+    let memory = tt.run_once(async move { fs.await }).await.unwrap();
+    memory.write_file("src/a.txt", "hello").unwrap();
+    memory.write_file("src/b.txt", "b").unwrap();
+    memory.write_symlink("link", "src").unwrap();
+
+    let read = || {
+        tt.run_once(async move {
+            let root = fs.root();
+            let text = read_text(root.join("link/a.txt"))
+                .strongly_consistent()
+                .await?;
+            let names = list_dir(root.join("src")).strongly_consistent().await?;
+            Ok((text.clone_value(), names.clone_value()))
+        })
+    };
+    let (text, names) = read().await.unwrap();
+    assert_eq!(text, "hello");
+    assert_eq!(names, vec!["a.txt", "b.txt"]);
+    assert_eq!(READS.load(Ordering::SeqCst), 1);
+    assert_eq!(LISTINGS.load(Ordering::SeqCst), 1);
+
+    // Changing the content of a file doesn't change its directory
+    memory.write_file("src/b.txt", "changed").unwrap();
+    read().await.unwrap();
+    assert_eq!(READS.load(Ordering::SeqCst), 1);
+    assert_eq!(LISTINGS.load(Ordering::SeqCst), 1);
+
+    memory.write_file("src/a.txt", "world").unwrap();
+    let (text, _) = read().await.unwrap();
+    assert_eq!(text, "world");
+    assert_eq!(READS.load(Ordering::SeqCst), 2);
+    assert_eq!(LISTINGS.load(Ordering::SeqCst), 1);
+
+    memory.remove("src/b.txt").unwrap();
+    let (_, names) = read().await.unwrap();
+    assert_eq!(names, vec!["a.txt"]);
+    assert_eq!(LISTINGS.load(Ordering::SeqCst), 2);
+
+    // Retargeting the symlink invalidates reads through it
+    memory.write_file("other/a.txt", "other").unwrap();
+    memory.write_symlink("link", "/other").unwrap();
+    let (text, _) = read().await.unwrap();
+    assert_eq!(text, "other");
+}
+
+#[tokio::test]
+async fn implements_file_system() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.run_once(async {
+        let fs = MemoryFileSystemVc::new("memory".to_string());
+        let root = fs.root();
+        root.join("dir/file.txt")
+            .write(File::from("content").into())
+            .await?;
+        root.join("dir/link")
+            .write_link(
+                LinkContent::Link {
+                    target: "file.txt".to_string(),
+                    link_type: LinkType::UNSET,
+                }
+                .cell(),
+            )
+            .await?;
+
+        let FileContent::Content(file) = &*root.join("dir/link").read().await? else {
+            panic!("file not found");
+        };
+        assert_eq!(file.content().to_str()?, "content");
+        let LinkContent::Link { target, .. } = &*root.join("dir/link").read_link().await? else {
+            panic!("link not found");
+        };
+        assert_eq!(target, "file.txt");
+        let DirectoryContent::Entries(entries) = &*root.join("dir").read_dir().await? else {
+            panic!("directory not found");
+        };
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(
+            &*root.join("missing.txt").read().await?,
+            FileContent::NotFound
+        ));
+        assert!(matches!(
+            &*root.join("dir/file.txt/child").read_dir().await?,
+            DirectoryContent::NotFound
+        ));
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::function]
+async fn read_text(path: FileSystemPathVc) -> Result<StringVc> {
+    READS.fetch_add(1, Ordering::SeqCst);
+    Ok(StringVc::cell(match &*path.read().await? {
+        FileContent::Content(file) => file.content().to_str()?.to_string(),
+        FileContent::NotFound => String::new(),
+    }))
+}
+
+#[turbo_tasks::function]
+async fn list_dir(path: FileSystemPathVc) -> Result<StringsVc> {
+    LISTINGS.fetch_add(1, Ordering::SeqCst);
+    let mut names = match &*path.read_dir().await? {
+        DirectoryContent::Entries(entries) => {
+            entries.iter().map(|(name, _)| name.clone()).collect()
+        }
+        DirectoryContent::NotFound => Vec::new(),
+    };
+    names.sort();
+    Ok(StringsVc::cell(names))
+}
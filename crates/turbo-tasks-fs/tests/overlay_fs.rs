@@ -0,0 +1,150 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::{StringVc, StringsVc},
+    TurboTasks,
+};
+use turbo_tasks_fs::{
+    memory::MemoryFileSystemVc, overlay::OverlayFileSystemVc, DirectoryContent, File, FileContent,
+    FileSystem, FileSystemPathVc, FileSystemVc,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn layers() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let (top, bottom, overlay) = tt
+        .run_once(async {
+            let top = MemoryFileSystemVc::new("top".to_string()).resolve().await?;
+            let bottom = MemoryFileSystemVc::new("bottom".to_string())
+                .resolve()
+                .await?;
+            let overlay =
+                OverlayFileSystemVc::new("overlay".to_string(), vec![top.into(), bottom.into()])
+                    .resolve()
+                    .await?;
+            Ok((top, bottom, FileSystemVc::from(overlay)))
+        })
+        .await
+        .unwrap();
+    let (top_memory, bottom_memory) = tt
+        .run_once(async move { Ok((top.await?, bottom.await?)) })
+        .await
+        .unwrap();
+    bottom_memory.write_file("a.txt", "bottom a").unwrap();
+    bottom_memory.write_file("b.txt", "bottom b").unwrap();
+    bottom_memory.write_file("dir/c.txt", "bottom c").unwrap();
+    bottom_memory.write_file("lib/f.txt", "bottom f").unwrap();
+    top_memory.write_file("a.txt", "top a").unwrap();
+    top_memory.write_file("dir/d.txt", "top d").unwrap();
+
+    let read = |path: &'static str| {
+        tt.run_once(async move {
+            let text = read_text(overlay.root().join(path))
+                .strongly_consistent()
+                .await?;
+            Ok(text.clone_value())
+        })
+    };
+    let list = |path: &'static str| {
+        tt.run_once(async move {
+            let names = list_dir(overlay.root().join(path))
+                .strongly_consistent()
+                .await?;
+            Ok(names.clone_value())
+        })
+    };
+
+    // Reads fall through the layers and directories are merged
+    assert_eq!(read("a.txt").await.unwrap(), "top a");
+    assert_eq!(read("b.txt").await.unwrap(), "bottom b");
+    assert_eq!(
+        list("").await.unwrap(),
+        vec!["a.txt", "b.txt", "dir", "lib"]
+    );
+    assert_eq!(list("dir").await.unwrap(), vec!["c.txt", "d.txt"]);
+
+    // Changes in every layer invalidate reads
+    bottom_memory.write_file("b.txt", "changed b").unwrap();
+    assert_eq!(read("b.txt").await.unwrap(), "changed b");
+    top_memory.write_file("b.txt", "top b").unwrap();
+    assert_eq!(read("b.txt").await.unwrap(), "top b");
+    bottom_memory.write_file("e.txt", "bottom e").unwrap();
+    assert_eq!(
+        list("").await.unwrap(),
+        vec!["a.txt", "b.txt", "dir", "e.txt", "lib"]
+    );
+
+    // Writes go to the top layer, deletions hide lower layers
+    tt.run_once(async move {
+        let root = overlay.root();
+        root.join("new.txt").write(File::from("new").into()).await?;
+        root.join("b.txt")
+            .write(FileContent::NotFound.cell())
+            .await?;
+        root.join("lib").write(FileContent::NotFound.cell()).await?;
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(read("new.txt").await.unwrap(), "new");
+    assert_eq!(read("b.txt").await.unwrap(), "<not found>");
+    assert_eq!(read("lib/f.txt").await.unwrap(), "<not found>");
+    assert_eq!(list("lib").await.unwrap(), Vec::<String>::new());
+    assert_eq!(
+        list("").await.unwrap(),
+        vec!["a.txt", "dir", "e.txt", "new.txt"]
+    );
+    let top_names = tt
+        .run_once(async move {
+            let names = list_dir(FileSystemVc::from(top).root())
+                .strongly_consistent()
+                .await?;
+            Ok(names.clone_value())
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        top_names,
+        vec![".wh.b.txt", ".wh.lib", "a.txt", "dir", "new.txt"]
+    );
+
+    // Writing a deleted path makes it visible again
+    tt.run_once(async move {
+        overlay
+            .root()
+            .join("b.txt")
+            .write(File::from("again").into())
+            .await?;
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(read("b.txt").await.unwrap(), "again");
+}
+
+#[turbo_tasks::function]
+async fn read_text(path: FileSystemPathVc) -> Result<StringVc> {
+    Ok(StringVc::cell(match &*path.read().await? {
+        FileContent::Content(file) => file.content().to_str()?.to_string(),
+        FileContent::NotFound => "<not found>".to_string(),
+    }))
+}
+
+#[turbo_tasks::function]
+async fn list_dir(path: FileSystemPathVc) -> Result<StringsVc> {
+    let mut names = match &*path.read_dir().await? {
+        DirectoryContent::Entries(entries) => {
+            entries.iter().map(|(name, _)| name.clone()).collect()
+        }
+        DirectoryContent::NotFound => Vec::new(),
+    };
+    names.sort();
+    Ok(StringsVc::cell(names))
+}
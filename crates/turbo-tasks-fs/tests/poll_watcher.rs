@@ -0,0 +1,71 @@
+#![feature(min_specialization)]
+
+use std::{fs, time::Duration};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::StringVc, TurboTasks};
+use turbo_tasks_fs::{
+    DiskFileSystemVc, FileContent, FileSystem, FileSystemPathVc, FileSystemVc, WatchMode,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn polling_invalidates_reads() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let temp = tempfile::TempDir::new().unwrap();
+    fs::write(temp.path().join("a.txt"), "hello").unwrap();
+    let root = temp.path().to_string_lossy().to_string();
+
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let fs = tt
+        .run_once(async move {
+            let fs = DiskFileSystemVc::new("project".to_string(), root);
+            fs.await?.start_watching_with_mode(
+                WatchMode::Polling {
+                    interval: Duration::from_millis(10),
+                },
+                false,
+            )?;
+            Ok(fs)
+        })
+        .await
+        .unwrap();
+    let read = |path: &'static str| {
+        tt.run_once(async move {
+            let text = read_text(FileSystemVc::from(fs).root().join(path))
+                .strongly_consistent()
+                .await?;
+            Ok(text.clone_value())
+        })
+    };
+    assert_eq!(read("a.txt").await.unwrap(), "hello");
+    assert_eq!(read("b.txt").await.unwrap(), "<not found>");
+
+    fs::write(temp.path().join("a.txt"), "changed").unwrap();
+    fs::write(temp.path().join("b.txt"), "new").unwrap();
+    let mut attempts = 0;
+    while read("a.txt").await.unwrap() != "changed" || read("b.txt").await.unwrap() != "new" {
+        attempts += 1;
+        assert!(attempts < 500, "changes were not detected");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Selecting polling is not a fallback
+    let fallback = tt
+        .run_once(async move { Ok(fs.watch_fallback().await?.clone_value()) })
+        .await
+        .unwrap();
+    assert_eq!(fallback, None);
+}
+
+#[turbo_tasks::function]
+async fn read_text(path: FileSystemPathVc) -> Result<StringVc> {
+    Ok(StringVc::cell(match &*path.read().await? {
+        FileContent::Content(file) => file.content().to_str()?.to_string(),
+        FileContent::NotFound => "<not found>".to_string(),
+    }))
+}
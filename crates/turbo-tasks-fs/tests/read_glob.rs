@@ -0,0 +1,138 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{primitives::StringsVc, TurboTasks};
+use turbo_tasks_fs::{
+    glob::GlobVc, memory::MemoryFileSystemVc, FileSystem, FileSystemPathVc, FileSystemVc,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn ignore_files() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let fs = tt
+        .run_once(async {
+            MemoryFileSystemVc::new("memory".to_string())
+                .resolve()
+                .await
+        })
+        .await
+        .unwrap();
+    let memory = tt.run_once(async move { fs.await }).await.unwrap();
+    memory.write_file(".git/HEAD", "ref").unwrap();
+    memory
+        .write_file(".gitignore", "node_modules\n*.log\n")
+        .unwrap();
+    memory.write_file("src/a.ts", "a").unwrap();
+    memory.write_file("src/b.log", "b").unwrap();
+    memory.write_file("node_modules/x/index.ts", "x").unwrap();
+    memory
+        .write_file("packages/p/.ignore", "!keep.log")
+        .unwrap();
+    memory.write_file("packages/p/keep.log", "keep").unwrap();
+    memory.write_file("packages/p/other.log", "other").unwrap();
+
+    let glob = |glob: &'static str| {
+        tt.run_once(async move {
+            let root = FileSystemVc::from(fs).root();
+            let paths = glob_paths(root, glob).strongly_consistent().await?;
+            Ok(paths.clone_value())
+        })
+    };
+    assert_eq!(
+        glob("**").await.unwrap(),
+        vec![
+            ".gitignore",
+            "packages",
+            "packages/p",
+            "packages/p/.ignore",
+            "packages/p/keep.log",
+            "src",
+            "src/a.ts",
+        ]
+    );
+    assert_eq!(
+        glob("!**/*.ts").await.unwrap(),
+        vec![
+            ".gitignore",
+            "packages",
+            "packages/p",
+            "packages/p/.ignore",
+            "packages/p/keep.log",
+            "src",
+        ]
+    );
+
+    // Editing an ignore file invalidates the globs
+    memory.write_file(".gitignore", "node_modules\n").unwrap();
+    assert_eq!(
+        glob("**/*.log").await.unwrap(),
+        vec!["packages/p/keep.log", "packages/p/other.log", "src/b.log"]
+    );
+}
+
+#[tokio::test]
+async fn negated_globs_skip_excluded_directories() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let fs = tt
+        .run_once(async {
+            MemoryFileSystemVc::new("memory".to_string())
+                .resolve()
+                .await
+        })
+        .await
+        .unwrap();
+    let memory = tt.run_once(async move { fs.await }).await.unwrap();
+    memory.write_file("src/a.ts", "a").unwrap();
+    memory.write_file("node_modules/x/index.ts", "x").unwrap();
+    memory
+        .write_file("apps/app/node_modules/y/index.ts", "y")
+        .unwrap();
+
+    let directories = tt
+        .run_once(async move {
+            let root = FileSystemVc::from(fs).root();
+            let directories = glob_directories(root, "!**/node_modules/**")
+                .strongly_consistent()
+                .await?;
+            Ok(directories.clone_value())
+        })
+        .await
+        .unwrap();
+    // Nothing inside of node_modules can match, so it isn't read
+    assert_eq!(directories, vec!["apps", "apps/app", "src"]);
+}
+
+/// Returns the directories that have been read to find the matches of `glob`.
+#[turbo_tasks::function]
+async fn glob_directories(directory: FileSystemPathVc, glob: &str) -> Result<StringsVc> {
+    let mut directories = Vec::new();
+    let mut queue = vec![directory.read_glob(GlobVc::new(glob), true)];
+    while let Some(result) = queue.pop() {
+        let result = result.await?;
+        directories.extend(result.inner.keys().cloned());
+        queue.extend(result.inner.values().copied());
+    }
+    directories.sort();
+    Ok(StringsVc::cell(directories))
+}
+
+#[turbo_tasks::function]
+async fn glob_paths(directory: FileSystemPathVc, glob: &str) -> Result<StringsVc> {
+    let mut paths = Vec::new();
+    let mut queue = vec![directory.read_glob_with_ignore_files(GlobVc::new(glob), true)];
+    while let Some(result) = queue.pop() {
+        let result = result.await?;
+        paths.extend(result.results.keys().cloned());
+        queue.extend(result.inner.values().copied());
+    }
+    paths.sort();
+    Ok(StringsVc::cell(paths))
+}
@@ -0,0 +1,78 @@
+#![feature(min_specialization)]
+
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::StringVc,
+    record::{RecordedEvent, Recording},
+    TurboTasks,
+};
+use turbo_tasks_fs::{DiskFileSystemVc, FileContent, FileSystem, FileSystemPathVc, FileSystemVc};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+async fn run_session(root: &Path, recording: &Path, replay: Option<Recording>) -> (String, String) {
+    let tt = TurboTasks::new(MemoryBackend::default());
+    match replay {
+        Some(replay) => tt.start_replay(replay, recording).unwrap(),
+        None => tt.start_recording(recording).unwrap(),
+    }
+    let root = root.to_string_lossy().to_string();
+    let texts = tt
+        .run_once(async move {
+            let fs = FileSystemVc::from(DiskFileSystemVc::new("project".to_string(), root));
+            let a = read_text(fs.root().join("a.txt"))
+                .strongly_consistent()
+                .await?;
+            let b = read_text(fs.root().join("b.txt"))
+                .strongly_consistent()
+                .await?;
+            Ok((a.clone_value(), b.clone_value()))
+        })
+        .await
+        .unwrap();
+    tt.stop_and_wait().await;
+    tt.flush_recording().unwrap();
+    texts
+}
+
+#[tokio::test]
+async fn replay_reads_recorded_files() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let project = tempfile::tempdir().unwrap();
+    let recordings = tempfile::tempdir().unwrap();
+    fs::write(project.path().join("a.txt"), "recorded").unwrap();
+
+    let texts = run_session(project.path(), &recordings.path().join("a"), None).await;
+    assert_eq!(texts, ("recorded".to_string(), "<not found>".to_string()));
+    let recording = Recording::read(&recordings.path().join("a")).unwrap();
+    assert!(recording.events.contains(&RecordedEvent::FileRead {
+        path: "project/a.txt".to_string(),
+        content: Some(b"recorded".to_vec()),
+    }));
+
+    // The replay sees the recorded files, not the current ones
+    fs::write(project.path().join("a.txt"), "changed").unwrap();
+    fs::write(project.path().join("b.txt"), "new").unwrap();
+    let texts = run_session(
+        project.path(),
+        &recordings.path().join("b"),
+        Some(recording.clone()),
+    )
+    .await;
+    assert_eq!(texts, ("recorded".to_string(), "<not found>".to_string()));
+    let replayed = Recording::read(&recordings.path().join("b")).unwrap();
+    assert_eq!(recording.diff(&replayed), None);
+}
+
+#[turbo_tasks::function]
+async fn read_text(path: FileSystemPathVc) -> Result<StringVc> {
+    Ok(StringVc::cell(match &*path.read().await? {
+        FileContent::Content(file) => file.content().to_str()?.to_string(),
+        FileContent::NotFound => "<not found>".to_string(),
+    }))
+}
@@ -0,0 +1,84 @@
+#![feature(min_specialization)]
+
+use std::{fs, path::Path};
+
+use turbo_tasks::TurboTasks;
+use turbo_tasks_fs::{DiskFileSystemVc, File, FileSystem, FileSystemVc, WriteBatchVc};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+#[tokio::test]
+async fn write_dir_atomic() {
+    lazy_static::initialize(&REGISTER);
+    turbo_tasks_fs::register();
+    let temp = tempfile::TempDir::new().unwrap();
+    let root = temp.path().to_string_lossy().to_string();
+    let out = temp.path().join("out");
+
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let disk_fs = tt
+        .run_once(async move {
+            DiskFileSystemVc::new("output".to_string(), root)
+                .resolve()
+                .await
+        })
+        .await
+        .unwrap();
+    let write = |files: &'static [(&'static str, &'static str)]| {
+        tt.run_once(async move {
+            let dir = FileSystemVc::from(disk_fs).root().join("out");
+            let batch = files
+                .iter()
+                .map(|(path, content)| (dir.join(path), File::from(*content).into()))
+                .collect();
+            disk_fs
+                .write_dir_atomic(dir, WriteBatchVc::cell(batch))
+                .await?;
+            Ok(())
+        })
+    };
+
+    write(&[("a.txt", "a"), ("b/c.txt", "c")]).await.unwrap();
+    assert_eq!(read(&out.join("a.txt")), "a");
+    assert_eq!(read(&out.join("b/c.txt")), "c");
+    let unchanged_modified = fs::metadata(out.join("a.txt")).unwrap().modified().unwrap();
+
+    // Unchanged files keep their modification time, missing files are removed
+    write(&[("a.txt", "a"), ("b/c.txt", "changed"), ("d.txt", "d")])
+        .await
+        .unwrap();
+    assert_eq!(read(&out.join("b/c.txt")), "changed");
+    assert_eq!(read(&out.join("d.txt")), "d");
+    assert_eq!(
+        fs::metadata(out.join("a.txt")).unwrap().modified().unwrap(),
+        unchanged_modified
+    );
+    write(&[("a.txt", "a"), ("d.txt", "d")]).await.unwrap();
+    assert!(!out.join("b").exists());
+
+    // Staging directories don't remain next to the output
+    let names = fs::read_dir(temp.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["out"]);
+
+    // Paths outside of the directory are rejected
+    let result = tt
+        .run_once(async move {
+            let root = FileSystemVc::from(disk_fs).root();
+            let batch = vec![(root.join("a.txt"), File::from("a").into())];
+            disk_fs
+                .write_dir_atomic(root.join("out"), WriteBatchVc::cell(batch))
+                .await?;
+            Ok(())
+        })
+        .await;
+    assert!(result.is_err());
+}
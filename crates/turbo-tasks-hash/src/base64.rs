@@ -0,0 +1,7 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+/// Encodes a byte slice into an unpadded base64url string, which is safe to
+/// use in URLs and file names.
+pub fn encode_base64url(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -5,15 +5,23 @@
 //! file name.
 
 mod base16;
+mod base64;
+mod blake3;
 mod deterministic_hash;
 mod hex;
 mod md4;
+mod sha256;
+mod sri;
 mod xxh3_hash64;
 
 pub use crate::{
     base16::encode_base16,
+    base64::encode_base64url,
+    blake3::{hash_blake3, Blake3Hasher},
     deterministic_hash::{DeterministicHash, DeterministicHasher},
     hex::{encode_hex, encode_hex_string},
     md4::hash_md4,
+    sha256::{hash_sha256, Sha256Hasher},
+    sri::{encode_sri, SriAlgorithm, SriHasher},
     xxh3_hash64::{hash_xxh3_hash64, Xxh3Hash64Hasher},
 };
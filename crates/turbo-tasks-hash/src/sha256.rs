@@ -0,0 +1,50 @@
+use sha2::{Digest, Sha256};
+
+use crate::{DeterministicHash, DeterministicHasher};
+
+/// Hash some content with the SHA-256 cryptographic hash function.
+///
+/// Returns a 32-byte hash digest.
+pub fn hash_sha256(content: &[u8]) -> [u8; 32] {
+    Sha256::digest(content).into()
+}
+
+/// SHA-256 hasher.
+#[derive(Clone, Default)]
+pub struct Sha256Hasher(Sha256);
+
+impl Sha256Hasher {
+    /// Create a new hasher.
+    pub fn new() -> Self {
+        Self(Sha256::new())
+    }
+
+    /// Uses the DeterministicHash trait to hash the input in a
+    /// cross-platform way.
+    pub fn write_value<T: DeterministicHash>(&mut self, input: T) {
+        input.deterministic_hash(self);
+    }
+
+    /// Uses the DeterministicHash trait to hash the input in a
+    /// cross-platform way.
+    pub fn write_ref<T: DeterministicHash>(&mut self, input: &T) {
+        input.deterministic_hash(self);
+    }
+
+    /// Finish the hash computation and return the 32-byte digest.
+    pub fn digest(&self) -> [u8; 32] {
+        self.0.clone().finalize().into()
+    }
+}
+
+impl DeterministicHasher for Sha256Hasher {
+    /// Returns the first 8 bytes of the digest.
+    fn finish(&self) -> u64 {
+        let digest = self.digest();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+}
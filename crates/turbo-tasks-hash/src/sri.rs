@@ -0,0 +1,136 @@
+use std::fmt::{self, Display, Formatter};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::DeterministicHasher;
+
+/// The hash algorithms of
+/// [subresource integrity](https://www.w3.org/TR/SRI/) metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SriAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl SriAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SriAlgorithm::Sha256 => "sha256",
+            SriAlgorithm::Sha384 => "sha384",
+            SriAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl Display for SriAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Computes the subresource integrity metadata of some content, e.g.
+/// `sha384-oqVuAfXRKap7fdgcCY5uykM6+R9GqQ8K/uxy9rx7HNQlGYl1kPzQho1wx4JwY8wC`.
+pub fn encode_sri(algorithm: SriAlgorithm, content: &[u8]) -> String {
+    let mut hasher = SriHasher::new(algorithm);
+    hasher.write_bytes(content);
+    hasher.integrity()
+}
+
+#[derive(Clone)]
+enum SriDigest {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+/// Computes subresource integrity metadata from content that is written in
+/// multiple parts.
+#[derive(Clone)]
+pub struct SriHasher(SriDigest);
+
+impl SriHasher {
+    /// Create a new hasher.
+    pub fn new(algorithm: SriAlgorithm) -> Self {
+        Self(match algorithm {
+            SriAlgorithm::Sha256 => SriDigest::Sha256(Sha256::new()),
+            SriAlgorithm::Sha384 => SriDigest::Sha384(Sha384::new()),
+            SriAlgorithm::Sha512 => SriDigest::Sha512(Sha512::new()),
+        })
+    }
+
+    pub fn algorithm(&self) -> SriAlgorithm {
+        match self.0 {
+            SriDigest::Sha256(_) => SriAlgorithm::Sha256,
+            SriDigest::Sha384(_) => SriAlgorithm::Sha384,
+            SriDigest::Sha512(_) => SriAlgorithm::Sha512,
+        }
+    }
+
+    /// Finish the hash computation and return the digest.
+    pub fn digest(&self) -> Vec<u8> {
+        match &self.0 {
+            SriDigest::Sha256(hasher) => hasher.clone().finalize().to_vec(),
+            SriDigest::Sha384(hasher) => hasher.clone().finalize().to_vec(),
+            SriDigest::Sha512(hasher) => hasher.clone().finalize().to_vec(),
+        }
+    }
+
+    /// Finish the hash computation and return the integrity metadata.
+    pub fn integrity(&self) -> String {
+        format!("{}-{}", self.algorithm(), STANDARD.encode(self.digest()))
+    }
+}
+
+impl DeterministicHasher for SriHasher {
+    /// Returns the first 8 bytes of the digest.
+    fn finish(&self) -> u64 {
+        let digest = self.digest();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        match &mut self.0 {
+            SriDigest::Sha256(hasher) => hasher.update(bytes),
+            SriDigest::Sha384(hasher) => hasher.update(bytes),
+            SriDigest::Sha512(hasher) => hasher.update(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_sri, SriAlgorithm, SriHasher};
+    use crate::{
+        encode_base64url, encode_hex_string, hash_blake3, hash_sha256, DeterministicHasher,
+    };
+
+    #[test]
+    fn known_digests() {
+        assert_eq!(
+            encode_hex_string(&hash_sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            encode_hex_string(&hash_blake3(b"abc")),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_eq!(encode_base64url(&[0xfb, 0xff, 0xfe]), "-__-");
+    }
+
+    #[test]
+    fn integrity() {
+        assert_eq!(
+            encode_sri(SriAlgorithm::Sha384, b"alert('Hello, world.');"),
+            "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO"
+        );
+        let mut hasher = SriHasher::new(SriAlgorithm::Sha384);
+        hasher.write_bytes(b"alert('Hello, ");
+        hasher.write_bytes(b"world.');");
+        assert_eq!(
+            hasher.integrity(),
+            encode_sri(SriAlgorithm::Sha384, b"alert('Hello, world.');")
+        );
+    }
+}
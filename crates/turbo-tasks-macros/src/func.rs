@@ -1,9 +1,10 @@
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
-use proc_macro_error::abort;
-use quote::quote;
+use proc_macro_error::{abort, abort_if_dirty, emit_error};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::{
-    punctuated::Punctuated, spanned::Spanned, FnArg, Pat, PatIdent, PatType, Receiver, ReturnType,
-    Signature, Token, Type, TypePath, TypeReference,
+    punctuated::Punctuated, spanned::Spanned, AngleBracketedGenericArguments, FnArg,
+    GenericArgument, Pat, PatIdent, PatType, PathArguments, Receiver, ReturnType, Signature, Token,
+    Type, TypePath, TypeReference, TypeTuple,
 };
 
 use crate::util::*;
@@ -25,6 +26,8 @@ pub fn gen_native_function_code(
     output_type: &Type,
     self_ref_type: Option<(&Ident, SelfType<'_>)>,
 ) -> (TokenStream2, Vec<TokenStream2>) {
+    validate_signature(inputs, output_type);
+
     let mut input_extraction = Vec::new();
     let mut input_convert = Vec::new();
     let mut input_clone = Vec::new();
@@ -115,7 +118,7 @@ pub fn gen_native_function_code(
                     } else {
                         quote! { #elem }
                     };
-                    input_convert.push(quote! {
+                    input_convert.push(quote_spanned! { elem.span() =>
                         let #pat: #ty = turbo_tasks::FromTaskInput::try_from(#pat)?;
                     });
                     input_clone.push(quote! {
@@ -125,7 +128,7 @@ pub fn gen_native_function_code(
                         #and_token #mutability #pat
                     });
                 } else {
-                    input_convert.push(quote! {
+                    input_convert.push(quote_spanned! { ty.span() =>
                         let #pat: #ty = turbo_tasks::FromTaskInput::try_from(#pat)?;
                     });
                     input_clone.push(quote! {
@@ -202,6 +205,116 @@ pub fn gen_native_function_code(
     )
 }
 
+const TASK_INPUT_HELP: &str = "arguments of turbo_tasks functions must be Vcs, `String`, `&str`, \
+                               `bool`, integers, or `Vec`s, `Option`s and tuples of those. Other \
+                               values can be wrapped in `Value<T>`, `TransientValue<T>` or \
+                               `TransientInstance<T>`";
+
+/// Types that are commonly used as arguments, but can't be task inputs.
+const NON_TASK_INPUT_TYPES: &[&str] = &[
+    "Box", "Rc", "Arc", "Cow", "Cell", "RefCell", "Mutex", "RwLock", "HashMap", "HashSet",
+    "BTreeMap", "BTreeSet", "IndexMap", "IndexSet", "Path", "PathBuf", "i8", "u8", "i64", "isize",
+    "i128", "u128", "f32", "f64", "char",
+];
+
+/// Reports arguments that can't be task inputs and return types that aren't
+/// Vcs. These would otherwise fail with trait bound errors deep in the
+/// generated code.
+fn validate_signature(inputs: &Punctuated<FnArg, Token![,]>, output_type: &Type) {
+    for input in inputs {
+        let FnArg::Typed(PatType { pat, ty, .. }) = input else {
+            continue;
+        };
+        if !matches!(&**pat, Pat::Ident(PatIdent { subpat: None, .. })) {
+            emit_error!(
+                pat,
+                "arguments of turbo_tasks functions must be identifiers, patterns are not \
+                 supported"
+            );
+        }
+        validate_input_type(ty);
+    }
+
+    let (raw_output_type, _) = unwrap_result_type(output_type);
+    if !is_empty_type(raw_output_type) && !is_vc_type(raw_output_type) {
+        emit_error!(
+            raw_output_type,
+            "turbo_tasks functions must return a Vc, `()` or a `Result` of those, found `{}`",
+            raw_output_type.to_token_stream();
+            help = "place the value in a cell, e.g. `StringVc::cell(value)` for a `String`"
+        );
+    }
+
+    abort_if_dirty();
+}
+
+fn validate_input_type(ty: &Type) {
+    match ty {
+        Type::Reference(TypeReference {
+            mutability: Some(_),
+            ..
+        }) => {
+            emit_error!(
+                ty,
+                "mutable references can't be task inputs, the arguments of turbo_tasks functions \
+                 are immutable"
+            );
+        }
+        Type::Reference(TypeReference { elem, .. }) | Type::Paren(syn::TypeParen { elem, .. }) => {
+            validate_input_type(elem)
+        }
+        Type::Tuple(TypeTuple { elems, .. }) => elems.iter().for_each(validate_input_type),
+        Type::Path(TypePath { qself: None, path }) => {
+            let Some(segment) = path.segments.last() else {
+                return;
+            };
+            let name = segment.ident.to_string();
+            if NON_TASK_INPUT_TYPES.contains(&name.as_str()) {
+                emit_error!(ty, "`{}` is not a task input", name; help = TASK_INPUT_HELP);
+            } else if name == "Vec" || name == "Option" {
+                if let PathArguments::AngleBracketed(AngleBracketedGenericArguments {
+                    args, ..
+                }) = &segment.arguments
+                {
+                    for arg in args {
+                        if let GenericArgument::Type(ty) = arg {
+                            validate_input_type(ty);
+                        }
+                    }
+                }
+            }
+        }
+        Type::ImplTrait(_) | Type::TraitObject(_) => {
+            emit_error!(
+                ty,
+                "traits can't be task inputs";
+                help = "use the Vc of a value trait, e.g. `AssetVc`, instead"
+            );
+        }
+        Type::Slice(_) | Type::Array(_) => {
+            emit_error!(ty, "slices and arrays can't be task inputs"; help = "use a `Vec` instead");
+        }
+        Type::Ptr(_) | Type::BareFn(_) | Type::Never(_) => {
+            emit_error!(ty, "`{}` is not a task input", ty.to_token_stream(); help = TASK_INPUT_HELP);
+        }
+        _ => {}
+    }
+}
+
+/// Checks if the type is a Vc by its name, e.g. `StringVc` or `Self` in the
+/// impl of a Vc.
+fn is_vc_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => {
+            path.segments.last().map_or(false, |segment| {
+                segment.ident == "Self" || segment.ident.to_string().ends_with("Vc")
+            })
+        }
+        Type::Paren(syn::TypeParen { elem, .. }) => is_vc_type(elem),
+        _ => false,
+    }
+}
+
 pub fn split_signature(sig: &Signature) -> (Signature, Signature, Type, TokenStream2) {
     let output_type = get_return_type(&sig.output);
     let inline_ident = get_internal_function_ident(&sig.ident);
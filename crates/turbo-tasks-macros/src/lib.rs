@@ -52,6 +52,17 @@ pub fn derive_deterministic_hash(input: TokenStream) -> TokenStream {
 /// `into: shared`: Compares with the existing value in the cell, before
 /// overriding it. Requires Value to implement [Eq].
 ///
+/// `version` argument (`#[turbo_tasks::value(version = 2)]`)
+///
+/// The version of the serialized format, which is persisted together with the
+/// value. Persisted values of other versions are discarded and recomputed.
+/// Defaults to 0.
+///
+/// `migrate` argument (`#[turbo_tasks::value(version = 2, migrate)]`)
+///
+/// Persisted values of other versions are converted by the
+/// `turbo_tasks::MigrateValue` implementation of the type instead.
+///
 /// TODO: add more documentation: presets, traits
 #[allow_internal_unstable(min_specialization, into_future, trivial_bounds)]
 #[proc_macro_error]
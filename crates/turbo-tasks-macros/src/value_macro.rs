@@ -1,13 +1,15 @@
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
+use proc_macro_error::{abort_if_dirty, emit_error};
 use quote::quote;
 use syn::{
     parse::{Parse, ParseStream},
     parse_macro_input,
     punctuated::Punctuated,
     spanned::Spanned,
-    Error, Fields, FieldsUnnamed, Item, ItemEnum, ItemStruct, Lit, LitStr, Meta, MetaNameValue,
-    Result, Token,
+    Attribute, Error, Field, Fields, FieldsUnnamed, GenericArgument, Item, ItemEnum, ItemStruct,
+    Lit, LitInt, LitStr, Meta, MetaList, MetaNameValue, NestedMeta, PathArguments, Result, Token,
+    Type, TypePath,
 };
 use turbo_tasks_macros_shared::{get_ref_ident, get_register_value_type_ident};
 
@@ -131,6 +133,8 @@ struct ValueArguments {
     cell_mode: CellMode,
     manual_eq: bool,
     transparent: bool,
+    version: Option<LitInt>,
+    migrate: bool,
 }
 
 impl Parse for ValueArguments {
@@ -141,6 +145,8 @@ impl Parse for ValueArguments {
             cell_mode: CellMode::Shared,
             manual_eq: false,
             transparent: false,
+            version: None,
+            migrate: false,
         };
         let punctuated: Punctuated<Meta, Token![,]> = input.parse_terminated(Meta::parse)?;
         for meta in punctuated {
@@ -195,12 +201,24 @@ impl Parse for ValueArguments {
                 ("transparent", Meta::Path(_)) => {
                     result.transparent = true;
                 }
+                (
+                    "version",
+                    Meta::NameValue(MetaNameValue {
+                        lit: Lit::Int(int), ..
+                    }),
+                ) => {
+                    int.base10_parse::<u32>()?;
+                    result.version = Some(int);
+                }
+                ("migrate", Meta::Path(_)) => {
+                    result.migrate = true;
+                }
                 (_, meta) => {
                     return Err(Error::new_spanned(
                         &meta,
                         format!(
                             "unexpected {:?}, expected \"shared\", \"into\", \"serialization\", \
-                             \"cell\", \"eq\", \"transparent\"",
+                             \"cell\", \"eq\", \"transparent\", \"version\", \"migrate\"",
                             meta
                         ),
                     ))
@@ -212,6 +230,70 @@ impl Parse for ValueArguments {
     }
 }
 
+/// Reports Vc fields that are ignored by `TraceRawVcs`. The cells they point
+/// to would be garbage collected while the value is still alive.
+fn validate_fields(item: &Item) {
+    let fields: Box<dyn Iterator<Item = &Field>> = match item {
+        Item::Struct(ItemStruct { fields, .. }) => Box::new(fields.iter()),
+        Item::Enum(ItemEnum { variants, .. }) => {
+            Box::new(variants.iter().flat_map(|variant| variant.fields.iter()))
+        }
+        _ => return,
+    };
+    for field in fields {
+        if !has_trace_ignore(&field.attrs) {
+            continue;
+        }
+        if is_traced_vc(&field.ty) {
+            emit_error!(
+                field.ty,
+                "Vc fields can't be ignored by TraceRawVcs";
+                help = "remove `trace_ignore` from `#[turbo_tasks(...)]`"
+            );
+        }
+    }
+    abort_if_dirty();
+}
+
+/// Whether `ty` is a Vc, or a type like `Vec<XVc>` or `HashMap<String, XVc>`
+/// with a Vc in any of its type arguments, so that TraceRawVcs needs to trace
+/// it. The type argument of `TraitRef<XVc>` and `PhantomData<XVc>` is only a
+/// marker.
+fn is_traced_vc(ty: &Type) -> bool {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return false;
+    };
+    let Some(segment) = path.segments.last() else {
+        return false;
+    };
+    let ident = segment.ident.to_string();
+    if ident.ends_with("Vc") {
+        return true;
+    }
+    if ident == "TraitRef" || ident == "PhantomData" {
+        return false;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return false;
+    };
+    arguments.args.iter().any(|argument| match argument {
+        GenericArgument::Type(ty) => is_traced_vc(ty),
+        _ => false,
+    })
+}
+
+fn has_trace_ignore(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("turbo_tasks"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(MetaList { nested, .. })) => Some(nested),
+            _ => None,
+        })
+        .flatten()
+        .any(|meta| matches!(meta, NestedMeta::Meta(Meta::Path(path)) if path.is_ident("trace_ignore")))
+}
+
 pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
     let item = parse_macro_input!(input as Item);
     let ValueArguments {
@@ -220,6 +302,8 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         cell_mode,
         manual_eq,
         transparent,
+        version,
+        migrate,
     } = parse_macro_input!(args as ValueArguments);
 
     let (vis, ident) = match &item {
@@ -235,6 +319,8 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
 
+    validate_fields(&item);
+
     let ref_ident = get_ref_ident(ident);
     let read_ref_ident = get_read_ref_ident(ident);
     let value_type_init_ident = get_value_type_init_ident(ident);
@@ -390,6 +476,16 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
 
+    let new_value_type = match version {
+        Some(version) => quote! { #new_value_type.with_version(#version) },
+        None => new_value_type,
+    };
+    let new_value_type = if migrate {
+        quote! { #new_value_type.with_migration::<#ident>() }
+    } else {
+        new_value_type
+    };
+
     let for_input_marker = match serialization_mode {
         SerializationMode::None | SerializationMode::Auto | SerializationMode::Custom => quote! {},
         SerializationMode::AutoForInput | SerializationMode::CustomForInput => quote! {
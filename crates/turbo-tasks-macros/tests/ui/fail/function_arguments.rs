@@ -0,0 +1,29 @@
+#![feature(min_specialization)]
+
+#[turbo_tasks::function]
+fn mutable_reference(value: &mut String) {}
+
+#[turbo_tasks::function]
+fn pattern((a, b): (u32, u32)) {}
+
+#[turbo_tasks::function]
+fn impl_trait(value: impl ToString) {}
+
+#[turbo_tasks::function]
+fn slice(values: &[u32]) {}
+
+#[turbo_tasks::function]
+fn nested_map(values: Option<std::collections::HashMap<String, String>>) {}
+
+#[turbo_tasks::value]
+struct Counter(u32);
+
+#[turbo_tasks::value_impl]
+impl CounterVc {
+    #[turbo_tasks::function]
+    fn new(value: f64) -> Self {
+        Counter(value as u32).cell()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#![feature(min_specialization)]
+
+#[turbo_tasks::function]
+fn string() -> String {
+    String::new()
+}
+
+#[turbo_tasks::function]
+async fn result() -> anyhow::Result<u32> {
+    Ok(1)
+}
+
+fn main() {}
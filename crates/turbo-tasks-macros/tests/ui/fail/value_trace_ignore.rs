@@ -0,0 +1,15 @@
+#![feature(min_specialization)]
+
+#[turbo_tasks::value]
+struct Wrapper {
+    #[turbo_tasks(trace_ignore)]
+    inner: turbo_tasks::primitives::StringVc,
+    #[turbo_tasks(trace_ignore)]
+    list: Vec<turbo_tasks::primitives::StringVc>,
+    #[turbo_tasks(trace_ignore)]
+    maybe: Option<turbo_tasks::primitives::StringVc>,
+    #[turbo_tasks(trace_ignore)]
+    map: std::collections::HashMap<String, turbo_tasks::primitives::StringVc>,
+}
+
+fn main() {}
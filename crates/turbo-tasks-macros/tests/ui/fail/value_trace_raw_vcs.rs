@@ -0,0 +1,11 @@
+#![feature(min_specialization)]
+
+#[derive(Debug, PartialEq, Eq)]
+struct Opaque;
+
+#[turbo_tasks::value(serialization = "none")]
+struct Wrapper {
+    opaque: Opaque,
+}
+
+fn main() {}
@@ -0,0 +1,38 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::{StringVc, StringsVc},
+    TransientInstance, Value,
+};
+
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(PartialOrd, Ord, Debug, Hash, Clone, Copy)]
+enum Mode {
+    Development,
+    Production,
+}
+
+#[turbo_tasks::function]
+fn inputs(
+    name: &str,
+    owned: String,
+    enabled: bool,
+    count: u32,
+    names: Vec<StringVc>,
+    fallback: Option<String>,
+    mode: Value<Mode>,
+    state: TransientInstance<String>,
+) -> StringVc {
+    StringVc::cell(name.to_string())
+}
+
+#[turbo_tasks::function]
+async fn result(names: StringsVc) -> Result<StringVc> {
+    Ok(StringVc::cell(names.await?.join(",")))
+}
+
+#[turbo_tasks::function]
+fn nothing(name: StringVc) {}
+
+fn main() {}
@@ -0,0 +1,16 @@
+#![feature(min_specialization)]
+
+use std::marker::PhantomData;
+
+use turbo_tasks::{primitives::StringVc, TraitRef};
+
+// The type arguments are only markers, so the fields don't contain Vcs
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+struct Wrapper {
+    #[turbo_tasks(trace_ignore)]
+    trait_ref: Option<TraitRef<StringVc>>,
+    #[turbo_tasks(trace_ignore)]
+    marker: PhantomData<StringVc>,
+}
+
+fn main() {}
@@ -0,0 +1,40 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use turbo_malloc::{AllocationCounters, TurboMalloc};
+
+pin_project! {
+    /// Attributes the allocations made while polling the inner future to the
+    /// task that is executed by it. The counters are taken with
+    /// [TurboMalloc::take_allocation_counters] while the future is polled.
+    /// Allocations on other threads, e. g. in
+    /// [turbo_tasks::spawn_blocking], are not attributed.
+    pub struct AllocationScopeFuture<F> {
+        counters: AllocationCounters,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> AllocationScopeFuture<F> {
+    pub fn new(future: F) -> Self {
+        Self {
+            counters: AllocationCounters::default(),
+            future,
+        }
+    }
+}
+
+impl<F: Future> Future for AllocationScopeFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let future = this.future;
+        TurboMalloc::with_allocation_counters(this.counters, || future.poll(cx))
+    }
+}
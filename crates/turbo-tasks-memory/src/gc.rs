@@ -1,5 +1,5 @@
 use std::{
-    cmp::Reverse,
+    cmp::{min, Reverse},
     collections::HashMap,
     time::{Duration, Instant},
 };
@@ -48,8 +48,122 @@ pub enum GcPriority {
     Placeholder,
 }
 
+/// Memory usage thresholds in bytes, which control garbage collection.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MemoryBudget {
+    /// The memory usage that the backend keeps under.
+    pub limit: usize,
+    /// Usage from which garbage collection starts when the backend is idle.
+    pub idle_start: usize,
+    /// Usage from which garbage collection starts after a task execution.
+    pub start: usize,
+    /// Upper bound of the amount of work a garbage collection run does. See
+    /// [GcQueue::run_gc].
+    pub max_collect_factor: u8,
+    /// Number of garbage collection runs in a row while the usage stays above
+    /// the limit.
+    pub max_rounds: usize,
+    /// Restricts garbage collection to actions up to a [GcPriority] while the
+    /// usage is below a threshold, ordered by threshold. All actions are
+    /// taken above the last threshold.
+    pub priority_limits: Vec<(usize, GcPriority)>,
+}
+
+/// `value * numerator / denominator` without overflowing.
+fn fraction(value: usize, numerator: usize, denominator: usize) -> usize {
+    value / denominator * numerator + value % denominator * numerator / denominator
+}
+
+impl MemoryBudget {
+    /// Creates a budget that starts to collect at 3/4 of the `limit` when idle
+    /// and at 7/8 otherwise, with a collect factor of at most 1/8 of the
+    /// maximum. This is what [MemoryBackend::new] uses.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            idle_start: fraction(limit, 3, 4),
+            start: fraction(limit, 7, 8),
+            max_collect_factor: u8::MAX / 8,
+            max_rounds: 1,
+            priority_limits: Vec::new(),
+        }
+    }
+
+    /// Creates a budget that starts to collect at 3/4 of the `limit` and
+    /// escalates as the usage rises: Only unused cells of inactive tasks are
+    /// unloaded up to 7/8, inactive tasks and unused cells up to 15/16 and all
+    /// cells from there on. Collection is repeated while the usage exceeds the
+    /// limit.
+    pub fn escalating(limit: usize) -> Self {
+        Self {
+            limit,
+            idle_start: fraction(limit, 3, 4),
+            start: fraction(limit, 3, 4),
+            max_collect_factor: u8::MAX,
+            max_rounds: 8,
+            priority_limits: vec![
+                (
+                    fraction(limit, 7, 8),
+                    GcPriority::InactiveEmptyUnusedCells {
+                        compute_duration: SmallDuration::MAX,
+                    },
+                ),
+                (
+                    fraction(limit, 15, 16),
+                    GcPriority::EmptyUnusedCells {
+                        compute_duration: SmallDuration::MAX,
+                    },
+                ),
+            ],
+        }
+    }
+
+    /// The usage from which garbage collection starts.
+    pub fn start(&self, idle: bool) -> usize {
+        if idle {
+            self.idle_start
+        } else {
+            self.start
+        }
+    }
+
+    /// The lowest priority of actions that are taken at the given memory
+    /// usage, or `None` when all actions are taken.
+    pub fn max_priority(&self, usage: usize) -> Option<GcPriority> {
+        self.priority_limits
+            .iter()
+            .find(|&&(threshold, _)| usage < threshold)
+            .map(|&(_, priority)| priority)
+    }
+
+    /// Controls how much work a garbage collection run does at the given
+    /// memory usage. It grows from 0 at `start` up to `max_collect_factor`.
+    /// See [GcQueue::run_gc].
+    pub fn collect_factor(&self, usage: usize, start: usize) -> u8 {
+        let range = self.limit.saturating_sub(start).max(1);
+        let pressure = usage.saturating_sub(start);
+        (pressure.saturating_mul(u8::MAX as usize) / range).min(self.max_collect_factor as usize)
+            as u8
+    }
+}
+
+/// Reported after every garbage collection run.
+#[derive(Debug, Clone)]
+pub struct GcEvent {
+    /// The lowest priority of actions that could be taken, see
+    /// [MemoryBudget::max_priority].
+    pub max_priority: Option<GcPriority>,
+    /// true, when the run was triggered by the backend being idle
+    pub idle: bool,
+    pub memory_before: usize,
+    pub memory_after: usize,
+    /// Number of tasks that were processed.
+    pub tasks: usize,
+    pub stats: GcStats,
+}
+
 /// Statistics about actions performed during garbage collection.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct GcStats {
     /// How many tasks were unloaded.
     pub unloaded: usize,
@@ -147,10 +261,12 @@ impl GcQueue {
 
     /// Run garbage collection on the queue. The `factor` parameter controls how
     /// much work should be done. It's a value between 0 and 255, where 255
-    /// performs all the work possible.
+    /// performs all the work possible. Actions with a lower priority than
+    /// `max_priority` are postponed.
     pub fn run_gc(
         &self,
         factor: u8,
+        max_priority: Option<GcPriority>,
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> Option<(GcPriority, usize, GcStats)> {
@@ -170,7 +286,10 @@ impl GcQueue {
         let mut task_duration_cache = HashMap::with_hasher(BuildNoHashHasher::default());
         let mut scope_active_cache = HashMap::with_hasher(BuildNoHashHasher::default());
         let mut stats = GcStats::default();
-        let result = self.select_tasks(factor, |task_id, _priority, max_priority| {
+        let result = self.select_tasks(factor, |task_id, _priority, highest_priority| {
+            let max_priority = max_priority.map_or(highest_priority, |max_priority| {
+                min(max_priority, highest_priority)
+            });
             backend.with_task(task_id, |task| {
                 task.run_gc(
                     now,
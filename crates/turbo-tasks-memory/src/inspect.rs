@@ -0,0 +1,217 @@
+//! Introspection of the task graph of a running [MemoryBackend].
+//!
+//! [serve] exposes [MemoryBackend::inspect_task] as JSON over a local HTTP
+//! server, together with a small page to navigate the graph:
+//!
+//! * `GET /` - the navigation page
+//! * `GET /api/task/<id>` - a [TaskInfo]
+//! * `GET /api/tasks?q=<text>` - up to 100 cached tasks whose description
+//!   contains `text`
+//!
+//! Needs the `inspect` feature. Invalidation reasons are only recorded by a
+//! backend created with [MemoryBackend::with_inspector].
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use anyhow::Result;
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Serialize;
+use turbo_tasks::{TaskId, TurboTasks};
+
+use crate::MemoryBackend;
+
+/// Maximum number of tasks returned by a search.
+const MAX_SEARCH_RESULTS: usize = 100;
+
+/// A reference to another task.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TaskRef {
+    pub id: usize,
+    pub description: String,
+}
+
+/// Everything known about a task.
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskInfo {
+    pub id: usize,
+    pub description: String,
+    /// `dirty`, `scheduled`, `in progress`, `in progress (dirty)`, `done` or
+    /// `unloaded`
+    pub state: String,
+    pub output: Option<String>,
+    pub children: Vec<TaskRef>,
+    /// Cells, outputs and scopes the last execution has read.
+    pub dependencies: Vec<DependencyInfo>,
+    /// Tasks that have read the output or a cell of this task.
+    pub dependents: Vec<TaskRef>,
+    pub cells: Vec<CellInfo>,
+    pub collectibles: Vec<CollectibleInfo>,
+    pub scopes: Vec<ScopeInfo>,
+    /// The most recent reasons for external invalidations, oldest first.
+    pub invalidations: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DependencyInfo {
+    /// `output`, `cell`, `scope children` or `scope collectibles`
+    pub kind: &'static str,
+    pub task: Option<TaskRef>,
+    pub cell: Option<String>,
+    pub scope: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CellInfo {
+    pub cell: String,
+    /// `empty`, `value`, `valueless` or `recomputing`
+    pub state: &'static str,
+    /// The serialized size of the value, when it's serializable.
+    pub size: Option<u64>,
+    pub dependents: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CollectibleInfo {
+    pub trait_type: String,
+    pub value: String,
+    pub emitted: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScopeInfo {
+    pub id: usize,
+    /// true, when this is the root scope of the task
+    pub root: bool,
+    pub active: bool,
+    pub interactive: bool,
+}
+
+/// Serves the task graph of `turbo_tasks` on `addr` until the future is
+/// dropped.
+pub async fn serve(turbo_tasks: Arc<TurboTasks<MemoryBackend>>, addr: SocketAddr) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let turbo_tasks = turbo_tasks.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = handle_request(turbo_tasks.backend(), request);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+    Server::try_bind(&addr)?.serve(make_service).await?;
+    Ok(())
+}
+
+fn handle_request(backend: &MemoryBackend, request: Request<Body>) -> Response<Body> {
+    if request.method() != Method::GET {
+        return status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    let path = request.uri().path();
+    if path == "/" {
+        return Response::builder()
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(Body::from(INDEX_HTML))
+            .unwrap();
+    }
+    if let Some(id) = path.strip_prefix("/api/task/") {
+        let Ok(id) = id.parse::<usize>() else {
+            return status(StatusCode::BAD_REQUEST);
+        };
+        return match backend.inspect_task(TaskId::from(id)) {
+            Some(info) => json(&info),
+            None => status(StatusCode::NOT_FOUND),
+        };
+    }
+    if path == "/api/tasks" {
+        let query = request
+            .uri()
+            .query()
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .find_map(|pair| pair.strip_prefix("q="))
+            .map(|value| urlencoding::decode(&value.replace('+', " ")).map(|v| v.into_owned()))
+            .transpose();
+        let Ok(query) = query else {
+            return status(StatusCode::BAD_REQUEST);
+        };
+        return json(&backend.search_tasks(query.as_deref().unwrap_or(""), MAX_SEARCH_RESULTS));
+    }
+    status(StatusCode::NOT_FOUND)
+}
+
+fn json<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_vec(value) {
+        Ok(bytes) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(bytes))
+            .unwrap(),
+        Err(_) => status(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(status.to_string()))
+        .unwrap()
+}
+
+const INDEX_HTML: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<title>turbo-tasks inspector</title>
+<style>
+body{font-family:monospace;margin:20px}a{cursor:pointer;color:#06c}h2{margin-top:20px}
+td,th{padding:2px 10px;text-align:left;vertical-align:top}
+</style>
+</head>
+<body>
+<form id="search"><input id="q" placeholder="search tasks or enter id" size="50"> <button>go</button></form>
+<div id="content"></div>
+<script>
+const content = document.getElementById("content");
+const esc = s => String(s).replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;");
+const link = t => t ? `<a href="#${t.id}">${esc(t.description)}</a>` : "";
+const table = (title, rows, columns) => `<h2>${title} (${rows.length})</h2><table><tr>${
+  columns.map(c => `<th>${c[0]}</th>`).join("")}</tr>${
+  rows.map(r => `<tr>${columns.map(c => `<td>${c[1](r)}</td>`).join("")}</tr>`).join("")}</table>`;
+async function show(id) {
+  const response = await fetch(`/api/task/${id}`);
+  if (!response.ok) { content.innerHTML = esc(`task ${id}: ${await response.text()}`); return; }
+  const t = await response.json();
+  content.innerHTML = `<h1>${esc(t.description)}</h1><p>state: ${esc(t.state)}<br>output: ${esc(t.output ?? "")}</p>`
+    + table("invalidations", t.invalidations, [["reason", r => esc(r)]])
+    + table("children", t.children, [["task", link]])
+    + table("dependencies", t.dependencies, [["kind", d => d.kind], ["task", d => link(d.task)],
+        ["cell", d => esc(d.cell ?? "")], ["scope", d => d.scope ?? ""]])
+    + table("dependents", t.dependents, [["task", link]])
+    + table("cells", t.cells, [["cell", c => esc(c.cell)], ["state", c => c.state],
+        ["size", c => c.size ?? "?"], ["dependents", c => c.dependents]])
+    + table("collectibles", t.collectibles, [["trait", c => esc(c.trait_type)], ["value", c => esc(c.value)],
+        ["emitted", c => c.emitted]])
+    + table("scopes", t.scopes, [["scope", s => s.id], ["root", s => s.root], ["active", s => s.active],
+        ["interactive", s => s.interactive]]);
+}
+async function search(q) {
+  const tasks = await (await fetch(`/api/tasks?q=${encodeURIComponent(q)}`)).json();
+  content.innerHTML = table("tasks", tasks, [["task", link]]);
+}
+function route() {
+  const id = location.hash.slice(1);
+  if (id) show(id); else search("");
+}
+document.getElementById("search").onsubmit = e => {
+  e.preventDefault();
+  const q = document.getElementById("q").value.trim();
+  if (/^\d+$/.test(q)) location.hash = q; else search(q);
+};
+window.onhashchange = route;
+route();
+</script>
+</body>
+</html>
+"##;
@@ -7,19 +7,24 @@
 #![feature(int_roundings)]
 #![deny(unsafe_op_in_unsafe_fn)]
 
+mod allocation_scope;
 mod cell;
 mod concurrent_priority_queue;
 mod count_hash_set;
 mod gc;
+#[cfg(feature = "inspect")]
+pub mod inspect;
 mod map_guard;
 mod memory_backend;
 mod memory_backend_with_pg;
 mod output;
+mod persistent_cache;
 mod priority_pair;
 pub mod scope;
 pub mod stats;
 mod task;
 pub mod viz;
 
+pub use gc::{GcEvent, GcPriority, GcStats, MemoryBudget};
 pub use memory_backend::MemoryBackend;
 pub use memory_backend_with_pg::MemoryBackendWithPersistedGraph;
@@ -1,13 +1,13 @@
 use std::{
-    borrow::{Borrow, Cow},
+    borrow::Borrow,
     cell::RefCell,
-    cmp::min,
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     future::Future,
     hash::{BuildHasher, BuildHasherDefault, Hash},
+    path::PathBuf,
     pin::Pin,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
     time::{Duration, Instant},
@@ -18,22 +18,29 @@ use auto_hash_map::AutoSet;
 use dashmap::{mapref::entry::Entry, DashMap};
 use nohash_hasher::BuildNoHashHasher;
 use rustc_hash::FxHasher;
-use tokio::task::futures::TaskLocalFuture;
+use tokio::{sync::broadcast, task::futures::TaskLocalFuture};
+use turbo_malloc::TurboMalloc;
 use turbo_tasks::{
     backend::{
-        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionSpec,
-        TransientTaskType,
+        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionResult,
+        TaskExecutionSpec, TaskPriority, TransientTaskType,
     },
     event::EventListener,
     primitives::RawVcSetVc,
     util::{IdFactory, NoMoveVec},
     CellId, RawVc, TaskId, TraitTypeId, TurboTasksBackendApi, Unused,
 };
+#[cfg(feature = "inspect")]
+use turbo_tasks::{util::StaticOrArc, InvalidationReason};
 
+#[cfg(feature = "inspect")]
+use crate::inspect::{TaskInfo, TaskRef};
 use crate::{
+    allocation_scope::AllocationScopeFuture,
     cell::RecomputingCell,
-    gc::GcQueue,
+    gc::{GcEvent, GcQueue, MemoryBudget},
     output::Output,
+    persistent_cache::PersistentCache,
     priority_pair::PriorityPair,
     scope::{TaskScope, TaskScopeId},
     task::{
@@ -42,6 +49,13 @@ use crate::{
     },
 };
 
+/// Number of [GcEvent]s that are buffered for slow subscribers.
+const GC_EVENTS_CAPACITY: usize = 64;
+
+/// Number of invalidation reasons that are kept per task for [crate::inspect].
+#[cfg(feature = "inspect")]
+const MAX_INVALIDATION_REASONS: usize = 10;
+
 pub struct MemoryBackend {
     memory_tasks: NoMoveVec<Task, 13>,
     memory_task_scopes: NoMoveVec<TaskScope>,
@@ -50,10 +64,21 @@ pub struct MemoryBackend {
     backend_jobs: NoMoveVec<Job>,
     backend_job_id_factory: IdFactory<BackendJobId>,
     task_cache: DashMap<Arc<PersistentTaskType>, TaskId, BuildHasherDefault<FxHasher>>,
-    memory_limit: usize,
+    memory_budget: MemoryBudget,
     gc_queue: Option<GcQueue>,
     idle_gc_active: AtomicBool,
+    gc_events: broadcast::Sender<GcEvent>,
     scope_add_remove_priority: PriorityPair,
+    /// Number of scopes flagged as interactive or boosted
+    interactive_scopes: AtomicUsize,
+    /// Incremented when interactive scopes or the scope graph changes.
+    /// Invalidates the priorities cached in the scopes.
+    priority_epoch: AtomicUsize,
+    /// The most recent external invalidation reasons by task, only recorded
+    /// when the inspector is enabled
+    #[cfg(feature = "inspect")]
+    invalidation_reasons: Option<DashMap<TaskId, VecDeque<String>, BuildNoHashHasher<TaskId>>>,
+    pub(crate) persistent_cache: Option<PersistentCache>,
 }
 
 impl Default for MemoryBackend {
@@ -78,10 +103,134 @@ impl MemoryBackend {
             backend_jobs: NoMoveVec::new(),
             backend_job_id_factory: IdFactory::new(),
             task_cache: DashMap::default(),
-            memory_limit,
+            memory_budget: MemoryBudget::new(memory_limit),
             gc_queue: (memory_limit != usize::MAX).then(GcQueue::new),
             idle_gc_active: AtomicBool::new(false),
+            gc_events: broadcast::channel(GC_EVENTS_CAPACITY).0,
             scope_add_remove_priority: PriorityPair::new(),
+            interactive_scopes: AtomicUsize::new(0),
+            priority_epoch: AtomicUsize::new(1),
+            #[cfg(feature = "inspect")]
+            invalidation_reasons: None,
+            persistent_cache: None,
+        }
+    }
+
+    /// Keeps the memory usage, as counted by [TurboMalloc], under the budget.
+    /// See [MemoryBudget::escalating] for a budget that escalates garbage
+    /// collection as the usage rises.
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        if self.gc_queue.is_none() && budget.limit != usize::MAX {
+            self.gc_queue = Some(GcQueue::new());
+        }
+        self.memory_budget = budget;
+        self
+    }
+
+    /// Returns a receiver for a [GcEvent] after every garbage collection run.
+    pub fn subscribe_gc_events(&self) -> broadcast::Receiver<GcEvent> {
+        self.gc_events.subscribe()
+    }
+
+    /// Stores task outputs and cells in a file at `path` on stop and when
+    /// idle, and restores them after a restart. `version` identifies the
+    /// build, the cache is discarded when it doesn't match.
+    pub fn with_persistent_cache(
+        mut self,
+        path: impl Into<PathBuf>,
+        version: impl Into<String>,
+    ) -> Self {
+        self.persistent_cache = Some(PersistentCache::new(path.into(), version.into()));
+        self
+    }
+
+    /// Drops entries of the persistent cache that no task has used for more
+    /// than `sessions` sessions in a row. Defaults to 10. Has no effect unless
+    /// [MemoryBackend::with_persistent_cache] has been called before.
+    pub fn with_persistent_cache_max_unused_sessions(mut self, sessions: u32) -> Self {
+        if let Some(cache) = &mut self.persistent_cache {
+            cache.set_max_unused_sessions(sessions);
+        }
+        self
+    }
+
+    /// Writes the persistent cache to disk, when it's enabled.
+    pub fn persist_cache(&self) -> Result<()> {
+        if let Some(cache) = &self.persistent_cache {
+            cache.snapshot(self)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the persistent cache on stop or when idle, keeping the error for
+    /// [MemoryBackend::take_persist_cache_error].
+    fn try_persist_cache(&self) {
+        if let Some(cache) = &self.persistent_cache {
+            if let Err(err) = cache.snapshot(self) {
+                cache.set_error(err);
+            }
+        }
+    }
+
+    /// Returns the error of the last failed write of the persistent cache on
+    /// stop or when idle.
+    pub fn take_persist_cache_error(&self) -> Option<anyhow::Error> {
+        self.persistent_cache
+            .as_ref()
+            .and_then(|cache| cache.take_error())
+    }
+
+    /// Records the reasons of external invalidations for [crate::inspect].
+    #[cfg(feature = "inspect")]
+    pub fn with_inspector(mut self) -> Self {
+        self.invalidation_reasons = Some(DashMap::default());
+        self
+    }
+
+    /// Returns everything known about a task, or `None` when there is no task
+    /// with that id.
+    #[cfg(feature = "inspect")]
+    pub fn inspect_task(&self, id: TaskId) -> Option<TaskInfo> {
+        self.memory_tasks.get(*id).map(|task| task.inspect(self))
+    }
+
+    /// Returns cached tasks whose description contains `query`, ordered by id.
+    #[cfg(feature = "inspect")]
+    pub fn search_tasks(&self, query: &str, limit: usize) -> Vec<TaskRef> {
+        let mut tasks = Vec::new();
+        self.with_all_cached_tasks(|id| {
+            let task = self.get_task_ref(id);
+            if task.description.contains(query) {
+                tasks.push(task);
+            }
+        });
+        tasks.sort_by_key(|task| task.id);
+        tasks.truncate(limit);
+        tasks
+    }
+
+    #[cfg(feature = "inspect")]
+    pub(crate) fn get_task_ref(&self, id: TaskId) -> TaskRef {
+        TaskRef {
+            id: *id,
+            description: self.with_task(id, |task| task.get_description()),
+        }
+    }
+
+    #[cfg(feature = "inspect")]
+    pub(crate) fn get_invalidation_reasons(&self, id: TaskId) -> Vec<String> {
+        self.invalidation_reasons
+            .as_ref()
+            .and_then(|reasons| reasons.get(&id))
+            .map(|reasons| reasons.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forgets the invalidation reasons of a task that has been unloaded.
+    #[cfg(feature = "inspect")]
+    pub(crate) fn remove_invalidation_reasons(&self, id: TaskId) {
+        if let Some(reasons) = &self.invalidation_reasons {
+            reasons.remove(&id);
         }
     }
 
@@ -96,6 +245,99 @@ impl MemoryBackend {
         });
     }
 
+    /// Returns the priority of tasks in the given scopes. Tasks are
+    /// interactive when any of their scopes has an interactive root scope as
+    /// (transitive) parent.
+    pub(crate) fn get_scopes_priority(
+        &self,
+        scopes: impl IntoIterator<Item = TaskScopeId>,
+    ) -> TaskPriority {
+        if self.interactive_scopes.load(Ordering::Acquire) == 0 {
+            return TaskPriority::Normal;
+        }
+        let epoch = self.priority_epoch.load(Ordering::Acquire);
+        if scopes
+            .into_iter()
+            .any(|scope| self.is_scope_interactive(scope, epoch))
+        {
+            TaskPriority::Interactive
+        } else {
+            TaskPriority::Normal
+        }
+    }
+
+    /// Returns true when the scope is or has an interactive root scope as
+    /// (transitive) parent. The result is cached in the scopes until the
+    /// interactive scopes or the scope graph change.
+    fn is_scope_interactive(&self, start: TaskScopeId, epoch: usize) -> bool {
+        if let Some(interactive) =
+            self.with_scope(start, |scope| scope.state.lock().cached_interactive(epoch))
+        {
+            return interactive;
+        }
+        let mut queue = vec![start];
+        let mut visited = HashSet::new();
+        while let Some(scope) = queue.pop() {
+            if !visited.insert(scope) {
+                continue;
+            }
+            let interactive = self.with_scope(scope, |scope| {
+                let state = scope.state.lock();
+                if state.is_interactive() {
+                    return true;
+                }
+                if let Some(interactive) = state.cached_interactive(epoch) {
+                    return interactive;
+                }
+                queue.extend(state.parents.iter().copied());
+                false
+            });
+            if interactive {
+                self.with_scope(start, |scope| {
+                    scope.state.lock().set_cached_interactive(epoch, true)
+                });
+                return true;
+            }
+        }
+        // None of the visited scopes has an interactive parent
+        for scope in visited {
+            self.with_scope(scope, |scope| {
+                scope.state.lock().set_cached_interactive(epoch, false)
+            });
+        }
+        false
+    }
+
+    /// Executes the unfinished tasks of a scope with interactive priority when
+    /// the current task is interactive and waits for the scope.
+    pub(crate) fn boost_scope(
+        &self,
+        scope: TaskScopeId,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        if turbo_tasks.current_task_priority() != TaskPriority::Interactive {
+            return;
+        }
+        if self.with_scope(scope, |scope| scope.state.lock().boost()) {
+            self.interactive_scopes.fetch_add(1, Ordering::AcqRel);
+            self.priority_epoch.fetch_add(1, Ordering::AcqRel);
+            turbo_tasks.task_priorities_changed();
+        }
+    }
+
+    /// Called when the tasks of a boosted scope are finished.
+    pub(crate) fn scope_unboosted(&self) {
+        self.interactive_scopes.fetch_sub(1, Ordering::AcqRel);
+        self.priority_epoch.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Invalidates the cached priorities of the scopes.
+    pub(crate) fn scope_parents_changed(&self) {
+        if self.interactive_scopes.load(Ordering::Acquire) != 0 {
+            self.priority_epoch.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
     pub(crate) fn create_backend_job(&self, job: Job) -> BackendJobId {
         job.before_schedule(self);
         let id = self.backend_job_id_factory.get();
@@ -125,6 +367,15 @@ impl MemoryBackend {
         }
     }
 
+    pub(crate) fn with_all_cached_tasks_and_types(
+        &self,
+        mut func: impl FnMut(Arc<PersistentTaskType>, TaskId),
+    ) {
+        for (ty, id) in self.task_cache.clone().into_read_only().iter() {
+            func(ty.clone(), *id);
+        }
+    }
+
     #[inline(always)]
     pub fn with_task<T>(&self, id: TaskId, func: impl FnOnce(&Task) -> T) -> T {
         func(self.memory_tasks.get(*id).unwrap())
@@ -239,33 +490,44 @@ impl MemoryBackend {
 
     pub fn run_gc(&self, idle: bool, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
         if let Some(gc_queue) = &self.gc_queue {
-            const MAX_COLLECT_FACTOR: u8 = u8::MAX / 8;
-
-            let mem_limit = self.memory_limit;
+            let budget = &self.memory_budget;
+            let mut collected_any = false;
+            let start = budget.start(idle);
+            for _ in 0..budget.max_rounds.max(1) {
+                let memory_before = TurboMalloc::memory_usage();
+                if memory_before < start {
+                    if idle {
+                        // Always run propagation when idle
+                        gc_queue.run_gc(0, None, self, turbo_tasks);
+                    }
+                    break;
+                }
 
-            let usage = turbo_malloc::TurboMalloc::memory_usage();
-            let target = if idle {
-                mem_limit * 3 / 4
-            } else {
-                mem_limit * 7 / 8
-            };
-            if usage < target {
-                if idle {
-                    // Always run propagation when idle
-                    gc_queue.run_gc(0, self, turbo_tasks);
+                let collect_factor = budget.collect_factor(memory_before, start);
+                let max_priority = budget.max_priority(memory_before);
+                let Some((_priority, tasks, stats)) =
+                    gc_queue.run_gc(collect_factor, max_priority, self, turbo_tasks)
+                else {
+                    break;
+                };
+                collected_any = true;
+                let memory_after = TurboMalloc::memory_usage();
+                // There might be no subscribers
+                let _ = self.gc_events.send(GcEvent {
+                    max_priority,
+                    idle,
+                    memory_before,
+                    memory_after,
+                    tasks,
+                    stats,
+                });
+                if idle || memory_after < budget.limit {
+                    break;
                 }
-                return;
             }
 
-            let collect_factor = min(
-                MAX_COLLECT_FACTOR as usize,
-                (usage - target) * u8::MAX as usize / (mem_limit - target),
-            ) as u8;
-
-            let collected = gc_queue.run_gc(collect_factor, self, turbo_tasks);
-
             if idle {
-                if let Some((_collected, _count, _stats)) = collected {
+                if collected_any {
                     let job = self.create_backend_job(Job::GarbageCollection);
                     turbo_tasks.schedule_backend_background_job(job);
                 } else {
@@ -392,6 +654,10 @@ impl MemoryBackend {
 }
 
 impl Backend for MemoryBackend {
+    fn stop(&self, _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
+        self.try_persist_cache();
+    }
+
     fn idle_start(&self, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
         if self
             .idle_gc_active
@@ -401,12 +667,35 @@ impl Backend for MemoryBackend {
             let job = self.create_backend_job(Job::GarbageCollection);
             turbo_tasks.schedule_backend_background_job(job);
         }
+        if let Some(cache) = &self.persistent_cache {
+            if cache.take_dirty() {
+                let job = self.create_backend_job(Job::PersistCache);
+                turbo_tasks.schedule_backend_background_job(job);
+            }
+        }
     }
 
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>) {
         self.with_task(task, |task| task.invalidate(self, turbo_tasks));
     }
 
+    #[cfg(feature = "inspect")]
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        reason: &StaticOrArc<dyn InvalidationReason>,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        if let Some(reasons) = &self.invalidation_reasons {
+            let mut reasons = reasons.entry(task).or_default();
+            if reasons.len() == MAX_INVALIDATION_REASONS {
+                reasons.pop_front();
+            }
+            reasons.push_back(reason.to_string());
+        }
+        self.invalidate_task(task, turbo_tasks);
+    }
+
     fn invalidate_tasks(
         &self,
         tasks: Vec<TaskId>,
@@ -424,13 +713,13 @@ impl Backend for MemoryBackend {
     }
 
     type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static> =
-        TaskLocalFuture<RefCell<AutoSet<TaskDependency>>, T>;
+        AllocationScopeFuture<TaskLocalFuture<RefCell<AutoSet<TaskDependency>>, T>>;
     fn execution_scope<T: Future<Output = Result<()>> + Send + 'static>(
         &self,
         _task: TaskId,
         future: T,
     ) -> Self::ExecutionScopeFuture<T> {
-        DEPENDENCIES_TO_TRACK.scope(Default::default(), future)
+        AllocationScopeFuture::new(DEPENDENCIES_TO_TRACK.scope(Default::default(), future))
     }
 
     fn try_start_task_execution(
@@ -438,13 +727,16 @@ impl Backend for MemoryBackend {
         task: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> Option<TaskExecutionSpec> {
+        // Allocations made while waiting for the execution, e. g. for the
+        // previous execution, are not part of it
+        TurboMalloc::take_allocation_counters();
         self.with_task(task, |task| task.execute(self, turbo_tasks))
     }
 
     fn task_execution_result(
         &self,
         task: TaskId,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: TaskExecutionResult,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) {
         self.with_task(task, |task| {
@@ -460,9 +752,13 @@ impl Backend for MemoryBackend {
         stateful: bool,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> bool {
+        let allocations = TurboMalloc::take_allocation_counters();
         let reexecute = self.with_task(task_id, |task| {
-            task.execution_completed(duration, instant, stateful, self, turbo_tasks)
+            task.execution_completed(duration, instant, allocations, stateful, self, turbo_tasks)
         });
+        if let Some(cache) = &self.persistent_cache {
+            cache.task_executed();
+        }
         if !reexecute {
             self.run_gc(false, turbo_tasks);
             if let Some(gc_queue) = &self.gc_queue {
@@ -663,22 +959,25 @@ impl Backend for MemoryBackend {
             let task_type = Arc::new(task_type);
             // slow pass with key lock
             let id = turbo_tasks.get_fresh_task_id();
-            let task = Task::new_persistent(
-                // Safety: That task will hold the value, but we are still in
-                // control of the task
-                *unsafe { id.get_unchecked() },
-                task_type.clone(),
-                turbo_tasks.stats_type(),
-            );
-            self.insert_and_connect_fresh_task(
+            // Safety: That task will hold the value, but we are still in
+            // control of the task
+            let new_id = *unsafe { id.get_unchecked() };
+            let task = Task::new_persistent(new_id, task_type.clone(), turbo_tasks.stats_type());
+            let task_id = self.insert_and_connect_fresh_task(
                 parent_task,
                 &self.task_cache,
-                task_type,
+                task_type.clone(),
                 id,
                 task,
                 false,
                 turbo_tasks,
-            )
+            );
+            if task_id == new_id {
+                if let Some(cache) = &self.persistent_cache {
+                    cache.task_created(task_id, &task_type);
+                }
+            }
+            task_id
         }
     }
 
@@ -699,6 +998,45 @@ impl Backend for MemoryBackend {
         self.with_task(task, |task| task.mark_as_finished(self))
     }
 
+    fn mark_own_task_as_session_dependent(
+        &self,
+        task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        if let Some(cache) = &self.persistent_cache {
+            cache.mark_session_dependent(task);
+        }
+    }
+
+    fn set_root_task_priority(
+        &self,
+        task: TaskId,
+        priority: TaskPriority,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) {
+        let root_scope = self.with_task(task, |task| {
+            task.get_or_create_root_scope(self, turbo_tasks)
+        });
+        let interactive = priority == TaskPriority::Interactive;
+        if self.with_scope(root_scope, |scope| {
+            scope.state.lock().set_interactive(interactive)
+        }) {
+            if interactive {
+                self.interactive_scopes.fetch_add(1, Ordering::AcqRel);
+            } else {
+                self.interactive_scopes.fetch_sub(1, Ordering::AcqRel);
+            }
+            self.priority_epoch.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    fn get_task_priority(&self, task: TaskId) -> TaskPriority {
+        if self.interactive_scopes.load(Ordering::Acquire) == 0 {
+            return TaskPriority::Normal;
+        }
+        self.with_task(task, |task| task.get_priority(self))
+    }
+
     fn create_transient_task(
         &self,
         task_type: TransientTaskType,
@@ -746,6 +1084,7 @@ pub(crate) enum Job {
     /// are done.
     UnloadRootScope(TaskScopeId),
     GarbageCollection,
+    PersistCache,
 }
 
 impl Job {
@@ -819,6 +1158,9 @@ impl Job {
             Job::GarbageCollection => {
                 backend.run_gc(true, turbo_tasks);
             }
+            Job::PersistCache => {
+                backend.try_persist_cache();
+            }
         }
     }
 }
@@ -1,5 +1,4 @@
 use std::{
-    borrow::Cow,
     collections::{BinaryHeap, HashMap},
     fmt::Debug,
     future::Future,
@@ -19,8 +18,8 @@ use dashmap::{mapref::entry::Entry, DashMap, DashSet};
 use nohash_hasher::BuildNoHashHasher;
 use turbo_tasks::{
     backend::{
-        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionSpec,
-        TransientTaskType,
+        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionResult,
+        TaskExecutionSpec, TransientTaskType,
     },
     event::{Event, EventListener},
     persisted_graph::{
@@ -1102,22 +1101,30 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
                 )
             }
         };
-        Some(TaskExecutionSpec { future })
+        Some(TaskExecutionSpec {
+            future,
+            cancel: None,
+        })
     }
 
     fn task_execution_result(
         &self,
         task: TaskId,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: TaskExecutionResult,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackendWithPersistedGraph<P>>,
     ) {
+        let result = match result {
+            TaskExecutionResult::Finished(result) => result,
+            TaskExecutionResult::Panicked(Some(message)) => {
+                Err(anyhow!("A task panicked: {message}"))
+            }
+            TaskExecutionResult::Panicked(None) => Err(anyhow!("A task panicked")),
+            // Executions are never cancelled by this backend
+            TaskExecutionResult::Cancelled => return,
+        };
         let (mut state, _task_info) = self.mem_state_mut(task, turbo_tasks);
         let TaskState { ref mut memory, .. } = *state;
         let mem_state = memory.as_mut().unwrap();
-        let result = result.unwrap_or_else(|panic| match panic {
-            Some(message) => Err(anyhow!("A task panicked: {message}")),
-            None => Err(anyhow!("A task panicked")),
-        });
         let output_change = if let (Some(Ok(old)), Ok(new)) = (&mem_state.output, &result) {
             old != new
         } else {
@@ -0,0 +1,586 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fs,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Context, Result};
+use bincode::Options;
+use dashmap::{DashMap, DashSet};
+use nohash_hasher::BuildNoHashHasher;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use turbo_tasks::{
+    backend::{Backend, CellContent, PersistentTaskType},
+    with_task_id_mapping, CellId, IdMapping, RawVc, TaskId, TurboTasksBackendApi,
+};
+use turbo_tasks_hash::hash_xxh3_hash64;
+
+use crate::{
+    task::{CacheableTaskState, TaskDependency},
+    MemoryBackend,
+};
+
+/// Bumped whenever the layout of the cache file changes.
+const FORMAT_VERSION: u32 = 2;
+
+/// How many sessions an entry is kept without being used by a task.
+const DEFAULT_MAX_UNUSED_SESSIONS: u32 = 10;
+
+/// The file stored on disk.
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    format: u32,
+    version: String,
+    entries: Vec<CacheEntry>,
+}
+
+/// A persistent task stored in the cache.
+///
+/// Task ids are session specific, so all serialized values refer to other
+/// tasks by their index in the list of entries.
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// The serialized [PersistentTaskType], or None when it isn't
+    /// serializable.
+    key: Option<Vec<u8>>,
+    /// Entries referenced by the key.
+    key_refs: Vec<usize>,
+    data: Option<CacheEntryData>,
+    /// How many sessions in a row no task has been matched to this entry.
+    unused_sessions: u32,
+}
+
+impl CacheEntry {
+    fn empty() -> Self {
+        CacheEntry {
+            key: None,
+            key_refs: Vec::new(),
+            data: None,
+            unused_sessions: 0,
+        }
+    }
+
+    /// Entries that have to be kept along with this entry.
+    fn refs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.key_refs
+            .iter()
+            .copied()
+            .chain(self.data.iter().flat_map(|data| {
+                data.refs
+                    .iter()
+                    .copied()
+                    .chain(data.dependencies.iter().map(|d| d.entry))
+            }))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntryData {
+    /// The serialized output [RawVc].
+    output: Vec<u8>,
+    /// The serialized [CellContent] of each cell.
+    cells: Vec<(CellId, Vec<u8>)>,
+    /// Values read by the execution, which need to be unchanged to reuse the
+    /// data.
+    dependencies: Vec<CacheDependency>,
+    /// Entries referenced by the output, the cells or as children. These
+    /// tasks are created again when the data is restored.
+    refs: Vec<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheDependency {
+    entry: usize,
+    /// The cell that has been read, or None when the output has been read.
+    cell: Option<CellId>,
+    /// Hash of the serialized value at the time the data was stored.
+    hash: u64,
+}
+
+struct LoadedCache {
+    entries: Vec<CacheEntry>,
+    lookup: HashMap<Vec<u8>, usize>,
+}
+
+/// Stores task outputs and cells of a [MemoryBackend] on disk, so they can be
+/// reused after a restart.
+///
+/// Entries are restored lazily: When a task is created that matches an entry,
+/// its first execution is replaced by reading all dependencies the stored
+/// execution had read. When all of them have the same content hash as before,
+/// the stored cells and output are used. Otherwise the task is executed
+/// normally.
+///
+/// Tasks that are stateful or [session
+/// dependent](turbo_tasks::mark_session_dependent) (e. g. reading the file
+/// system) are never restored. They are executed and their dependents are
+/// validated against their new values.
+pub(crate) struct PersistentCache {
+    path: PathBuf,
+    version: String,
+    max_unused_sessions: u32,
+    loaded: OnceCell<LoadedCache>,
+    /// Maps tasks of this session to entries of the loaded cache.
+    task_to_entry: DashMap<TaskId, usize, BuildNoHashHasher<TaskId>>,
+    entry_to_task: DashMap<usize, TaskId>,
+    /// Tasks that have been matched to an entry with data, but have not been
+    /// executed yet.
+    pending: DashMap<TaskId, usize, BuildNoHashHasher<TaskId>>,
+    session_dependent: DashSet<TaskId, BuildNoHashHasher<TaskId>>,
+    /// Set when tasks have been executed since the last snapshot.
+    dirty: AtomicBool,
+    snapshot_lock: Mutex<()>,
+    /// The error of the last snapshot written on stop or when idle.
+    error: Mutex<Option<anyhow::Error>>,
+}
+
+/// Maps task ids to entry indices while serializing and remembers the
+/// referenced entries.
+struct ToEntry<F: Fn(TaskId) -> Option<usize>> {
+    lookup: F,
+    refs: RefCell<Vec<usize>>,
+    missing: Cell<bool>,
+}
+
+impl<F: Fn(TaskId) -> Option<usize>> IdMapping<TaskId> for ToEntry<F> {
+    fn forward(&self, id: TaskId) -> usize {
+        if let Some(index) = (self.lookup)(id) {
+            self.refs.borrow_mut().push(index);
+            index
+        } else {
+            self.missing.set(true);
+            usize::MAX
+        }
+    }
+
+    fn backward(&self, _id: usize) -> TaskId {
+        unreachable!("ToEntry is only used for serialization")
+    }
+}
+
+/// Maps entry indices to task ids of this session while deserializing.
+struct FromEntry<'a> {
+    entry_to_task: &'a DashMap<usize, TaskId>,
+    missing: Cell<bool>,
+}
+
+impl IdMapping<TaskId> for FromEntry<'_> {
+    fn forward(&self, _id: TaskId) -> usize {
+        unreachable!("FromEntry is only used for deserialization")
+    }
+
+    fn backward(&self, index: usize) -> TaskId {
+        if let Some(task) = self.entry_to_task.get(&index) {
+            *task
+        } else {
+            self.missing.set(true);
+            TaskId::from(0)
+        }
+    }
+}
+
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Serializes a value with task ids mapped to entry indices. Returns the bytes
+/// and the referenced entries, or None when the value isn't serializable or
+/// references tasks without an entry.
+fn serialize_with<T: Serialize>(
+    value: &T,
+    lookup: impl Fn(TaskId) -> Option<usize>,
+) -> Option<(Vec<u8>, Vec<usize>)> {
+    let mapping = ToEntry {
+        lookup,
+        refs: RefCell::new(Vec::new()),
+        missing: Cell::new(false),
+    };
+    let bytes = with_task_id_mapping(&mapping, || bincode_options().serialize(value)).ok()?;
+    if mapping.missing.get() {
+        return None;
+    }
+    let mut refs = mapping.refs.into_inner();
+    refs.sort_unstable();
+    refs.dedup();
+    Some((bytes, refs))
+}
+
+impl PersistentCache {
+    pub fn new(path: PathBuf, version: String) -> Self {
+        Self {
+            path,
+            version,
+            max_unused_sessions: DEFAULT_MAX_UNUSED_SESSIONS,
+            loaded: OnceCell::new(),
+            task_to_entry: DashMap::default(),
+            entry_to_task: DashMap::new(),
+            pending: DashMap::default(),
+            session_dependent: DashSet::default(),
+            dirty: AtomicBool::new(false),
+            snapshot_lock: Mutex::new(()),
+            error: Mutex::new(None),
+        }
+    }
+
+    pub fn set_max_unused_sessions(&mut self, sessions: u32) {
+        self.max_unused_sessions = sessions;
+    }
+
+    /// Reads the cache file on first access. The registry needs to be
+    /// populated at that point, so this can't happen on construction.
+    fn loaded(&self) -> &LoadedCache {
+        self.loaded.get_or_init(|| {
+            let entries = self.read_entries().unwrap_or_default();
+            let mut lookup = HashMap::with_capacity(entries.len());
+            for (index, entry) in entries.iter().enumerate() {
+                if let Some(key) = &entry.key {
+                    lookup.entry(key.clone()).or_insert(index);
+                }
+            }
+            LoadedCache { entries, lookup }
+        })
+    }
+
+    fn read_entries(&self) -> Option<Vec<CacheEntry>> {
+        let bytes = fs::read(&self.path).ok()?;
+        let file: CacheFile = bincode_options().deserialize(&bytes).ok()?;
+        (file.format == FORMAT_VERSION && file.version == self.version).then_some(file.entries)
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Option<Vec<u8>> {
+        serialize_with(value, |task| self.task_to_entry.get(&task).map(|e| *e))
+            .map(|(bytes, _)| bytes)
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Option<T> {
+        let mapping = FromEntry {
+            entry_to_task: &self.entry_to_task,
+            missing: Cell::new(false),
+        };
+        let value = with_task_id_mapping(&mapping, || bincode_options().deserialize(bytes)).ok()?;
+        (!mapping.missing.get()).then_some(value)
+    }
+
+    pub fn mark_session_dependent(&self, task: TaskId) {
+        self.session_dependent.insert(task);
+    }
+
+    pub fn task_executed(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Returns true when tasks have been executed since the last snapshot.
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::AcqRel)
+    }
+
+    /// Remembers a failed snapshot which had no caller to report to.
+    pub fn set_error(&self, error: anyhow::Error) {
+        *self.error.lock() = Some(error);
+    }
+
+    pub fn take_error(&self) -> Option<anyhow::Error> {
+        self.error.lock().take()
+    }
+
+    /// Matches a newly created task against the entries of the cache.
+    pub fn task_created(&self, task: TaskId, ty: &PersistentTaskType) {
+        let loaded = self.loaded();
+        if loaded.lookup.is_empty() {
+            return;
+        }
+        if let Some(key) = self.serialize(ty) {
+            if let Some(&index) = loaded.lookup.get(&key) {
+                self.claim(index, task);
+            }
+        }
+    }
+
+    fn claim(&self, index: usize, task: TaskId) {
+        let mut inserted = false;
+        self.entry_to_task.entry(index).or_insert_with(|| {
+            inserted = true;
+            task
+        });
+        if inserted {
+            self.task_to_entry.insert(task, index);
+            if self.loaded().entries[index].data.is_some() {
+                self.pending.insert(task, index);
+            }
+        }
+    }
+
+    /// Returns the task of this session for an entry, creating it when needed.
+    /// The task is connected as child of `parent`.
+    fn ensure_task(
+        &self,
+        index: usize,
+        parent: TaskId,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> Option<TaskId> {
+        if let Some(task) = self.entry_to_task.get(&index).map(|t| *t) {
+            backend.connect_task(task, parent, turbo_tasks);
+            return Some(task);
+        }
+        let entry = self.loaded().entries.get(index)?;
+        for &key_ref in entry.key_refs.iter() {
+            self.ensure_task(key_ref, parent, backend, turbo_tasks)?;
+        }
+        let ty: PersistentTaskType = self.deserialize(entry.key.as_ref()?)?;
+        let task = backend.get_or_create_persistent_task(ty, parent, turbo_tasks);
+        self.claim(index, task);
+        Some(task)
+    }
+
+    /// Wraps the execution of a native task, so its first execution restores
+    /// the cached data instead when it's still valid.
+    pub fn wrap_execution(
+        &self,
+        task: TaskId,
+        future: Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>> {
+        let Some((_, index)) = self.pending.remove(&task) else {
+            return future;
+        };
+        let turbo_tasks = turbo_tasks.pin();
+        Box::pin(async move {
+            let backend = turbo_tasks.backend();
+            let cache = backend
+                .persistent_cache
+                .as_ref()
+                .expect("persistent cache is enabled");
+            if let Some(output) = cache.restore(index, task, backend, &*turbo_tasks).await {
+                return Ok(output);
+            }
+            future.await
+        })
+    }
+
+    async fn restore(
+        &self,
+        index: usize,
+        task: TaskId,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> Option<RawVc> {
+        let data = self.loaded().entries[index].data.as_ref()?;
+        let tt = turbo_tasks::turbo_tasks();
+        for dependency in data.dependencies.iter() {
+            let dependency_task = self.ensure_task(dependency.entry, task, backend, turbo_tasks)?;
+            // These reads are tracked, so the restored task is invalidated like an
+            // executed task would be.
+            let bytes = if let Some(cell) = dependency.cell {
+                let content = loop {
+                    match tt.try_read_task_cell(dependency_task, cell).ok()? {
+                        Ok(content) => break content,
+                        Err(listener) => listener.await,
+                    }
+                };
+                self.serialize(&content)?
+            } else {
+                let output = loop {
+                    match tt.try_read_task_output(dependency_task, false).ok()? {
+                        Ok(output) => break output,
+                        Err(listener) => listener.await,
+                    }
+                };
+                self.serialize(&output)?
+            };
+            if hash_xxh3_hash64(&bytes) != dependency.hash {
+                return None;
+            }
+        }
+        for &reference in data.refs.iter() {
+            self.ensure_task(reference, task, backend, turbo_tasks)?;
+        }
+        let output: RawVc = self.deserialize(&data.output)?;
+        let cells = data
+            .cells
+            .iter()
+            .map(|(cell, bytes)| Some((*cell, self.deserialize::<CellContent>(bytes)?)))
+            .collect::<Option<Vec<_>>>()?;
+        for (cell, content) in cells {
+            backend.update_task_cell(task, cell, content, turbo_tasks);
+        }
+        Some(output)
+    }
+
+    /// Returns the loaded entries to keep, with their number of unused
+    /// sessions updated. Entries which have not been used for more than
+    /// `max_unused_sessions` sessions are dropped, unless a kept entry refers
+    /// to them.
+    fn aged_entries(&self) -> Vec<Option<CacheEntry>> {
+        let loaded = self.loaded();
+        let mut entries: Vec<_> = loaded
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let mut entry = entry.clone();
+                entry.unused_sessions = if self.entry_to_task.contains_key(&index) {
+                    0
+                } else {
+                    entry.unused_sessions.saturating_add(1)
+                };
+                entry
+            })
+            .collect();
+
+        let mut keep = HashSet::new();
+        let mut queue: Vec<_> = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.unused_sessions <= self.max_unused_sessions)
+            .map(|(index, _)| index)
+            .collect();
+        while let Some(index) = queue.pop() {
+            if keep.insert(index) {
+                queue.extend(entries[index].refs());
+            }
+        }
+
+        entries
+            .drain(..)
+            .enumerate()
+            .map(|(index, entry)| keep.contains(&index).then_some(entry))
+            .collect()
+    }
+
+    /// Writes all cacheable tasks of the backend to disk.
+    ///
+    /// Entries of the loaded cache keep their index. Entries which have not
+    /// been used in this session are written unchanged until they age out,
+    /// and new entries reuse the indices of dropped ones.
+    pub fn snapshot(&self, backend: &MemoryBackend) -> Result<()> {
+        let _guard = self.snapshot_lock.lock();
+        let mut entries = self.aged_entries();
+
+        let mut tasks = Vec::new();
+        backend.with_all_cached_tasks_and_types(|ty, task| tasks.push((ty, task)));
+
+        let mut index_of: HashMap<TaskId, usize, BuildNoHashHasher<TaskId>> = HashMap::default();
+        let mut free = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.is_none())
+            .map(|(index, _)| index)
+            .rev()
+            .collect::<Vec<_>>();
+        let mut next_index = entries.len();
+        let mut new_entries = HashSet::new();
+        for (_, task) in tasks.iter() {
+            let index = self.task_to_entry.get(task).map(|e| *e).unwrap_or_else(|| {
+                let index = free.pop().unwrap_or_else(|| {
+                    next_index += 1;
+                    next_index - 1
+                });
+                new_entries.insert(index);
+                index
+            });
+            index_of.insert(*task, index);
+        }
+        let lookup = |task: TaskId| index_of.get(&task).copied();
+
+        entries.resize_with(next_index, || None);
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .map(|entry| entry.unwrap_or_else(CacheEntry::empty))
+            .collect();
+        for (ty, task) in tasks {
+            let index = index_of[&task];
+            let entry = &mut entries[index];
+            if new_entries.contains(&index) {
+                if let Some((key, key_refs)) = serialize_with(&*ty, lookup) {
+                    entry.key = Some(key);
+                    entry.key_refs = key_refs;
+                }
+            }
+            if self.pending.contains_key(&task) {
+                // Not executed in this session, keep the loaded data.
+                continue;
+            }
+            entry.data = if self.session_dependent.contains(&task) {
+                None
+            } else if let PersistentTaskType::Native(..) = &*ty {
+                backend
+                    .with_task(task, |task| task.cacheable_state())
+                    .and_then(|state| Self::entry_data(state, backend, lookup))
+            } else {
+                None
+            };
+        }
+
+        let file = CacheFile {
+            format: FORMAT_VERSION,
+            version: self.version.clone(),
+            entries,
+        };
+        let bytes = bincode_options().serialize(&file)?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, bytes)
+            .with_context(|| format!("failed to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    fn entry_data(
+        state: CacheableTaskState,
+        backend: &MemoryBackend,
+        lookup: impl Fn(TaskId) -> Option<usize> + Copy,
+    ) -> Option<CacheEntryData> {
+        let (output, mut refs) = serialize_with(&state.output, lookup)?;
+        let mut cells = Vec::with_capacity(state.cells.len());
+        for (cell, content) in state.cells {
+            let (bytes, cell_refs) = serialize_with(&content, lookup)?;
+            refs.extend(cell_refs);
+            cells.push((cell, bytes));
+        }
+        for child in state.children {
+            refs.push(lookup(child)?);
+        }
+        refs.sort_unstable();
+        refs.dedup();
+        let mut dependencies = Vec::with_capacity(state.dependencies.len());
+        for dependency in state.dependencies {
+            let (task, cell) = match dependency {
+                TaskDependency::TaskOutput(task) => (task, None),
+                TaskDependency::TaskCell(task, cell) => (task, Some(cell)),
+                TaskDependency::ScopeChildren(_) | TaskDependency::ScopeCollectibles(..) => {
+                    return None
+                }
+            };
+            let (bytes, _) = if let Some(cell) = cell {
+                let content = backend.with_task(task, |task| {
+                    task.with_cell(cell, |cell| cell.read_own_content_untracked())
+                });
+                serialize_with(&content, lookup)?
+            } else {
+                let output = backend.with_task(task, |task| task.linked_output())?;
+                serialize_with(&output, lookup)?
+            };
+            dependencies.push(CacheDependency {
+                entry: lookup(task)?,
+                cell,
+                hash: hash_xxh3_hash64(&bytes),
+            });
+        }
+        Some(CacheEntryData {
+            output,
+            cells,
+            dependencies,
+            refs,
+        })
+    }
+}
@@ -153,6 +153,15 @@ pub struct TaskScopeState {
     /// Number of active parents or tasks. Non-zero value means the scope is
     /// active
     active: isize,
+    /// If true, this is the root scope of an interactive root task. Tasks in
+    /// this scope and all child scopes are executed with interactive priority.
+    interactive: bool,
+    /// If true, an interactive task waits for this scope to finish. The
+    /// unfinished tasks are executed with interactive priority until then.
+    boosted: bool,
+    /// Caches if the scope has an interactive (transitive) parent, tagged
+    /// with the priority epoch of the backend it has been computed in.
+    has_interactive_parent: (usize, bool),
     /// When not active, this list contains all dirty tasks.
     /// When the scope becomes active, these need to be scheduled.
     dirty_tasks: AutoSet<TaskId, BuildNoHashHasher<TaskId>>,
@@ -263,7 +272,11 @@ impl TaskScope {
     pub fn add_parent(&self, parent: TaskScopeId, backend: &MemoryBackend) {
         {
             let mut state = self.state.lock();
-            if !state.parents.add(parent) || !state.has_unfinished_tasks {
+            if !state.parents.add(parent) {
+                return;
+            }
+            backend.scope_parents_changed();
+            if !state.has_unfinished_tasks {
                 return;
             }
         };
@@ -283,7 +296,11 @@ impl TaskScope {
     pub fn remove_parent(&self, parent: TaskScopeId, backend: &MemoryBackend) -> bool {
         let result = {
             let mut state = self.state.lock();
-            if !state.parents.remove(parent) || !state.has_unfinished_tasks {
+            if !state.parents.remove(parent) {
+                return state.parents.is_unset();
+            }
+            backend.scope_parents_changed();
+            if !state.has_unfinished_tasks {
                 return state.parents.is_unset();
             }
             state.parents.is_unset()
@@ -314,6 +331,9 @@ impl TaskScope {
                 }));
             } else {
                 state.event.notify(usize::MAX);
+                if take(&mut state.boosted) {
+                    backend.scope_unboosted();
+                }
                 to_update.extend(state.parents.iter().copied().filter(|parent| {
                     backend.with_scope(*parent, |scope| scope.decrement_unfinished_tasks_internal())
                 }));
@@ -469,6 +489,9 @@ impl TaskScopeState {
             #[cfg(feature = "print_scope_updates")]
             id,
             active: 0,
+            interactive: false,
+            boosted: false,
+            has_interactive_parent: (0, false),
             dirty_tasks: AutoSet::default(),
             children: CountHashSet::new(),
             collectibles: AutoMap::default(),
@@ -493,6 +516,9 @@ impl TaskScopeState {
             #[cfg(feature = "print_scope_updates")]
             id,
             active: 1,
+            interactive: false,
+            boosted: false,
+            has_interactive_parent: (0, false),
             dirty_tasks: AutoSet::default(),
             children: CountHashSet::new(),
             collectibles: AutoMap::default(),
@@ -513,6 +539,41 @@ impl TaskScopeState {
         self.active > 0
     }
 
+    /// returns true if the scope is the root scope of an interactive root task
+    /// or an interactive task waits for it
+    pub fn is_interactive(&self) -> bool {
+        self.interactive || self.boosted
+    }
+
+    /// flags the scope as waited for by an interactive task until its tasks
+    /// are finished, returns true when the flag has changed
+    pub fn boost(&mut self) -> bool {
+        if self.boosted || !self.has_unfinished_tasks {
+            return false;
+        }
+        self.boosted = true;
+        true
+    }
+
+    /// flags the scope as root scope of an interactive root task, returns true
+    /// when the flag has changed
+    pub fn set_interactive(&mut self, interactive: bool) -> bool {
+        let changed = self.interactive != interactive;
+        self.interactive = interactive;
+        changed
+    }
+
+    /// returns the cached interactive flag when it has been computed in the
+    /// given priority epoch
+    pub fn cached_interactive(&self, epoch: usize) -> Option<bool> {
+        let (cached_epoch, interactive) = self.has_interactive_parent;
+        (cached_epoch == epoch).then_some(interactive)
+    }
+
+    pub fn set_cached_interactive(&mut self, epoch: usize, interactive: bool) {
+        self.has_interactive_parent = (epoch, interactive);
+    }
+
     /// increments the active counter, returns list of tasks that need to be
     /// scheduled and list of child scope that need to be incremented after
     /// releasing the scope lock
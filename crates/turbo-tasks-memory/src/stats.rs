@@ -6,7 +6,8 @@ use std::{
     time::Duration,
 };
 
-use turbo_tasks::{registry, FunctionId, TaskId, TraitTypeId};
+use turbo_malloc::AllocationCounters;
+use turbo_tasks::{backend::TaskPriority, registry, FunctionId, TaskId, TraitTypeId};
 
 use crate::{
     scope::TaskScopeId,
@@ -65,6 +66,8 @@ pub struct ExportedTaskStats {
     pub count: usize,
     pub active_count: usize,
     pub unloaded_count: usize,
+    /// Number of tasks executed with [TaskPriority::Interactive]
+    pub interactive_count: usize,
     pub executions: Option<u32>,
     pub roots: usize,
     pub scopes: usize,
@@ -72,6 +75,8 @@ pub struct ExportedTaskStats {
     pub total_current_duration: Duration,
     pub total_update_duration: Duration,
     pub max_duration: Duration,
+    /// The allocations of all executions, only available with full stats.
+    pub allocations: Option<AllocationCounters>,
     pub references: HashMap<(ReferenceType, StatsTaskType), ReferenceStats>,
 }
 
@@ -81,6 +86,7 @@ impl Default for ExportedTaskStats {
             count: 0,
             active_count: 0,
             unloaded_count: 0,
+            interactive_count: 0,
             executions: None,
             roots: 0,
             scopes: 0,
@@ -88,6 +94,7 @@ impl Default for ExportedTaskStats {
             total_current_duration: Duration::ZERO,
             total_update_duration: Duration::ZERO,
             max_duration: Duration::ZERO,
+            allocations: None,
             references: Default::default(),
         }
     }
@@ -129,6 +136,7 @@ impl Stats {
             total_duration,
             last_duration,
             executions,
+            allocations,
             root_scoped,
             child_scopes,
             active,
@@ -145,6 +153,9 @@ impl Stats {
         if unloaded {
             stats.unloaded_count += 1
         }
+        if task.get_priority(backend) == TaskPriority::Interactive {
+            stats.interactive_count += 1
+        }
         stats.total_current_duration += last_duration;
         if executions.map(|executions| executions > 1).unwrap_or(true) {
             stats.total_update_duration += last_duration;
@@ -153,6 +164,12 @@ impl Stats {
         if let Some(executions) = executions {
             *stats.executions.get_or_insert(0) += executions;
         }
+        if let Some(allocations) = allocations {
+            stats
+                .allocations
+                .get_or_insert_default()
+                .merge(&allocations);
+        }
         if root_scoped {
             stats.roots += 1;
         }
@@ -186,6 +203,12 @@ impl Stats {
         });
     }
 
+    /// Returns the stats of all tasks of the given type, e. g. of all
+    /// executions of a function with [StatsTaskType::Native].
+    pub fn get(&self, ty: &StatsTaskType) -> Option<&ExportedTaskStats> {
+        self.tasks.get(ty)
+    }
+
     pub fn merge_resolve(&mut self) {
         self.merge(|ty, _stats| match ty {
             StatsTaskType::Root(_)
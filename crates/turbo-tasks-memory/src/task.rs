@@ -2,7 +2,6 @@ mod meta_state;
 mod stats;
 
 use std::{
-    borrow::Cow,
     cell::RefCell,
     cmp::{max, Ordering, Reverse},
     collections::{HashMap, HashSet, VecDeque},
@@ -21,8 +20,11 @@ use nohash_hasher::BuildNoHashHasher;
 use parking_lot::{Mutex, RwLock};
 use stats::TaskStats;
 use tokio::task_local;
+use turbo_malloc::AllocationCounters;
 use turbo_tasks::{
-    backend::{PersistentTaskType, TaskExecutionSpec},
+    backend::{
+        CellContent, PersistentTaskType, TaskExecutionResult, TaskExecutionSpec, TaskPriority,
+    },
     event::{Event, EventListener},
     get_invalidator,
     primitives::{RawVcSet, RawVcSetVc},
@@ -59,6 +61,15 @@ pub enum TaskDependency {
     ScopeCollectibles(TaskScopeId, TraitTypeId),
 }
 
+/// The result of a finished task execution, as stored in the persistent
+/// cache.
+pub(crate) struct CacheableTaskState {
+    pub output: RawVc,
+    pub cells: Vec<(CellId, CellContent)>,
+    pub dependencies: Vec<TaskDependency>,
+    pub children: Vec<TaskId>,
+}
+
 task_local! {
     /// Vc/Scopes that are read during task execution
     /// These will be stored as dependencies when the execution has finished
@@ -481,10 +492,12 @@ enum TaskStateType {
     ///
     /// on finish this will move to Done
     ///
-    /// on invalidation this will move to InProgressDirty
+    /// on invalidation this will move to InProgressDirty and notify `cancel`
     InProgress {
         event: Event,
         count_as_finished: bool,
+        /// Notified when the execution became stale and should be cancelled.
+        cancel: Event,
     },
 
     /// Invalid execution is happening
@@ -779,8 +792,12 @@ impl Task {
         if !self.try_start_execution(&mut state, turbo_tasks, backend) {
             return None;
         }
+        let cancel = match state.state_type {
+            InProgress { ref cancel, .. } => Some(cancel.listen()),
+            _ => None,
+        };
         let future = self.make_execution_future(state, backend, turbo_tasks);
-        Some(TaskExecutionSpec { future })
+        Some(TaskExecutionSpec { future, cancel })
     }
 
     /// Tries to change the state to InProgress and returns true if it was
@@ -797,9 +814,11 @@ impl Task {
                 return false;
             }
             Scheduled { ref mut event } => {
+                let description = self.get_event_description();
                 state.state_type = InProgress {
                     event: event.take(),
                     count_as_finished: false,
+                    cancel: Event::new(move || format!("TaskState({})::cancel", description())),
                 };
                 state.stats.increment_executions();
                 // TODO we need to reconsider the approach of doing scope changes in background
@@ -886,7 +905,11 @@ impl Task {
                         future
                     };
                     drop(state);
-                    future
+                    if let Some(cache) = &backend.persistent_cache {
+                        cache.wrap_execution(self.id, future, turbo_tasks)
+                    } else {
+                        future
+                    }
                 }
                 PersistentTaskType::ResolveNative(ref native_fn, inputs) => {
                     drop(state);
@@ -936,14 +959,14 @@ impl Task {
 
     pub(crate) fn execution_result(
         &self,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: TaskExecutionResult,
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) {
         let mut state = self.full_state_mut();
         match state.state_type {
             InProgress { .. } => match result {
-                Ok(Ok(result)) => {
+                TaskExecutionResult::Finished(Ok(result)) => {
                     if state.output != result {
                         if cfg!(feature = "print_task_invalidation")
                             && !matches!(state.output.content, OutputContent::Empty)
@@ -961,18 +984,41 @@ impl Task {
                         state.output.link(result, turbo_tasks)
                     }
                 }
-                Ok(Err(mut err)) => {
+                TaskExecutionResult::Finished(Err(mut err)) => {
                     if let Some(name) = self.get_function_name() {
                         err = err.context(format!("Execution of {} failed", name));
                     }
                     state.output.error(err, turbo_tasks)
                 }
-                Err(message) => state.output.panic(message, turbo_tasks),
+                TaskExecutionResult::Panicked(message) => state.output.panic(message, turbo_tasks),
+                TaskExecutionResult::Cancelled => {
+                    // Cancellation is only notified when the task is invalidated, which
+                    // moves it to InProgressDirty. Should a cancelled execution still end
+                    // up here, it has no result, so it needs to be executed again.
+                    if let InProgress {
+                        ref mut event,
+                        count_as_finished,
+                        ..
+                    } = state.state_type
+                    {
+                        let event = event.take();
+                        if count_as_finished {
+                            for scope in state.scopes.iter() {
+                                backend.with_scope(scope, |scope| {
+                                    scope.increment_unfinished_tasks(backend);
+                                })
+                            }
+                        }
+                        state.state_type = InProgressDirty { event };
+                    }
+                }
             },
             InProgressDirty { .. } => {
                 // We don't want to assign the output cell here
                 // as we want to avoid unnecessary updates
                 // TODO maybe this should be controlled by a heuristic
+                // A cancelled execution has no result and will be executed
+                // again
             }
             Dirty { .. } | Scheduled { .. } | Done { .. } => {
                 panic!(
@@ -988,6 +1034,7 @@ impl Task {
         &self,
         duration: Duration,
         instant: Instant,
+        allocations: AllocationCounters,
         stateful: bool,
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
@@ -997,13 +1044,16 @@ impl Task {
         {
             let mut state = self.full_state_mut();
 
-            state
-                .stats
-                .register_execution(duration, turbo_tasks.program_duration_until(instant));
+            state.stats.register_execution(
+                duration,
+                turbo_tasks.program_duration_until(instant),
+                allocations,
+            );
             match state.state_type {
                 InProgress {
                     ref mut event,
                     count_as_finished,
+                    ..
                 } => {
                     let event = event.take();
                     let mut dependencies = take(&mut dependencies);
@@ -1183,8 +1233,12 @@ impl Task {
                 InProgress {
                     ref mut event,
                     count_as_finished,
+                    ref cancel,
                 } => {
                     let event = event.take();
+                    // The running execution is stale now, so it's cancelled unless it's
+                    // stateful and scheduled again when it has finished.
+                    cancel.notify(usize::MAX);
                     if count_as_finished {
                         for scope in state.scopes.iter() {
                             backend.with_scope(scope, |scope| {
@@ -1629,6 +1683,29 @@ impl Task {
         self.make_root_scoped_internal(state, backend, turbo_tasks);
     }
 
+    /// Makes the task root scoped and returns its root scope.
+    pub(crate) fn get_or_create_root_scope(
+        &self,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
+    ) -> TaskScopeId {
+        let state = self.ensure_root_scoped(self.full_state_mut(), backend, turbo_tasks);
+        match state.scopes {
+            TaskScopes::Root(root) => root,
+            TaskScopes::Inner(..) => unreachable!("ensure_root_scoped makes the task root scoped"),
+        }
+    }
+
+    /// Returns the scheduling priority inherited from the scopes of the task.
+    pub(crate) fn get_priority(&self, backend: &MemoryBackend) -> TaskPriority {
+        let scopes = match self.state() {
+            TaskMetaStateReadGuard::Full(state) => state.scopes.iter().collect::<Vec<_>>(),
+            TaskMetaStateReadGuard::Partial(state) => state.scopes.iter().collect::<Vec<_>>(),
+            TaskMetaStateReadGuard::Unloaded(_) => return TaskPriority::Normal,
+        };
+        backend.get_scopes_priority(scopes)
+    }
+
     fn make_root_scoped_internal<'a>(
         &self,
         mut state: FullTaskWriteGuard<'a>,
@@ -1883,6 +1960,62 @@ impl Task {
         }
     }
 
+    /// Returns the output of the task if it currently links to a value.
+    pub(crate) fn linked_output(&self) -> Option<RawVc> {
+        if let TaskMetaStateReadGuard::Full(state) = self.state() {
+            if let OutputContent::Link(raw_vc) = state.output.content {
+                return Some(raw_vc);
+            }
+        }
+        None
+    }
+
+    /// Returns the output, cells, dependencies and children of the task when
+    /// they fully describe the last execution, so they can be stored in a
+    /// persistent cache. Returns None for tasks which are not done, are
+    /// stateful, emitted collectibles or had cells garbage collected.
+    pub(crate) fn cacheable_state(&self) -> Option<CacheableTaskState> {
+        let TaskMetaStateReadGuard::Full(state) = self.state() else {
+            return None;
+        };
+        let Done { ref dependencies } = state.state_type else {
+            return None;
+        };
+        if state.stateful {
+            return None;
+        }
+        if let Some(collectibles) = state.collectibles.as_ref() {
+            if !collectibles.emitted.is_empty() || !collectibles.unemitted.is_empty() {
+                return None;
+            }
+        }
+        let OutputContent::Link(output) = state.output.content else {
+            return None;
+        };
+        let mut cells = Vec::new();
+        for (&type_id, list) in state.cells.iter() {
+            for (index, cell) in list.iter().enumerate() {
+                match cell {
+                    Cell::Empty => {}
+                    Cell::Value { content, .. } => cells.push((
+                        CellId {
+                            type_id,
+                            index: index as u32,
+                        },
+                        content.clone(),
+                    )),
+                    Cell::TrackedValueless { .. } | Cell::Recomputing { .. } => return None,
+                }
+            }
+        }
+        Some(CacheableTaskState {
+            output,
+            cells,
+            dependencies: dependencies.iter().copied().collect(),
+            children: state.children.iter().copied().collect(),
+        })
+    }
+
     pub fn reset_stats(&self) {
         if let TaskMetaStateWriteGuard::Full(mut state) = self.state_mut() {
             state.stats.reset();
@@ -1892,12 +2025,13 @@ impl Task {
     pub fn get_stats_info(&self, backend: &MemoryBackend) -> TaskStatsInfo {
         match self.state() {
             TaskMetaStateReadGuard::Full(state) => {
-                let (total_duration, last_duration, executions) = match &state.stats {
-                    TaskStats::Essential(stats) => (None, stats.last_duration(), None),
+                let (total_duration, last_duration, executions, allocations) = match &state.stats {
+                    TaskStats::Essential(stats) => (None, stats.last_duration(), None, None),
                     TaskStats::Full(stats) => (
                         Some(stats.total_duration()),
                         stats.last_duration(),
                         Some(stats.executions()),
+                        Some(stats.allocations()),
                     ),
                 };
 
@@ -1905,6 +2039,7 @@ impl Task {
                     total_duration,
                     last_duration,
                     executions,
+                    allocations,
                     root_scoped: matches!(state.scopes, TaskScopes::Root(_)),
                     child_scopes: match state.scopes {
                         TaskScopes::Root(_) => 1,
@@ -1920,6 +2055,7 @@ impl Task {
                 total_duration: None,
                 last_duration: Duration::ZERO,
                 executions: None,
+                allocations: None,
                 root_scoped: false,
                 child_scopes: if let TaskScopes::Inner(ref set, _) = state.scopes {
                     set.len()
@@ -1933,6 +2069,7 @@ impl Task {
                 total_duration: None,
                 last_duration: Duration::ZERO,
                 executions: None,
+                allocations: None,
                 root_scoped: false,
                 child_scopes: 0,
                 active: false,
@@ -2002,6 +2139,135 @@ impl Task {
         }
     }
 
+    /// Collects everything known about the task for [crate::inspect].
+    #[cfg(feature = "inspect")]
+    pub(crate) fn inspect(&self, backend: &MemoryBackend) -> crate::inspect::TaskInfo {
+        use crate::inspect::{CellInfo, CollectibleInfo, DependencyInfo, ScopeInfo, TaskInfo};
+
+        let mut info = TaskInfo {
+            id: *self.id,
+            description: self.get_description(),
+            state: "unloaded".to_string(),
+            output: None,
+            children: Vec::new(),
+            dependencies: Vec::new(),
+            dependents: Vec::new(),
+            cells: Vec::new(),
+            collectibles: Vec::new(),
+            scopes: Vec::new(),
+            invalidations: backend.get_invalidation_reasons(self.id),
+        };
+        let scopes = match self.state() {
+            TaskMetaStateReadGuard::Full(state) => {
+                info.state = match state.state_type {
+                    Scheduled { .. } => "scheduled",
+                    InProgress { .. } => "in progress",
+                    InProgressDirty { .. } => "in progress (dirty)",
+                    Done { .. } => "done",
+                    Dirty { .. } => "dirty",
+                }
+                .to_string();
+                info.output = Some(state.output.content.to_string());
+                info.children = state
+                    .children
+                    .iter()
+                    .map(|&child| backend.get_task_ref(child))
+                    .collect();
+                if let Done { ref dependencies } = state.state_type {
+                    info.dependencies = dependencies
+                        .iter()
+                        .map(|dependency| match *dependency {
+                            TaskDependency::TaskOutput(task) => DependencyInfo {
+                                kind: "output",
+                                task: Some(backend.get_task_ref(task)),
+                                cell: None,
+                                scope: None,
+                            },
+                            TaskDependency::TaskCell(task, cell) => DependencyInfo {
+                                kind: "cell",
+                                task: Some(backend.get_task_ref(task)),
+                                cell: Some(cell.to_string()),
+                                scope: None,
+                            },
+                            TaskDependency::ScopeChildren(scope) => DependencyInfo {
+                                kind: "scope children",
+                                task: None,
+                                cell: None,
+                                scope: Some(*scope),
+                            },
+                            TaskDependency::ScopeCollectibles(scope, _) => DependencyInfo {
+                                kind: "scope collectibles",
+                                task: None,
+                                cell: None,
+                                scope: Some(*scope),
+                            },
+                        })
+                        .collect();
+                }
+                let mut dependents: AutoSet<TaskId, BuildNoHashHasher<TaskId>> =
+                    state.output.dependent_tasks.clone();
+                for (&type_id, list) in state.cells.iter() {
+                    for (index, cell) in list.iter().enumerate() {
+                        dependents.extend(cell.dependent_tasks().iter().copied());
+                        let (state, size) = match cell {
+                            Cell::Empty => ("empty", None),
+                            Cell::Value { content, .. } => {
+                                ("value", bincode::serialized_size(content).ok())
+                            }
+                            Cell::TrackedValueless { .. } => ("valueless", None),
+                            Cell::Recomputing { .. } => ("recomputing", None),
+                        };
+                        info.cells.push(CellInfo {
+                            cell: CellId {
+                                type_id,
+                                index: index as u32,
+                            }
+                            .to_string(),
+                            state,
+                            size,
+                            dependents: cell.dependent_tasks().len(),
+                        });
+                    }
+                }
+                info.dependents = dependents
+                    .into_iter()
+                    .map(|task| backend.get_task_ref(task))
+                    .collect();
+                if let Some(collectibles) = state.collectibles.as_ref() {
+                    for (emitted, list) in [
+                        (true, &collectibles.emitted),
+                        (false, &collectibles.unemitted),
+                    ] {
+                        info.collectibles
+                            .extend(list.iter().map(|(trait_type, value)| CollectibleInfo {
+                                trait_type: registry::get_trait(*trait_type).name.clone(),
+                                value: format!("{:?}", value),
+                                emitted,
+                            }));
+                    }
+                }
+                state.scopes.clone()
+            }
+            TaskMetaStateReadGuard::Partial(state) => state.scopes.clone(),
+            TaskMetaStateReadGuard::Unloaded(_) => TaskScopes::default(),
+        };
+        info.scopes = scopes
+            .iter()
+            .map(|scope| {
+                backend.with_scope(scope, |s| {
+                    let state = s.state.lock();
+                    ScopeInfo {
+                        id: *scope,
+                        root: scopes.is_root(),
+                        active: state.is_active(),
+                        interactive: state.is_interactive(),
+                    }
+                })
+            })
+            .collect();
+        info
+    }
+
     fn state_string(state: &TaskState) -> String {
         let mut state_str = match state.state_type {
             Scheduled { .. } => "scheduled".to_string(),
@@ -2149,6 +2415,7 @@ impl Task {
                     }
                     None
                 }) {
+                    backend.boost_scope(root, turbo_tasks);
                     return Ok(Err(listener));
                 }
             } else {
@@ -2199,13 +2466,18 @@ impl Task {
                     let state =
                         task.ensure_root_scoped(task.full_state_mut(), backend, turbo_tasks);
                     if let TaskScopes::Root(scope_id) = state.scopes {
-                        backend.with_scope(scope_id, |scope| {
-                            scope.read_collectibles_and_children(
-                                scope_id,
-                                trait_type_id,
-                                read_task_id,
-                            )
-                        })
+                        backend
+                            .with_scope(scope_id, |scope| {
+                                scope.read_collectibles_and_children(
+                                    scope_id,
+                                    trait_type_id,
+                                    read_task_id,
+                                )
+                            })
+                            .map_err(|listener| {
+                                backend.boost_scope(scope_id, turbo_tasks);
+                                listener
+                            })
                     } else {
                         unreachable!();
                     }
@@ -2227,9 +2499,14 @@ impl Task {
             read_task_id,
             |turbo_tasks| {
                 let backend = turbo_tasks.backend();
-                backend.with_scope(scope_id, |scope| {
-                    scope.read_collectibles_and_children(scope_id, trait_type_id, read_task_id)
-                })
+                backend
+                    .with_scope(scope_id, |scope| {
+                        scope.read_collectibles_and_children(scope_id, trait_type_id, read_task_id)
+                    })
+                    .map_err(|listener| {
+                        backend.boost_scope(scope_id, turbo_tasks);
+                        listener
+                    })
             },
             trait_type_id,
             turbo_tasks,
@@ -2789,6 +3066,8 @@ impl Task {
             *state = TaskMetaState::Partial(box PartialTaskState { scopes, stats_type });
         }
         drop(state);
+        #[cfg(feature = "inspect")]
+        backend.remove_invalidation_reasons(self.id);
 
         // Notify everyone that is listening on our output or cells.
         // This will mark everyone as dirty and will trigger a new execution when they
@@ -2962,6 +3241,7 @@ pub struct TaskStatsInfo {
     pub total_duration: Option<Duration>,
     pub last_duration: Duration,
     pub executions: Option<u32>,
+    pub allocations: Option<AllocationCounters>,
     pub root_scoped: bool,
     pub child_scopes: usize,
     pub active: bool,
@@ -1,9 +1,10 @@
 use std::time::Duration;
 
+use turbo_malloc::AllocationCounters;
 use turbo_tasks::{small_duration::SmallDuration, StatsType};
 
-/// Keeps track of the number of times a task has been executed, and its
-/// duration.
+/// Keeps track of the number of times a task has been executed, its duration
+/// and its allocations.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum TaskStats {
     Essential(TaskStatsEssential),
@@ -35,12 +36,18 @@ impl TaskStats {
         }
     }
 
-    /// Registers a task duration.
-    pub fn register_execution(&mut self, duration: Duration, duration_since_start: Duration) {
+    /// Registers a task duration and the allocations made by the execution.
+    pub fn register_execution(
+        &mut self,
+        duration: Duration,
+        duration_since_start: Duration,
+        allocations: AllocationCounters,
+    ) {
         match self {
             Self::Full(stats) => {
                 stats.total_duration += duration;
                 stats.last_duration = duration;
+                stats.allocations.merge(&allocations);
             }
             Self::Essential(stats) => {
                 stats.last_duration = duration.into();
@@ -56,6 +63,7 @@ impl TaskStats {
                 stats.executions = 0;
                 stats.total_duration = Duration::ZERO;
                 stats.last_duration = Duration::ZERO;
+                stats.allocations = AllocationCounters::default();
             }
             Self::Essential(stats) => {
                 stats.last_duration = SmallDuration::MIN;
@@ -116,6 +124,9 @@ pub struct TaskStatsFull {
     /// The last execution of the task relative to the start of the program,
     /// with a precision of 1 millisecond.
     last_execution_relative_to_start: SmallDuration<1_000_000>,
+    /// The allocations of all executions of the task. Only counted when
+    /// [turbo_malloc::TurboMalloc] is the global allocator.
+    allocations: AllocationCounters,
 }
 
 impl TaskStatsFull {
@@ -134,6 +145,11 @@ impl TaskStatsFull {
         self.total_duration
     }
 
+    /// Returns the allocations of all executions of the task.
+    pub fn allocations(&self) -> AllocationCounters {
+        self.allocations
+    }
+
     /// Returns the last execution of the task relative to the start of the
     /// program.
     #[allow(dead_code)] // NOTE(alexkirsz) This will be useful for GC.
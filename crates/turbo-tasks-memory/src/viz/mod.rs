@@ -37,6 +37,7 @@ struct MaxValues {
     pub count: usize,
     pub active_count: usize,
     pub unloaded_count: usize,
+    pub interactive_count: usize,
     pub updates: Option<usize>,
     pub roots: usize,
     /// stored as scopes * 100
@@ -83,6 +84,7 @@ fn get_max_values_internal(depth: u32, node: &GroupTree) -> MaxValues {
     let mut max_count = 0;
     let mut max_active_count = 0;
     let mut max_unloaded_count = 0;
+    let mut max_interactive_count = 0;
     let mut max_updates = None;
     let mut max_roots = 0;
     let mut max_scopes = 0;
@@ -109,6 +111,7 @@ fn get_max_values_internal(depth: u32, node: &GroupTree) -> MaxValues {
         max_count = max(max_count, s.count);
         max_active_count = max(max_active_count, s.active_count);
         max_unloaded_count = max(max_unloaded_count, s.unloaded_count);
+        max_interactive_count = max(max_interactive_count, s.interactive_count);
         if let Some(executions) = s.executions {
             let updates = (executions as usize).saturating_sub(s.count);
             max_updates = max_updates
@@ -138,6 +141,7 @@ fn get_max_values_internal(depth: u32, node: &GroupTree) -> MaxValues {
             count,
             active_count,
             unloaded_count,
+            interactive_count,
             updates,
             roots,
             scopes,
@@ -155,6 +159,7 @@ fn get_max_values_internal(depth: u32, node: &GroupTree) -> MaxValues {
         max_count = max(max_count, count);
         max_active_count = max(max_active_count, active_count);
         max_unloaded_count = max(max_unloaded_count, unloaded_count);
+        max_interactive_count = max(max_interactive_count, interactive_count);
         max_updates = max_updates.zip(updates).map(|(a, b)| max(a, b));
         max_roots = max(max_roots, roots);
         max_scopes = max(max_scopes, scopes);
@@ -171,6 +176,7 @@ fn get_max_values_internal(depth: u32, node: &GroupTree) -> MaxValues {
         count: max_count,
         active_count: max_active_count,
         unloaded_count: max_unloaded_count,
+        interactive_count: max_interactive_count,
         updates: max_updates,
         roots: max_roots,
         scopes: max_scopes,
@@ -44,6 +44,7 @@ pub fn create_table(root: GroupTree, stats_type: StatsType) -> String {
     out += r#"<th>count</th>"#;
     out += r#"<th>active</th>"#;
     out += r#"<th>unloaded</th>"#;
+    out += r#"<th>interactive</th>"#;
     out += r#"<th>reexecutions</th>"#;
     out += r#"<th>total duration</th>"#;
     out += r#"<th>total current duration</th>"#;
@@ -97,6 +98,13 @@ pub fn create_table(root: GroupTree, stats_type: StatsType) -> String {
             as_frac_color(stats.unloaded_count, max_values.unloaded_count),
             stats.unloaded_count
         )?;
+        // interactive
+        write!(
+            out,
+            "<td bgcolor=\"{}\">{}</td>",
+            as_frac_color(stats.interactive_count, max_values.interactive_count),
+            stats.interactive_count
+        )?;
         // reexecutions
         let (executions_label, executions_color) =
             if let Some((executions, max_updates)) = stats.executions.zip(max_values.updates) {
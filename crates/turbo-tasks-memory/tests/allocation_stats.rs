@@ -0,0 +1,79 @@
+#![feature(min_specialization)]
+
+use std::hint::black_box;
+
+use anyhow::Result;
+use turbo_malloc::TurboMalloc;
+use turbo_tasks::{StatsType, TurboTasks, TurboTasksBackendApi};
+use turbo_tasks_memory::{
+    stats::{Stats, StatsTaskType},
+    MemoryBackend,
+};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[global_allocator]
+static ALLOC: TurboMalloc = TurboMalloc;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+const CHUNKS: u32 = 8;
+
+#[tokio::test]
+async fn attributes_allocations_to_functions() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.set_stats_type(StatsType::Full);
+    let result = tt
+        .run_once(async move { Ok(*caller(CHUNKS).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(result, CHUNKS as usize * CHUNK_SIZE);
+
+    let backend = tt.backend();
+    let mut stats = Stats::new();
+    backend.with_all_cached_tasks(|id| stats.add_id(backend, id));
+
+    let allocate = stats
+        .get(&StatsTaskType::Native(*ALLOCATE_FUNCTION_ID))
+        .unwrap()
+        .allocations
+        .unwrap();
+    assert!(
+        allocate.allocated >= CHUNKS as usize * CHUNK_SIZE,
+        "{allocate:?}"
+    );
+    assert!(
+        allocate.freed >= CHUNKS as usize * CHUNK_SIZE,
+        "{allocate:?}"
+    );
+    // Only a single chunk is alive at a time
+    assert!(allocate.peak >= CHUNK_SIZE, "{allocate:?}");
+    assert!(allocate.peak < 2 * CHUNK_SIZE, "{allocate:?}");
+
+    // The allocations of the called function are not attributed to the caller
+    let caller = stats
+        .get(&StatsTaskType::Native(*CALLER_FUNCTION_ID))
+        .unwrap()
+        .allocations
+        .unwrap();
+    assert!(caller.allocated < CHUNK_SIZE, "{caller:?}");
+}
+
+#[turbo_tasks::value(transparent)]
+struct Size(usize);
+
+#[turbo_tasks::function]
+fn allocate(chunks: u32) -> SizeVc {
+    let mut size = 0;
+    for i in 0..chunks {
+        let chunk = black_box(vec![i as u8; CHUNK_SIZE]);
+        size += chunk.len();
+    }
+    SizeVc::cell(size)
+}
+
+#[turbo_tasks::function]
+async fn caller(chunks: u32) -> Result<SizeVc> {
+    Ok(SizeVc::cell(*allocate(chunks).await?))
+}
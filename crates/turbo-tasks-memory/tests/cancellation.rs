@@ -0,0 +1,172 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, mark_stateful, Invalidator, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{register, run};
+
+register!();
+
+static STARTED: AtomicUsize = AtomicUsize::new(0);
+static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+static STATEFUL_STARTED: AtomicUsize = AtomicUsize::new(0);
+static STATEFUL_COMPLETED: AtomicUsize = AtomicUsize::new(0);
+static LINKED_STARTED: AtomicUsize = AtomicUsize::new(0);
+static LINKED_COMPLETED: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn cancels_stale_execution() {
+    run! {
+        let input = InputVc::cell(Input { value: Mutex::new((1, None)) });
+        let start = Instant::now();
+
+        let (result, ()) = tokio::join!(
+            async { slow(input).strongly_consistent().await },
+            async {
+                while STARTED.load(Ordering::SeqCst) == 0 {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+                input.await.unwrap().set(2);
+            },
+        );
+
+        // The first execution was dropped while sleeping, only the second one completed
+        assert_eq!(*result?, 20);
+        assert_eq!(STARTED.load(Ordering::SeqCst), 2);
+        assert_eq!(COMPLETED.load(Ordering::SeqCst), 1);
+        assert!(start.elapsed() < Duration::from_secs(60));
+    }
+}
+
+#[tokio::test]
+async fn finishes_stale_stateful_execution() {
+    run! {
+        let input = InputVc::cell(Input { value: Mutex::new((1, None)) });
+
+        let (result, ()) = tokio::join!(
+            async { slow_stateful(input).strongly_consistent().await },
+            async {
+                while STATEFUL_STARTED.load(Ordering::SeqCst) == 0 {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+                input.await.unwrap().set(2);
+            },
+        );
+
+        // The first execution is not cancelled, but executed again afterwards
+        assert_eq!(*result?, 20);
+        assert_eq!(STATEFUL_STARTED.load(Ordering::SeqCst), 2);
+        assert_eq!(STATEFUL_COMPLETED.load(Ordering::SeqCst), 2);
+    }
+}
+
+#[tokio::test]
+async fn waits_for_fresh_results() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let root = tt.spawn_root_task(|| Box::pin(async { Ok(forward(linked_input()).into()) }));
+
+    while LINKED_STARTED.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    tt.run_once(async {
+        linked_input().await?.set(2);
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    // The root task is done already, but the output it links to is only fresh
+    // once the cancelled execution has been executed again
+    tt.wait_task_completion(root, false).await.unwrap();
+    assert_eq!(LINKED_STARTED.load(Ordering::SeqCst), 2);
+    assert_eq!(LINKED_COMPLETED.load(Ordering::SeqCst), 1);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Input {
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    value: Mutex<(u32, Option<Invalidator>)>,
+}
+
+impl Input {
+    fn set(&self, value: u32) {
+        let mut lock = self.value.lock().unwrap();
+        lock.0 = value;
+        if let Some(i) = lock.1.take() {
+            i.invalidate();
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl InputVc {
+    #[turbo_tasks::function]
+    async fn get_value(self) -> Result<ValueVc> {
+        let this = self.await?;
+        let mut lock = this.value.lock().unwrap();
+        lock.1 = Some(get_invalidator());
+        Ok(ValueVc::cell(lock.0))
+    }
+}
+
+#[turbo_tasks::function]
+async fn slow(input: InputVc) -> Result<ValueVc> {
+    let value = *input.get_value().await?;
+    STARTED.fetch_add(1, Ordering::SeqCst);
+    if value == 1 {
+        tokio::time::sleep(Duration::from_secs(600)).await;
+    }
+    COMPLETED.fetch_add(1, Ordering::SeqCst);
+    Ok(ValueVc::cell(value * 10))
+}
+
+#[turbo_tasks::function]
+async fn slow_stateful(input: InputVc) -> Result<ValueVc> {
+    mark_stateful();
+    let value = *input.get_value().await?;
+    STATEFUL_STARTED.fetch_add(1, Ordering::SeqCst);
+    if value == 1 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    STATEFUL_COMPLETED.fetch_add(1, Ordering::SeqCst);
+    Ok(ValueVc::cell(value * 10))
+}
+
+#[turbo_tasks::function]
+fn linked_input() -> InputVc {
+    InputVc::cell(Input {
+        value: Mutex::new((1, None)),
+    })
+}
+
+#[turbo_tasks::function]
+fn forward(input: InputVc) -> ValueVc {
+    slow_linked(input)
+}
+
+#[turbo_tasks::function]
+async fn slow_linked(input: InputVc) -> Result<ValueVc> {
+    let value = *input.get_value().await?;
+    LINKED_STARTED.fetch_add(1, Ordering::SeqCst);
+    // The fresh execution takes a moment too, so waiting for it can be observed
+    let duration = if value == 1 {
+        Duration::from_secs(600)
+    } else {
+        Duration::from_millis(100)
+    };
+    tokio::time::sleep(duration).await;
+    LINKED_COMPLETED.fetch_add(1, Ordering::SeqCst);
+    Ok(ValueVc::cell(value * 10))
+}
@@ -0,0 +1,122 @@
+#![feature(min_specialization)]
+#![cfg(feature = "inspect")]
+
+use std::{
+    fmt::{Display, Formatter},
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::Result;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use turbo_tasks::{get_invalidator, InvalidationReason, Invalidator, TaskId, TurboTasks};
+use turbo_tasks_memory::{inspect, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+static SOURCE: AtomicU32 = AtomicU32::new(1);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn inspect_task_graph() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default().with_inspector());
+    let result = tt
+        .run_once(async move { Ok(*double(read_source()).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(result, 2);
+    let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+    invalidator.invalidate_with_reason(SourceChange);
+    tt.run_once(async move { Ok(*double(read_source()).strongly_consistent().await?) })
+        .await
+        .unwrap();
+
+    let backend = tt.backend();
+    let source = backend.search_tasks("read_source", 10);
+    assert_eq!(source.len(), 1);
+    // The first match is the task resolving the argument of `double`
+    let double = backend.search_tasks("double", 10);
+    assert_eq!(double.len(), 2);
+    assert!(double[0].description.contains("[resolve]"));
+
+    let info = backend.inspect_task(TaskId::from(source[0].id)).unwrap();
+    assert_eq!(info.state, "done");
+    assert_eq!(info.invalidations, vec!["source changed".to_string()]);
+    assert_eq!(info.dependents, double);
+    assert_eq!(info.cells.len(), 1);
+    assert_eq!(info.cells[0].state, "value");
+    assert!(info.cells[0].size.is_some());
+
+    let info = backend.inspect_task(TaskId::from(double[1].id)).unwrap();
+    assert!(info.dependencies.iter().any(
+        |dependency| dependency.kind == "cell" && dependency.task.as_ref() == Some(&source[0])
+    ));
+    assert!(info.invalidations.is_empty());
+
+    // The same information is served as JSON
+    let addr: SocketAddr = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+    let server = tokio::spawn(inspect::serve(tt.clone(), addr));
+    let response = loop {
+        match get(addr, &format!("/api/task/{}", source[0].id)).await {
+            Ok(response) => break response,
+            Err(_) => tokio::task::yield_now().await,
+        }
+    };
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains(r#""invalidations":["source changed"]"#));
+    let response = get(addr, "/api/task/999999").await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+    let response = get(addr, "/api/tasks?q=dou").await.unwrap();
+    assert!(response.contains("double"), "{}", response);
+    assert!(!response.contains("read_source"), "{}", response);
+    server.abort();
+}
+
+async fn get(addr: SocketAddr, path: &str) -> Result<String> {
+    let mut stream = tokio::net::TcpStream::connect(addr).await?;
+    stream
+        .write_all(
+            format!(
+                "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+                path, addr
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct SourceChange;
+
+impl InvalidationReason for SourceChange {}
+
+impl Display for SourceChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "source changed")
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+fn read_source() -> NumberVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    NumberVc::cell(SOURCE.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn double(value: NumberVc) -> Result<NumberVc> {
+    Ok(NumberVc::cell(*value.await? * 2))
+}
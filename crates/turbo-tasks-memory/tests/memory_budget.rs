@@ -0,0 +1,110 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use tokio::sync::broadcast::error::TryRecvError;
+use turbo_malloc::TurboMalloc;
+use turbo_tasks::{small_duration::SmallDuration, TurboTasks};
+use turbo_tasks_memory::{GcPriority, MemoryBackend, MemoryBudget};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[global_allocator]
+static ALLOC: TurboMalloc = TurboMalloc;
+
+const CHUNK_SIZE: usize = 256 * 1024;
+const CHUNKS: u32 = 256;
+const BUDGET: usize = 16 * 1024 * 1024;
+
+// A single threaded runtime interleaves the garbage collection with the
+// executions deterministically
+#[tokio::test(flavor = "current_thread")]
+async fn memory_stays_within_budget() {
+    lazy_static::initialize(&REGISTER);
+    let limit = TurboMalloc::memory_usage() + BUDGET;
+    let backend = MemoryBackend::default().with_memory_budget(MemoryBudget::escalating(limit));
+    let mut events = backend.subscribe_gc_events();
+    let tt = TurboTasks::new(backend);
+
+    // The graph holds 4 times the budget
+    let result = tt
+        .run_once(async move { Ok(*sum(CHUNKS).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(result, CHUNKS as usize * CHUNK_SIZE);
+
+    // Only the most recent events are buffered
+    let mut max_priorities = Vec::new();
+    let mut empty_cells = 0;
+    loop {
+        match events.try_recv() {
+            Ok(event) => {
+                assert!(
+                    event.memory_after <= limit,
+                    "memory after gc {} exceeds limit {}",
+                    event.memory_after,
+                    limit
+                );
+                max_priorities.push(event.max_priority);
+                empty_cells += event.stats.empty_cells;
+            }
+            Err(TryRecvError::Lagged(_)) => {}
+            Err(_) => break,
+        }
+    }
+    // All cells are emptied above the last threshold
+    assert!(max_priorities.contains(&None), "{:?}", max_priorities);
+    assert!(empty_cells > 0);
+}
+
+#[test]
+fn default_budget_takes_all_actions() {
+    let budget = MemoryBudget::new(1600);
+    assert_eq!(budget.start(true), 1200);
+    assert_eq!(budget.start(false), 1400);
+    assert_eq!(budget.max_priority(1500), None);
+    assert_eq!(budget.collect_factor(1500, 1400), 31);
+    assert_eq!(budget.collect_factor(1410, 1400), 12);
+}
+
+#[test]
+fn escalating_budget_escalates_with_usage() {
+    let budget = MemoryBudget::escalating(1600);
+    assert_eq!(budget.start(false), 1200);
+    assert_eq!(
+        budget.max_priority(1200),
+        Some(GcPriority::InactiveEmptyUnusedCells {
+            compute_duration: SmallDuration::MAX
+        })
+    );
+    assert_eq!(
+        budget.max_priority(1400),
+        Some(GcPriority::EmptyUnusedCells {
+            compute_duration: SmallDuration::MAX
+        })
+    );
+    assert_eq!(budget.max_priority(1500), None);
+    assert_eq!(budget.collect_factor(1200, 1200), 0);
+    assert_eq!(budget.collect_factor(1400, 1200), 127);
+    assert_eq!(budget.collect_factor(2000, 1200), 255);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Chunk(Vec<u8>);
+
+#[turbo_tasks::value(transparent)]
+struct Size(usize);
+
+#[turbo_tasks::function]
+fn chunk(i: u32) -> ChunkVc {
+    ChunkVc::cell(vec![i as u8; CHUNK_SIZE])
+}
+
+#[turbo_tasks::function]
+async fn sum(chunks: u32) -> Result<SizeVc> {
+    let mut sum = 0;
+    for i in 0..chunks {
+        sum += chunk(i).await?.len();
+    }
+    Ok(SizeVc::cell(sum))
+}
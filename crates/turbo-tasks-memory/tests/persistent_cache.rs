@@ -0,0 +1,188 @@
+#![feature(min_specialization)]
+
+use std::{
+    path::Path,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use turbo_tasks::{mark_session_dependent, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static COMPUTATIONS: AtomicUsize = AtomicUsize::new(0);
+static SOURCE: AtomicU32 = AtomicU32::new(0);
+static SOURCE_READS: AtomicUsize = AtomicUsize::new(0);
+static SOURCE_COMPUTATIONS: AtomicUsize = AtomicUsize::new(0);
+static AGED_COMPUTATIONS: AtomicUsize = AtomicUsize::new(0);
+
+async fn run_session(cache: &Path, version: &str, compute: fn() -> NumberVc) -> Result<u32> {
+    run_backend(
+        MemoryBackend::default().with_persistent_cache(cache, version),
+        compute,
+    )
+    .await
+}
+
+async fn run_backend(backend: MemoryBackend, compute: fn() -> NumberVc) -> Result<u32> {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(backend);
+    let result = tt
+        .run_once(async move {
+            let value = *compute().await?;
+            Ok(value)
+        })
+        .await?;
+    tt.stop_and_wait().await;
+    Ok(result)
+}
+
+#[tokio::test]
+async fn restores_unchanged_tasks() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("cache");
+    COMPUTATIONS.store(0, Ordering::SeqCst);
+
+    fn compute() -> NumberVc {
+        double(base(21))
+    }
+
+    assert_eq!(run_session(&cache, "1", compute).await.unwrap(), 42);
+    assert_eq!(COMPUTATIONS.load(Ordering::SeqCst), 2);
+    assert!(cache.exists());
+
+    assert_eq!(run_session(&cache, "1", compute).await.unwrap(), 42);
+    assert_eq!(COMPUTATIONS.load(Ordering::SeqCst), 2);
+
+    // A different version discards the cache
+    assert_eq!(run_session(&cache, "2", compute).await.unwrap(), 42);
+    assert_eq!(COMPUTATIONS.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn validates_session_dependent_tasks() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("cache");
+    SOURCE.store(1, Ordering::SeqCst);
+    SOURCE_READS.store(0, Ordering::SeqCst);
+    SOURCE_COMPUTATIONS.store(0, Ordering::SeqCst);
+
+    fn compute() -> NumberVc {
+        source_plus_one(read_source())
+    }
+
+    assert_eq!(run_session(&cache, "1", compute).await.unwrap(), 2);
+    assert_eq!(SOURCE_READS.load(Ordering::SeqCst), 1);
+    assert_eq!(SOURCE_COMPUTATIONS.load(Ordering::SeqCst), 1);
+
+    // The source is read again, but the dependent task is restored
+    assert_eq!(run_session(&cache, "1", compute).await.unwrap(), 2);
+    assert_eq!(SOURCE_READS.load(Ordering::SeqCst), 2);
+    assert_eq!(SOURCE_COMPUTATIONS.load(Ordering::SeqCst), 1);
+
+    // A changed source invalidates the cached dependent task
+    SOURCE.store(10, Ordering::SeqCst);
+    assert_eq!(run_session(&cache, "1", compute).await.unwrap(), 11);
+    assert_eq!(SOURCE_READS.load(Ordering::SeqCst), 3);
+    assert_eq!(SOURCE_COMPUTATIONS.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn drops_entries_unused_for_several_sessions() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("cache");
+    AGED_COMPUTATIONS.store(0, Ordering::SeqCst);
+
+    let run = |compute| {
+        run_backend(
+            MemoryBackend::default()
+                .with_persistent_cache(&cache, "1")
+                .with_persistent_cache_max_unused_sessions(1),
+            compute,
+        )
+    };
+    fn aged_task() -> NumberVc {
+        aged(3)
+    }
+    fn other_task() -> NumberVc {
+        constant()
+    }
+
+    assert_eq!(run(aged_task).await.unwrap(), 3);
+    assert_eq!(AGED_COMPUTATIONS.load(Ordering::SeqCst), 1);
+
+    // Kept for one unused session, and using it again resets its age
+    assert_eq!(run(other_task).await.unwrap(), 7);
+    assert_eq!(run(aged_task).await.unwrap(), 3);
+    assert_eq!(AGED_COMPUTATIONS.load(Ordering::SeqCst), 1);
+
+    // Dropped after two unused sessions
+    assert_eq!(run(other_task).await.unwrap(), 7);
+    assert_eq!(run(other_task).await.unwrap(), 7);
+    assert_eq!(run(aged_task).await.unwrap(), 3);
+    assert_eq!(AGED_COMPUTATIONS.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn reports_write_errors() {
+    lazy_static::initialize(&REGISTER);
+    let dir = tempfile::tempdir().unwrap();
+    // The parent of the cache is a file, so it can't be written
+    let file = dir.path().join("file");
+    std::fs::write(&file, "").unwrap();
+    let tt =
+        TurboTasks::new(MemoryBackend::default().with_persistent_cache(file.join("cache"), "1"));
+    let result = tt
+        .run_once(async move {
+            let value = *constant().await?;
+            Ok(value)
+        })
+        .await
+        .unwrap();
+    assert_eq!(result, 7);
+    tt.stop_and_wait().await;
+
+    assert!(tt.backend().take_persist_cache_error().is_some());
+    assert!(tt.backend().take_persist_cache_error().is_none());
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+fn base(value: u32) -> NumberVc {
+    COMPUTATIONS.fetch_add(1, Ordering::SeqCst);
+    NumberVc::cell(value)
+}
+
+#[turbo_tasks::function]
+async fn double(value: NumberVc) -> Result<NumberVc> {
+    COMPUTATIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(NumberVc::cell(*value.await? * 2))
+}
+
+#[turbo_tasks::function]
+fn aged(value: u32) -> NumberVc {
+    AGED_COMPUTATIONS.fetch_add(1, Ordering::SeqCst);
+    NumberVc::cell(value)
+}
+
+#[turbo_tasks::function]
+fn constant() -> NumberVc {
+    NumberVc::cell(7)
+}
+
+#[turbo_tasks::function]
+fn read_source() -> NumberVc {
+    mark_session_dependent();
+    SOURCE_READS.fetch_add(1, Ordering::SeqCst);
+    NumberVc::cell(SOURCE.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn source_plus_one(value: NumberVc) -> Result<NumberVc> {
+    SOURCE_COMPUTATIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(NumberVc::cell(*value.await? + 1))
+}
@@ -0,0 +1,157 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    backend::TaskPriority, turbo_tasks, Completion, CompletionVc, RawVc, TaskId, TurboTasks,
+    MAX_INTERACTIVE_DELAY,
+};
+use turbo_tasks_memory::{
+    stats::{ExportedTaskStats, GroupTree, ReferenceType, Stats},
+    MemoryBackend,
+};
+use turbo_tasks_testing::register;
+
+register!();
+
+static LOG: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+#[tokio::test]
+async fn interactive_tasks_are_executed_first() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let background = tt.spawn_root_task(|| Box::pin(async { Ok(prefetch().into()) }));
+    let interactive = tt.spawn_root_task_with_priority(TaskPriority::Interactive, || {
+        Box::pin(async { Ok(request().into()) })
+    });
+
+    tt.wait_task_completion(interactive, true).await.unwrap();
+    tt.wait_task_completion(background, true).await.unwrap();
+    assert_eq!(
+        *LOG.lock().unwrap(),
+        vec!["render done", "request done", "prefetch started"]
+    );
+
+    // Subtasks of the interactive root task are reported as interactive
+    assert_eq!(interactive_count(&tt, "render"), 1);
+    assert_eq!(interactive_count(&tt, "prefetch"), 0);
+
+    tt.set_root_task_priority(interactive, TaskPriority::Normal);
+    assert_eq!(interactive_count(&tt, "render"), 0);
+}
+
+#[tokio::test]
+async fn tasks_waited_for_by_interactive_tasks_are_boosted() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let rx = Arc::new(Mutex::new(Some(rx)));
+    let interactive = tt.spawn_root_task_with_priority(TaskPriority::Interactive, move || {
+        let rx = rx.clone();
+        Box::pin(async move {
+            let rx = rx.lock().unwrap().take();
+            if let Some(rx) = rx {
+                let background: TaskId = rx.await?;
+                // Neither the task nor its unfinished subtask are part of the
+                // interactive root task, they would wait for it otherwise
+                RawVc::TaskOutput(background)
+                    .into_strongly_consistent_read_untracked::<Completion>(&*turbo_tasks())
+                    .await?;
+            }
+            Ok(CompletionVc::new().into())
+        })
+    });
+    let background = tt.spawn_root_task(|| Box::pin(async { Ok(spawn_subtask().into()) }));
+    tx.send(background).unwrap();
+
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        tt.wait_task_completion(interactive, true),
+    )
+    .await
+    .expect("interactive task waits for normal tasks")
+    .unwrap();
+}
+
+#[tokio::test]
+async fn normal_tasks_are_not_starved() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+    let rx = Arc::new(Mutex::new(Some(rx)));
+    // Keeps executing until the normal task has finished
+    let interactive = tt.spawn_root_task_with_priority(TaskPriority::Interactive, move || {
+        let rx = rx.clone();
+        Box::pin(async move {
+            let rx = rx.lock().unwrap().take();
+            if let Some(rx) = rx {
+                rx.await?;
+            }
+            Ok(CompletionVc::new().into())
+        })
+    });
+    let normal = tt.spawn_root_task(|| Box::pin(async { Ok(CompletionVc::new().into()) }));
+
+    tokio::time::timeout(
+        MAX_INTERACTIVE_DELAY * 10,
+        tt.wait_task_completion(normal, true),
+    )
+    .await
+    .expect("normal task is only held back for a limited time")
+    .unwrap();
+    tx.send(()).unwrap();
+    tt.wait_task_completion(interactive, true).await.unwrap();
+}
+
+fn interactive_count(tt: &TurboTasks<MemoryBackend>, name: &str) -> usize {
+    fn find<'a>(tree: &'a GroupTree, name: &str) -> Option<&'a ExportedTaskStats> {
+        tree.primary
+            .iter()
+            .chain(tree.task_types.iter())
+            .find(|(ty, _)| ty.to_string().ends_with(name))
+            .map(|(_, stats)| stats)
+            .or_else(|| tree.children.iter().find_map(|child| find(child, name)))
+    }
+    let backend = tt.backend();
+    let mut stats = Stats::new();
+    backend.with_all_cached_tasks(|task| stats.add_id(backend, task));
+    let tree = stats.treeify(ReferenceType::Child);
+    find(&tree, name).unwrap().interactive_count
+}
+
+#[turbo_tasks::function]
+async fn request() -> Result<CompletionVc> {
+    render().await?;
+    LOG.lock().unwrap().push("request done");
+    Ok(CompletionVc::new())
+}
+
+#[turbo_tasks::function]
+async fn render() -> CompletionVc {
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    LOG.lock().unwrap().push("render done");
+    CompletionVc::new()
+}
+
+#[turbo_tasks::function]
+fn prefetch() -> CompletionVc {
+    LOG.lock().unwrap().push("prefetch started");
+    CompletionVc::new()
+}
+
+#[turbo_tasks::function]
+fn spawn_subtask() -> CompletionVc {
+    // Not awaited, only strongly consistent reads wait for it
+    let _ = subtask();
+    CompletionVc::new()
+}
+
+#[turbo_tasks::function]
+async fn subtask() -> CompletionVc {
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    CompletionVc::new()
+}
@@ -0,0 +1,189 @@
+#![feature(min_specialization)]
+
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    get_invalidator,
+    record::{RecordedEvent, Recording},
+    InvalidationReason, Invalidator, TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static SOURCE: AtomicU32 = AtomicU32::new(0);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+async fn run_session(path: &Path, changed_source: u32) -> Recording {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.start_recording(path).unwrap();
+    SOURCE.store(1, Ordering::SeqCst);
+    let result = tt
+        .run_once(async move { Ok(*double(read_source()).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(result, 2);
+
+    SOURCE.store(changed_source, Ordering::SeqCst);
+    let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+    invalidator.invalidate_with_reason(SourceChange);
+    let result = tt
+        .run_once(async move { Ok(*double(read_source()).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(result, changed_source * 2);
+    tt.stop_and_wait().await;
+    tt.flush_recording().unwrap();
+    Recording::read(path).unwrap()
+}
+
+#[tokio::test]
+async fn record_and_diff() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let recording = run_session(&dir.path().join("a"), 2).await;
+    assert!(recording.events.contains(&RecordedEvent::Invalidated {
+        task: recording
+            .events
+            .iter()
+            .find_map(|event| match event {
+                RecordedEvent::TaskCreated { task, .. } if task.contains("read_source") => {
+                    Some(task.clone())
+                }
+                _ => None,
+            })
+            .unwrap(),
+        reason: Some("source changed".to_string()),
+    }));
+
+    // Same inputs lead to the same cell values
+    let same = run_session(&dir.path().join("b"), 2).await;
+    assert_eq!(recording.diff(&same), None);
+
+    // The first divergent cell is the second value of the source
+    let changed = run_session(&dir.path().join("c"), 3).await;
+    let divergence = recording.diff(&changed).unwrap();
+    assert!(divergence.task.contains("read_source"), "{}", divergence);
+    assert_eq!(divergence.update, 1);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn write_errors_are_reported() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.start_recording(Path::new("/dev/full")).unwrap();
+    SOURCE.store(1, Ordering::SeqCst);
+    tt.run_once(async move { Ok(*double(read_source()).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    tt.stop_and_wait().await;
+    assert!(tt.flush_recording().is_err());
+}
+
+#[tokio::test]
+async fn replay_follows_recorded_execution_order() {
+    lazy_static::initialize(&REGISTER);
+    let dir = tempfile::tempdir().unwrap();
+    let run = |path: std::path::PathBuf, replay: Option<Recording>| async move {
+        let tt = TurboTasks::new(MemoryBackend::default());
+        match replay {
+            Some(recording) => tt.start_replay(recording, &path).unwrap(),
+            None => tt.start_recording(&path).unwrap(),
+        }
+        let result = tt
+            .run_once(async move { Ok(*sum_leaves().strongly_consistent().await?) })
+            .await
+            .unwrap();
+        assert_eq!(result, 6);
+        tt.stop_and_wait().await;
+        tt.flush_recording().unwrap();
+        Recording::read(&path).unwrap()
+    };
+    let execution_order = |recording: &Recording| {
+        recording
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                RecordedEvent::ExecutionStarted { task } => Some(task.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut recording = run(dir.path().join("a"), None).await;
+    let recorded_order = execution_order(&recording);
+    let leaves = recorded_order
+        .iter()
+        .filter(|task| task.contains("leaf"))
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(leaves.len(), 3, "{:?}", recorded_order);
+
+    // Start the independent leaves in reverse order, which differs from the
+    // order they would be executed in without replay
+    let mut reversed = leaves.iter().rev();
+    for event in recording.events.iter_mut() {
+        if let RecordedEvent::ExecutionStarted { task } = event {
+            if task.contains("leaf") {
+                *task = reversed.next().unwrap().clone();
+            }
+        }
+    }
+    let expected_order = execution_order(&recording);
+    assert_ne!(expected_order, recorded_order);
+
+    let replayed = run(dir.path().join("b"), Some(recording.clone())).await;
+    assert_eq!(execution_order(&replayed), expected_order);
+    assert_eq!(recording.diff(&replayed), None);
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct SourceChange;
+
+impl InvalidationReason for SourceChange {}
+
+impl Display for SourceChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "source changed")
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+fn read_source() -> NumberVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    NumberVc::cell(SOURCE.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn double(value: NumberVc) -> Result<NumberVc> {
+    Ok(NumberVc::cell(*value.await? * 2))
+}
+
+#[turbo_tasks::function]
+fn leaf(value: u32) -> NumberVc {
+    NumberVc::cell(value)
+}
+
+#[turbo_tasks::function]
+async fn sum_leaves() -> Result<NumberVc> {
+    let leaves = [leaf(1), leaf(2), leaf(3)];
+    let mut sum = 0;
+    for leaf in leaves {
+        sum += *leaf.await?;
+    }
+    Ok(NumberVc::cell(sum))
+}
@@ -0,0 +1,27 @@
+use std::env;
+
+use turbo_tasks_rocksdb::{list_checkpoints, OutputChange, Snapshot};
+
+fn main() {
+    let mut args = env::args_os()
+        .skip(1)
+        .map(|s| s.to_string_lossy().to_string());
+    let path = args.next().unwrap_or_else(|| "cache".to_string());
+    let (Some(old), Some(new)) = (args.next(), args.next()) else {
+        println!("usage: diff-checkpoints <db path> <checkpoint> <checkpoint>");
+        println!("checkpoints: {:?}", list_checkpoints(&path).unwrap());
+        return;
+    };
+
+    let open = |name: &str| Snapshot::open_checkpoint(&path, name).unwrap();
+    let diff = open(&old).diff(&open(&new)).unwrap();
+    for task in diff.iter() {
+        let change = match task.change {
+            OutputChange::Added => "+",
+            OutputChange::Removed => "-",
+            OutputChange::Changed => "~",
+        };
+        println!("{} {} {}", change, task.task, task.description);
+    }
+    println!("{} tasks changed", diff.len());
+}
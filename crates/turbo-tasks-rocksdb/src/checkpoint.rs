@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use bincode::Options;
+
+use crate::db::Database;
+
+/// Returns the path next to `path` with `suffix` appended to its name.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut sibling = path.as_os_str().to_owned();
+    sibling.push(suffix);
+    PathBuf::from(sibling)
+}
+
+/// Checkpoints of the database at `path` are stored next to it, in
+/// `<path>.checkpoints/<name>`.
+pub(crate) fn checkpoints_dir(path: &Path) -> PathBuf {
+    sibling_path(path, ".checkpoints")
+}
+
+pub(crate) fn checkpoint_path(path: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        bail!("Invalid checkpoint name {:?}", name);
+    }
+    Ok(checkpoints_dir(path).join(name))
+}
+
+/// Lists the names of the checkpoints of the database at `path`, sorted by
+/// name.
+pub fn list_checkpoints<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    let dir = checkpoints_dir(path.as_ref());
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Replaces the database at `path` with the checkpoint `name`. The database
+/// must not be opened while restoring.
+///
+/// The checkpoint is copied next to the database first and moved into place
+/// afterwards, so the database stays intact when copying fails.
+pub fn restore_checkpoint<P: AsRef<Path>>(path: P, name: &str) -> Result<()> {
+    let path = path.as_ref();
+    let checkpoint = checkpoint_path(path, name)?;
+    if !checkpoint.is_dir() {
+        bail!("Checkpoint {} doesn't exist", name);
+    }
+    let restored = sibling_path(path, ".restoring");
+    if restored.exists() {
+        fs::remove_dir_all(&restored)?;
+    }
+    fs::create_dir_all(&restored)?;
+    for entry in fs::read_dir(&checkpoint)? {
+        let entry = entry?;
+        fs::copy(entry.path(), restored.join(entry.file_name()))
+            .with_context(|| format!("Failed to restore {}", entry.path().display()))?;
+    }
+    let replaced = sibling_path(path, ".replaced");
+    if replaced.exists() {
+        fs::remove_dir_all(&replaced)?;
+    }
+    if path.exists() {
+        fs::rename(path, &replaced)?;
+    }
+    fs::rename(&restored, path)?;
+    if replaced.exists() {
+        fs::remove_dir_all(&replaced)?;
+    }
+    Ok(())
+}
+
+/// How the output of a task differs between two snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputChange {
+    /// The task was only persisted in the newer snapshot.
+    Added,
+    /// The task was only persisted in the older snapshot.
+    Removed,
+    /// The output or the cells of the task have changed.
+    Changed,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskDiff {
+    pub task: usize,
+    pub description: String,
+    pub change: OutputChange,
+}
+
+/// A read-only view of the database or one of its checkpoints.
+pub struct Snapshot {
+    database: Database,
+}
+
+impl Snapshot {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            database: Database::open_read_only(path)?,
+        })
+    }
+
+    /// Opens the checkpoint `name` of the database at `path`.
+    pub fn open_checkpoint<P: AsRef<Path>>(path: P, name: &str) -> Result<Self> {
+        Self::open(checkpoint_path(path.as_ref(), name)?)
+    }
+
+    /// Lists the tasks with different outputs in `newer`, sorted by task.
+    /// Tasks are matched by their id, which is stable as both snapshots are
+    /// taken from the same database.
+    pub fn diff(&self, newer: &Snapshot) -> Result<Vec<TaskDiff>> {
+        let old_hashes = self
+            .database
+            .output_hash
+            .get_all()?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let new_hashes = newer
+            .database
+            .output_hash
+            .get_all()?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let mut changes = Vec::new();
+        for (task, hash) in new_hashes.iter() {
+            match old_hashes.get(task) {
+                None => changes.push((*task, OutputChange::Added)),
+                Some(old_hash) if old_hash != hash => changes.push((*task, OutputChange::Changed)),
+                Some(_) => {}
+            }
+        }
+        for task in old_hashes.keys() {
+            if !new_hashes.contains_key(task) {
+                changes.push((*task, OutputChange::Removed));
+            }
+        }
+        changes.sort_by_key(|(task, _)| *task);
+
+        let mut descriptions = self.task_descriptions()?;
+        descriptions.extend(newer.task_descriptions()?);
+        Ok(changes
+            .into_iter()
+            .map(|(task, change)| TaskDiff {
+                task,
+                description: descriptions
+                    .remove(&task)
+                    .unwrap_or_else(|| format!("task {}", task)),
+                change,
+            })
+            .collect())
+    }
+
+    /// Describes tasks by the function they call, decoded from the keys of
+    /// the task cache. That doesn't need the registry, so snapshots can be
+    /// inspected outside of the application.
+    fn task_descriptions(&self) -> Result<HashMap<usize, String>> {
+        let opt = bincode::DefaultOptions::new().allow_trailing_bytes();
+        let mut descriptions = HashMap::new();
+        for (key, task) in self.database.cache.get_all()? {
+            let Some((kind, mut bytes)) = key.split_first() else {
+                continue;
+            };
+            let description = match kind {
+                0 => opt.deserialize_from::<_, String>(&mut bytes)?,
+                1 => format!(
+                    "[resolve] {}",
+                    opt.deserialize_from::<_, String>(&mut bytes)?
+                ),
+                2 => {
+                    let trait_type = opt.deserialize_from::<_, String>(&mut bytes)?;
+                    let name = opt.deserialize_from::<_, String>(&mut bytes)?;
+                    format!("[resolve trait] {}::{}", trait_type, name)
+                }
+                _ => continue,
+            };
+            descriptions.insert(task, description);
+        }
+        Ok(descriptions)
+    }
+}
@@ -2,9 +2,8 @@ use std::ops::Add;
 
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{
-    backend::PersistentTaskType,
-    persisted_graph::{TaskCell, TaskData},
-    without_task_id_mapping, RawVc,
+    backend::PersistentTaskType, persisted_graph::TaskCell, without_task_id_mapping, CellId, RawVc,
+    TaskId,
 };
 
 use crate::table::{database, table};
@@ -194,17 +193,45 @@ impl Add for TaskStateChange {
     }
 }
 
+/// The content of a cell. Values are stored together with the global name and
+/// version of their value type, so values of changed types can be migrated or
+/// discarded on load.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum PersistedCell {
+    Empty,
+    Value {
+        value_type: String,
+        version: u32,
+        data: Vec<u8>,
+    },
+    NeedComputation,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersistedTaskData {
+    pub children: Vec<TaskId>,
+    pub dependencies: Vec<RawVc>,
+    pub cells: Vec<(CellId, PersistedCell)>,
+    pub output: RawVc,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PartialTaskData {
     pub cells: Vec<TaskCell>,
     pub output: RawVc,
 }
 
+/// Version of the format of the stored data. Databases written with another
+/// version can't be read and are discarded on open.
+pub const FORMAT_VERSION: u32 = 1;
+
+table!(format_version, (u32));
 table!(last_task_id, (usize), merge((usize): |a: usize, b| a + b, |a, b| a + b));
 table!(task_type, (usize) => (PersistentTaskType));
 table!(cache, raw => (usize));
 table!(state, (usize) => (TaskState), merge((TaskStateChange): |s, c| s + c, |c1, c2| c1 + c2, without_task_id_mapping));
-table!(data, (usize) => (TaskData));
+table!(data, (usize) => (PersistedTaskData));
+table!(output_hash, (usize) => (u64));
 table!(children, (usize) => (Vec<usize>));
 table!(dependencies, (usize) => (Vec<RawVc>));
 table!(dependents, (RawVc) => [usize], prefix(u8));
@@ -214,11 +241,13 @@ table!(potential_dirty_active_tasks, (()) => [usize]);
 table!(pending_active_update, (()) => [usize]);
 
 database!(
+    format_version,
     last_task_id,
     task_type,
     cache,
     state,
     data,
+    output_hash,
     children,
     dependencies,
     dependents,
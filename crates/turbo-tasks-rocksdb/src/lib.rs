@@ -1,10 +1,12 @@
 #![feature(hash_drain_filter)]
 #![deny(unsafe_op_in_unsafe_fn)]
 
+mod checkpoint;
 mod db;
 mod persisted_graph;
 mod table;
 
+pub use checkpoint::{list_checkpoints, restore_checkpoint, OutputChange, Snapshot, TaskDiff};
 pub use persisted_graph::RocksDbPersistedGraph;
 
 #[doc(hidden)]
@@ -1,7 +1,8 @@
 use std::{
     collections::HashSet,
     fmt::Debug,
-    path::Path,
+    fs,
+    path::{Path, PathBuf},
     sync::atomic::{AtomicU8, AtomicUsize, Ordering},
 };
 
@@ -9,17 +10,22 @@ use anyhow::{anyhow, Error, Result};
 use bincode::Options;
 use flurry::HashMap;
 use turbo_tasks::{
-    backend::PersistentTaskType,
+    backend::{CellContent, PersistentTaskType},
     persisted_graph::{
         ActivateResult, DeactivateResult, PersistResult, PersistTaskState, PersistedGraph,
         PersistedGraphApi, ReadTaskState, TaskCell, TaskData,
     },
+    registry,
     util::{InfiniteVec, SharedError},
-    with_task_id_mapping, FunctionId, IdMapping, TaskId,
+    with_task_id_mapping, FunctionId, IdMapping, SharedReference, TaskId,
 };
+use turbo_tasks_hash::{DeterministicHasher, Xxh3Hash64Hasher};
 
 use super::db::{Database, TaskState, TaskStateChange};
-use crate::db::InternalTaskState;
+use crate::{
+    checkpoint::{self, Snapshot},
+    db::{InternalTaskState, PersistedCell, PersistedTaskData},
+};
 
 fn task_type_to_bytes(ty: &PersistentTaskType) -> Result<Vec<u8>, bincode::Error> {
     let mut result = Vec::new();
@@ -48,6 +54,73 @@ fn task_type_to_bytes(ty: &PersistentTaskType) -> Result<Vec<u8>, bincode::Error
     Ok(result)
 }
 
+/// Opens the database at `path`. A database in another format is wiped, as
+/// its tasks can't be restored. Errors while reading the format are returned,
+/// so a database is never wiped because of an I/O error.
+fn open_database(path: &Path) -> Result<Database> {
+    let mut db = Database::open(path)?;
+    let unsupported = match db.format_version.get()? {
+        Some(FORMAT_VERSION) => return Ok(db),
+        Some(_) => true,
+        // Databases written before the format was versioned have tasks but no
+        // version, new databases are empty
+        None => db.last_task_id.get()?.is_some(),
+    };
+    if unsupported {
+        #[cfg(feature = "log_db")]
+        println!("DB     wiped database with unsupported format");
+        drop(db);
+        fs::remove_dir_all(path)?;
+        db = Database::open(path)?;
+    }
+    let b = &mut db.batch();
+    db.format_version.write(b, &FORMAT_VERSION)?;
+    b.write()?;
+    Ok(db)
+}
+
+/// Serializes the value of a cell. Cells with values that are not serializable
+/// need to be recomputed.
+fn cell_to_persisted(cell: &TaskCell) -> PersistedCell {
+    match cell {
+        TaskCell::Content(CellContent(None)) => PersistedCell::Empty,
+        TaskCell::Content(CellContent(Some(SharedReference(Some(ty), value)))) => {
+            let value_type = registry::get_value_type(*ty);
+            let Some(serializable) = value_type.any_as_serializable(value) else {
+                return PersistedCell::NeedComputation;
+            };
+            match bincode::DefaultOptions::new().serialize(serializable) {
+                Ok(data) => PersistedCell::Value {
+                    value_type: registry::get_value_type_global_name(*ty).to_string(),
+                    version: value_type.version,
+                    data,
+                },
+                Err(_) => PersistedCell::NeedComputation,
+            }
+        }
+        TaskCell::Content(_) | TaskCell::NeedComputation => PersistedCell::NeedComputation,
+    }
+}
+
+/// Hashes the output and the cells of a task, to find changed tasks between
+/// checkpoints without deserializing values.
+fn output_hash(data: &PersistedTaskData) -> Result<u64, bincode::Error> {
+    let opt = bincode::DefaultOptions::new();
+    let mut cells = data
+        .cells
+        .iter()
+        .map(|cell| opt.serialize(cell))
+        .collect::<Result<Vec<_>, _>>()?;
+    // The order of cells is not deterministic
+    cells.sort();
+    let mut hasher = Xxh3Hash64Hasher::new();
+    hasher.write_bytes(&opt.serialize(&data.output)?);
+    for cell in cells.iter() {
+        hasher.write_bytes(cell);
+    }
+    Ok(hasher.finish())
+}
+
 #[derive(Default)]
 pub struct CountsByFunction(pub NoMoveVec<AtomicUsize>);
 
@@ -77,6 +150,8 @@ pub struct Stats {
     dependent_dirty: AtomicUsize,
     dirties: AtomicUsize,
     flaggings: AtomicUsize,
+    migrated_cells: AtomicUsize,
+    discarded_cells: AtomicUsize,
 }
 
 const AC_UNKNOWN: u8 = 0;
@@ -84,6 +159,7 @@ const AC_ACTIVE: u8 = 1;
 const AC_INACTIVE: u8 = 2;
 
 pub struct RocksDbPersistedGraph {
+    path: PathBuf,
     database: Database,
     task_id_forward_mapping: HashMap<TaskId, usize>,
     task_id_backward_mapping: HashMap<usize, TaskId>,
@@ -99,9 +175,11 @@ pub struct RocksDbPersistedGraph {
 
 impl RocksDbPersistedGraph {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let db = Database::open(path)?;
+        let path = path.as_ref().to_path_buf();
+        let db = open_database(&path)?;
         let last_id = db.last_task_id.get()?.unwrap_or_default();
         Ok(Self {
+            path,
             database: db,
             task_id_forward_mapping: HashMap::new(),
             task_id_backward_mapping: HashMap::new(),
@@ -115,6 +193,28 @@ impl RocksDbPersistedGraph {
         })
     }
 
+    /// Stores a named copy of the current state of the database, which can
+    /// be opened with [RocksDbPersistedGraph::open_checkpoint] or restored
+    /// with [crate::restore_checkpoint]. An existing checkpoint with the same
+    /// name is replaced.
+    pub fn create_checkpoint(&self, name: &str) -> Result<PathBuf> {
+        let path = checkpoint::checkpoint_path(&self.path, name)?;
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(checkpoint::checkpoints_dir(&self.path))?;
+        self.database.checkpoint(&path)?;
+        Ok(path)
+    }
+
+    pub fn checkpoints(&self) -> Result<Vec<String>> {
+        checkpoint::list_checkpoints(&self.path)
+    }
+
+    pub fn open_checkpoint(&self, name: &str) -> Result<Snapshot> {
+        Snapshot::open_checkpoint(&self.path, name)
+    }
+
     fn with_task_id_mapping<T>(&self, api: &dyn PersistedGraphApi, func: impl FnOnce() -> T) -> T {
         with_task_id_mapping(&PgApiMapping::new(self, api), func)
     }
@@ -170,6 +270,63 @@ impl RocksDbPersistedGraph {
             .ok_or_else(|| anyhow!("Invalid task id {}", id))
     }
 
+    /// Deserializes the cells of a task. Values of other versions of their
+    /// value type are migrated when the type supports it, otherwise they are
+    /// discarded and need to be recomputed.
+    fn restore_task_data(&self, data: PersistedTaskData) -> TaskData {
+        let PersistedTaskData {
+            children,
+            dependencies,
+            cells,
+            output,
+        } = data;
+        let cells = cells
+            .into_iter()
+            .map(|(id, cell)| (id, self.restore_cell(cell)))
+            .collect();
+        TaskData {
+            children,
+            dependencies,
+            cells,
+            output,
+        }
+    }
+
+    fn restore_cell(&self, cell: PersistedCell) -> TaskCell {
+        let (value_type, version, data) = match cell {
+            PersistedCell::Empty => return TaskCell::Content(CellContent(None)),
+            PersistedCell::NeedComputation => return TaskCell::NeedComputation,
+            PersistedCell::Value {
+                value_type,
+                version,
+                data,
+            } => (value_type, version, data),
+        };
+        let opt = bincode::DefaultOptions::new();
+        let restored = registry::get_value_type_id_by_global_name(&value_type).and_then(|ty| {
+            let value_type = registry::get_value_type(ty);
+            let value = if version == value_type.version {
+                let seed = value_type.get_any_deserialize_seed()?;
+                opt.deserialize_seed(seed, &data).ok()?
+            } else {
+                let seed = value_type.get_migration_seed(version)?;
+                let value = opt.deserialize_seed(seed, &data).ok()??;
+                self.stats.migrated_cells.fetch_add(1, Ordering::Relaxed);
+                value
+            };
+            Some(SharedReference(Some(ty), value.into()))
+        });
+        match restored {
+            Some(value) => TaskCell::Content(CellContent(Some(value))),
+            None => {
+                #[cfg(feature = "log_db")]
+                println!("DB     discarded cell of {value_type} version {version}");
+                self.stats.discarded_cells.fetch_add(1, Ordering::Relaxed);
+                TaskCell::NeedComputation
+            }
+        }
+    }
+
     fn get_active(&self, db_task: usize) -> Result<bool> {
         let ac = self.active_cache.get(db_task);
         let ac_value = ac.load(Ordering::Acquire);
@@ -236,7 +393,7 @@ impl PersistedGraph for RocksDbPersistedGraph {
                         _ => {}
                     }
                     return Ok(Some((
-                        data,
+                        self.restore_task_data(data),
                         ReadTaskState {
                             clean,
                             keeps_external_active: active_parents > 0,
@@ -307,7 +464,7 @@ impl PersistedGraph for RocksDbPersistedGraph {
     fn persist(
         &self,
         task: TaskId,
-        mut data: TaskData,
+        data: TaskData,
         state: PersistTaskState,
         api: &dyn PersistedGraphApi,
     ) -> Result<Option<PersistResult>> {
@@ -384,16 +541,23 @@ impl PersistedGraph for RocksDbPersistedGraph {
                 &TaskStateChange::Persist(state.externally_active),
             )?;
             ac.store(AC_ACTIVE, Ordering::Release);
-            for cell in data.cells.iter_mut() {
-                if let TaskCell::Content(ref c) = cell {
-                    // TODO we can avoid double serialization
-                    // by having a custom Serialize impl on TaskCell
-                    if bincode::DefaultOptions::new().serialize(c).is_err() {
-                        *cell = TaskCell::NeedComputation;
-                    }
-                }
+            let persisted_data = PersistedTaskData {
+                children: data.children.clone(),
+                dependencies: data.dependencies.clone(),
+                cells: data
+                    .cells
+                    .iter()
+                    .map(|(id, cell)| (*id, cell_to_persisted(cell)))
+                    .collect(),
+                output: data.output,
+            };
+            if db.data.write(b, &db_task, &persisted_data).is_err() {
+                b.cancel();
+                return Ok(None);
             }
-            if db.data.write(b, &db_task, &data).is_err() {
+            let hash_written = output_hash(&persisted_data)
+                .and_then(|hash| db.output_hash.write(b, &db_task, &hash));
+            if hash_written.is_err() {
                 b.cancel();
                 return Ok(None);
             }
@@ -897,3 +1061,290 @@ impl<'a> IdMapping<TaskId> for PgApiReadOnlyMapping<'a> {
         new_id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use lazy_static::lazy_static;
+    use serde::{Deserialize, Serialize};
+    use turbo_tasks::{registry, MigrateValue, PersistedValue, ValueType, ValueTypeId};
+
+    use super::*;
+    use crate::{checkpoint::OutputChange, restore_checkpoint};
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        minify: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ConfigV1 {
+        name: String,
+    }
+
+    impl MigrateValue for Config {
+        fn migrate(version: u32, value: PersistedValue<'_, '_>) -> Option<Self> {
+            match version {
+                1 => {
+                    let ConfigV1 { name } = value.deserialize().ok()?;
+                    Some(Config {
+                        name,
+                        minify: false,
+                    })
+                }
+                _ => None,
+            }
+        }
+    }
+
+    const CONFIG_NAME: &str = "turbo-tasks-rocksdb::tests::Config";
+
+    lazy_static! {
+        static ref CONFIG: ValueTypeId = {
+            let value_type: &'static ValueType = Box::leak(Box::new(
+                ValueType::new_with_any_serialization::<Config>()
+                    .with_version(2)
+                    .with_migration::<Config>(),
+            ));
+            value_type.register(CONFIG_NAME);
+            registry::get_value_type_id(value_type)
+        };
+    }
+
+    fn config_cell(name: &str) -> TaskCell {
+        TaskCell::Content(CellContent(Some(SharedReference(
+            Some(*CONFIG),
+            Arc::new(Config {
+                name: name.to_string(),
+                minify: true,
+            }),
+        ))))
+    }
+
+    fn assert_config(cell: TaskCell, expected: &Config) {
+        let TaskCell::Content(CellContent(Some(SharedReference(ty, value)))) = cell else {
+            panic!("expected a value, got {:?}", cell);
+        };
+        assert_eq!(ty, Some(*CONFIG));
+        assert_eq!(value.downcast_ref::<Config>(), Some(expected));
+    }
+
+    fn open_graph() -> (tempfile::TempDir, RocksDbPersistedGraph) {
+        let dir = tempfile::tempdir().unwrap();
+        let graph = RocksDbPersistedGraph::new(dir.path().join("db")).unwrap();
+        (dir, graph)
+    }
+
+    #[test]
+    fn cells_round_trip() {
+        let (_dir, graph) = open_graph();
+
+        let persisted = cell_to_persisted(&config_cell("app"));
+        let PersistedCell::Value {
+            ref value_type,
+            version,
+            ..
+        } = persisted else {
+            panic!("expected a value, got {:?}", persisted);
+        };
+        assert_eq!(value_type, CONFIG_NAME);
+        assert_eq!(version, 2);
+        assert_config(
+            graph.restore_cell(persisted),
+            &Config {
+                name: "app".to_string(),
+                minify: true,
+            },
+        );
+
+        let empty = cell_to_persisted(&TaskCell::Content(CellContent(None)));
+        assert!(matches!(empty, PersistedCell::Empty));
+        assert!(matches!(
+            graph.restore_cell(empty),
+            TaskCell::Content(CellContent(None))
+        ));
+
+        let not_computed = cell_to_persisted(&TaskCell::NeedComputation);
+        assert!(matches!(not_computed, PersistedCell::NeedComputation));
+        assert!(matches!(
+            graph.restore_cell(not_computed),
+            TaskCell::NeedComputation
+        ));
+    }
+
+    #[test]
+    fn older_versions_are_migrated() {
+        let (_dir, graph) = open_graph();
+        lazy_static::initialize(&CONFIG);
+
+        let data = bincode::DefaultOptions::new()
+            .serialize(&ConfigV1 {
+                name: "app".to_string(),
+            })
+            .unwrap();
+        let cell = graph.restore_cell(PersistedCell::Value {
+            value_type: CONFIG_NAME.to_string(),
+            version: 1,
+            data,
+        });
+        assert_config(
+            cell,
+            &Config {
+                name: "app".to_string(),
+                minify: false,
+            },
+        );
+        assert_eq!(graph.stats.migrated_cells.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn unknown_values_are_discarded() {
+        let (_dir, graph) = open_graph();
+        lazy_static::initialize(&CONFIG);
+
+        let unknown_version = graph.restore_cell(PersistedCell::Value {
+            value_type: CONFIG_NAME.to_string(),
+            version: 0,
+            data: Vec::new(),
+        });
+        assert!(matches!(unknown_version, TaskCell::NeedComputation));
+        let unknown_type = graph.restore_cell(PersistedCell::Value {
+            value_type: "turbo-tasks-rocksdb::tests::Unknown".to_string(),
+            version: 2,
+            data: Vec::new(),
+        });
+        assert!(matches!(unknown_type, TaskCell::NeedComputation));
+        assert_eq!(graph.stats.discarded_cells.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn databases_of_other_formats_are_wiped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        {
+            let db = Database::open(&path).unwrap();
+            let b = &mut db.batch();
+            db.format_version.write(b, &(FORMAT_VERSION + 1)).unwrap();
+            db.last_task_id.write(b, &42).unwrap();
+            b.write().unwrap();
+        }
+
+        let graph = RocksDbPersistedGraph::new(&path).unwrap();
+        assert_eq!(graph.last_task_id.load(Ordering::Relaxed), 0);
+        assert_eq!(
+            graph.database.format_version.get().unwrap(),
+            Some(FORMAT_VERSION)
+        );
+        drop(graph);
+
+        // Databases in the current format are kept
+        {
+            let db = Database::open(&path).unwrap();
+            let b = &mut db.batch();
+            db.last_task_id.write(b, &42).unwrap();
+            b.write().unwrap();
+        }
+        let graph = RocksDbPersistedGraph::new(&path).unwrap();
+        assert_eq!(graph.last_task_id.load(Ordering::Relaxed), 42);
+    }
+
+    #[test]
+    fn databases_are_kept_when_the_format_is_unreadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        {
+            let db = Database::open(&path).unwrap();
+            let b = &mut db.batch();
+            db.last_task_id.write(b, &42).unwrap();
+            b.write().unwrap();
+            // An empty value can't be deserialized as a version
+            let cf = db.db.cf_handle("format_version").unwrap();
+            db.db.put_cf(cf, b"", b"").unwrap();
+        }
+
+        assert!(RocksDbPersistedGraph::new(&path).is_err());
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.last_task_id.get().unwrap(), Some(42));
+    }
+
+    fn write_task(db: &Database, task: usize, name: &str, hash: u64) {
+        let mut key = vec![0];
+        bincode::DefaultOptions::new()
+            .serialize_into(&mut key, name)
+            .unwrap();
+        let b = &mut db.batch();
+        db.cache.write(b, &key, &task).unwrap();
+        db.output_hash.write(b, &task, &hash).unwrap();
+        b.write().unwrap();
+    }
+
+    #[test]
+    fn checkpoints_are_listed_and_diffed() {
+        let (_dir, graph) = open_graph();
+        assert!(graph.checkpoints().unwrap().is_empty());
+
+        write_task(&graph.database, 1, "app::unchanged", 1);
+        write_task(&graph.database, 2, "app::changed", 2);
+        write_task(&graph.database, 3, "app::removed", 3);
+        graph.create_checkpoint("before").unwrap();
+
+        write_task(&graph.database, 2, "app::changed", 20);
+        write_task(&graph.database, 4, "app::added", 4);
+        let b = &mut graph.database.batch();
+        graph.database.output_hash.delete(b, &3).unwrap();
+        b.write().unwrap();
+        graph.create_checkpoint("after").unwrap();
+
+        assert_eq!(graph.checkpoints().unwrap(), vec!["after", "before"]);
+        assert!(graph.create_checkpoint("../escaped").is_err());
+        assert!(graph.open_checkpoint("missing").is_err());
+
+        let before = graph.open_checkpoint("before").unwrap();
+        let after = graph.open_checkpoint("after").unwrap();
+        let diff = before
+            .diff(&after)
+            .unwrap()
+            .into_iter()
+            .map(|diff| (diff.task, diff.description, diff.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diff,
+            vec![
+                (2, "app::changed".to_string(), OutputChange::Changed),
+                (3, "app::removed".to_string(), OutputChange::Removed),
+                (4, "app::added".to_string(), OutputChange::Added),
+            ]
+        );
+        assert!(after.diff(&after).unwrap().is_empty());
+
+        // Checkpoints with the same name are replaced
+        drop((before, after));
+        graph.create_checkpoint("before").unwrap();
+        let before = graph.open_checkpoint("before").unwrap();
+        let after = graph.open_checkpoint("after").unwrap();
+        assert!(before.diff(&after).unwrap().is_empty());
+    }
+
+    #[test]
+    fn checkpoints_are_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        {
+            let graph = RocksDbPersistedGraph::new(&path).unwrap();
+            write_task(&graph.database, 1, "app::task", 1);
+            graph.create_checkpoint("before").unwrap();
+            write_task(&graph.database, 1, "app::task", 2);
+        }
+
+        assert!(restore_checkpoint(&path, "missing").is_err());
+        restore_checkpoint(&path, "before").unwrap();
+        assert!(!dir.path().join("db.restoring").exists());
+        assert!(!dir.path().join("db.replaced").exists());
+
+        let graph = RocksDbPersistedGraph::new(&path).unwrap();
+        assert_eq!(graph.database.output_hash.get(&1).unwrap(), Some(1));
+        assert_eq!(graph.checkpoints().unwrap(), vec!["before"]);
+    }
+}
@@ -1190,6 +1190,21 @@ macro_rules! table {
                     Ok((result, complete))
                 }
 
+                #[allow(unused_parens, dead_code)]
+                pub fn get_all(&self) -> Result<Vec<(Vec<u8>, ($($value),+))>> {
+                    let mut result = Vec::new();
+                    let cf = self.db.cf_handle(stringify!($name)).unwrap();
+                    let mut iter = self.db.raw_iterator_cf_opt(cf, $crate::table::get_default_read_options());
+                    iter.seek_to_first();
+                    while let(Some(key), Some(value)) = (iter.key(), iter.value()) {
+                        let value = DefaultOptions::new().deserialize(value)?;
+                        result.push((key.to_vec(), value));
+                        iter.next();
+                    }
+                    iter.status()?;
+                    Ok(result)
+                }
+
 
                 #[allow(dead_code)]
                 pub fn write(
@@ -1269,6 +1284,27 @@ macro_rules! database {
                     })
                 }
 
+                pub fn open_read_only<P: AsRef<std::path::Path>>(path: P) -> Result<Database> {
+                    let mut cfs = Vec::new();
+                    $(
+                        super::$table::Api::add_cf_descs(&mut cfs);
+                    )*
+                    let db = Arc::new(DB::open_cf_descriptors_read_only(&$crate::table::DEFAULT_OPTIONS, path, cfs, false)?);
+                    Ok(Database {
+                        $(
+                            $table: super::$table::Api::new(db.clone()),
+                        )*
+                        db,
+                    })
+                }
+
+                /// Writes a consistent copy of the database to `path`. Files
+                /// are hard linked when possible.
+                pub fn checkpoint<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
+                    rocksdb::checkpoint::Checkpoint::new(&self.db)?.create_checkpoint(path)?;
+                    Ok(())
+                }
+
                 pub fn batch(&self) -> $crate::table::WriteBatch {
                     $crate::table::WriteBatch::new(self.db.clone())
                 }
@@ -223,6 +223,10 @@ impl TurboTasksApi for VcStorage {
     fn mark_own_task_as_finished(&self, _task: TaskId) {
         // no-op
     }
+
+    fn mark_own_task_as_session_dependent(&self, _task: TaskId) {
+        // no-op
+    }
 }
 
 impl VcStorage {
@@ -0,0 +1,20 @@
+use std::{env, path::PathBuf, process::exit};
+
+use turbo_tasks::record::Recording;
+
+fn main() {
+    let mut args = env::args_os().skip(1).map(PathBuf::from);
+    let (Some(expected), Some(actual)) = (args.next(), args.next()) else {
+        eprintln!("usage: diff-recordings <expected> <actual>");
+        exit(2);
+    };
+    let expected = Recording::read(&expected).unwrap();
+    let actual = Recording::read(&actual).unwrap();
+    match expected.diff(&actual) {
+        Some(divergence) => {
+            println!("{}", divergence);
+            exit(1);
+        }
+        None => println!("no divergent cells"),
+    }
+}
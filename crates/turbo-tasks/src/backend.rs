@@ -15,8 +15,8 @@ use serde::{Deserialize, Serialize};
 pub use crate::id::BackendJobId;
 use crate::{
     event::EventListener, manager::TurboTasksBackendApi, primitives::RawVcSetVc, raw_vc::CellId,
-    registry, task_input::SharedReference, FunctionId, RawVc, ReadRef, TaskId, TaskIdProvider,
-    TaskInput, TraitRef, TraitTypeId, ValueTraitVc,
+    registry, task_input::SharedReference, util::StaticOrArc, FunctionId, InvalidationReason,
+    RawVc, ReadRef, TaskId, TaskIdProvider, TaskInput, TraitRef, TraitTypeId, ValueTraitVc,
 };
 
 pub enum TaskType {
@@ -127,6 +127,36 @@ impl PersistentTaskType {
 
 pub struct TaskExecutionSpec {
     pub future: Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>,
+    /// Resolves when the execution became stale, e. g. because a dependency
+    /// has been invalidated. The future is dropped in that case, unless the
+    /// task has been marked as [stateful](crate::mark_stateful), and the
+    /// execution is reported as [TaskExecutionResult::Cancelled].
+    pub cancel: Option<EventListener>,
+}
+
+/// The outcome of a task execution.
+pub enum TaskExecutionResult {
+    /// The execution finished with a value or an error.
+    Finished(Result<RawVc>),
+    /// The execution panicked, with the panic message when available.
+    Panicked(Option<Cow<'static, str>>),
+    /// The execution has been cancelled as it became stale. It will be
+    /// executed again.
+    Cancelled,
+}
+
+/// The scheduling priority of a task.
+///
+/// Executions of [TaskPriority::Normal] tasks are held back while
+/// [TaskPriority::Interactive] tasks are executing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    /// Speculative or background work, e. g. prefetching.
+    #[default]
+    Normal,
+    /// Work someone is actively waiting for, e. g. the currently requested
+    /// page. Applies to the root task and all its transitive subtasks.
+    Interactive,
 }
 
 // TODO technically CellContent is already indexed by the ValueTypeId, so we
@@ -202,6 +232,17 @@ pub trait Backend: Sync + Send {
 
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
+    /// Invalidates a task because of an external change, e. g. by an
+    /// [Invalidator](crate::Invalidator).
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        _reason: &StaticOrArc<dyn InvalidationReason>,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.invalidate_task(task, turbo_tasks);
+    }
+
     fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
     fn get_task_description(&self, task: TaskId) -> String;
@@ -225,7 +266,7 @@ pub trait Backend: Sync + Send {
     fn task_execution_result(
         &self,
         task: TaskId,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: TaskExecutionResult,
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     );
 
@@ -346,6 +387,33 @@ pub trait Backend: Sync + Send {
         // Do nothing by default
     }
 
+    /// Called when a task depends on state outside of turbo-tasks (e. g. the
+    /// file system), so its results can't be reused across sessions.
+    fn mark_own_task_as_session_dependent(
+        &self,
+        _task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        // Do nothing by default
+    }
+
+    /// Sets the priority of a root task. Subtasks of the root task inherit
+    /// the priority.
+    fn set_root_task_priority(
+        &self,
+        _task: TaskId,
+        _priority: TaskPriority,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        // Do nothing by default
+    }
+
+    /// Returns the priority of a task, inherited from the root tasks it is
+    /// part of.
+    fn get_task_priority(&self, _task: TaskId) -> TaskPriority {
+        TaskPriority::Normal
+    }
+
     fn create_transient_task(
         &self,
         task_type: TransientTaskType,
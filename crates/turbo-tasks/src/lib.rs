@@ -33,6 +33,7 @@
 #![feature(provide_any)]
 #![feature(new_uninit)]
 #![feature(never_type)]
+#![feature(rustc_attrs)]
 
 pub mod backend;
 mod collectibles;
@@ -55,6 +56,7 @@ pub mod persisted_graph;
 pub mod primitives;
 mod raw_vc;
 mod read_ref;
+pub mod record;
 pub mod registry;
 pub mod small_duration;
 mod state;
@@ -79,10 +81,10 @@ pub use invalidation::{
 };
 pub use join_iter_ext::{JoinIterExt, TryJoinIterExt};
 pub use manager::{
-    dynamic_call, emit, get_invalidator, mark_finished, mark_stateful, run_once,
-    run_once_with_reason, spawn_blocking, spawn_thread, trait_call, turbo_tasks, Invalidator,
-    StatsType, TaskIdProvider, TurboTasks, TurboTasksApi, TurboTasksBackendApi, TurboTasksCallApi,
-    Unused, UpdateInfo,
+    dynamic_call, emit, get_invalidator, mark_finished, mark_session_dependent, mark_stateful,
+    run_once, run_once_with_reason, spawn_blocking, spawn_thread, trait_call, turbo_tasks,
+    Invalidator, StatsType, TaskIdProvider, TurboTasks, TurboTasksApi, TurboTasksBackendApi,
+    TurboTasksCallApi, Unused, UpdateInfo, MAX_INTERACTIVE_DELAY,
 };
 pub use native_function::{NativeFunction, NativeFunctionVc};
 pub use nothing::{Nothing, NothingVc};
@@ -97,8 +99,8 @@ pub use trait_ref::{IntoTraitRef, TraitRef};
 pub use turbo_tasks_macros::{function, value, value_impl, value_trait};
 pub use value::{TransientInstance, TransientValue, Value};
 pub use value_type::{
-    FromSubTrait, IntoSuperTrait, TraitMethod, TraitType, Typed, TypedForInput, ValueTraitVc,
-    ValueType, ValueVc,
+    FromSubTrait, IntoSuperTrait, MigrateValue, PersistedValue, TraitMethod, TraitType, Typed,
+    TypedForInput, ValueTraitVc, ValueType, ValueVc,
 };
 
 #[doc(hidden)]
@@ -216,3 +216,35 @@ impl<'de> DeserializeSeed<'de> for AnyDeserializeSeed {
         (self.functor)(&mut deserializer).map_err(serde::de::Error::custom)
     }
 }
+
+pub(crate) type AnyMigrationSeedFunctor =
+    fn(
+        u32,
+        &mut dyn erased_serde::Deserializer<'_>,
+    ) -> Result<Option<Box<dyn Any + Sync + Send>>, erased_serde::Error>;
+
+/// Deserializes a value that has been serialized by an older version of its
+/// value type. See [crate::MigrateValue].
+#[derive(Clone, Copy)]
+pub struct AnyMigrationSeed {
+    functor: AnyMigrationSeedFunctor,
+    version: u32,
+}
+
+impl AnyMigrationSeed {
+    pub(crate) fn new(functor: AnyMigrationSeedFunctor, version: u32) -> Self {
+        Self { functor, version }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for AnyMigrationSeed {
+    type Value = Option<Box<dyn Any + Sync + Send>>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut deserializer = <dyn erased_serde::Deserializer>::erase(deserializer);
+        (self.functor)(self.version, &mut deserializer).map_err(serde::de::Error::custom)
+    }
+}
@@ -6,6 +6,7 @@ use std::{
     hash::Hash,
     mem::take,
     panic::AssertUnwindSafe,
+    path::Path,
     pin::Pin,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
@@ -17,19 +18,25 @@ use std::{
 
 use anyhow::{anyhow, Result};
 use auto_hash_map::AutoSet;
+use dashmap::DashSet;
 use futures::FutureExt;
 use nohash_hasher::BuildNoHashHasher;
+use once_cell::sync::OnceCell;
 use serde::{de::Visitor, Deserialize, Serialize};
 use tokio::{runtime::Handle, select, task_local};
 
 use crate::{
-    backend::{Backend, CellContent, PersistentTaskType, TransientTaskType},
+    backend::{
+        Backend, CellContent, PersistentTaskType, TaskExecutionResult, TaskExecutionSpec,
+        TaskPriority, TransientTaskType,
+    },
     event::{Event, EventListener},
     id::{BackendJobId, FunctionId, TraitTypeId},
     id_factory::IdFactory,
     invalidation::InvalidationReasonSet,
     primitives::RawVcSetVc,
     raw_vc::{CellId, RawVc},
+    record::{Recorder, Recording},
     registry,
     task_input::{SharedReference, TaskInput},
     timed_future::{self, TimedFuture},
@@ -116,8 +123,25 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
     fn read_own_task_cell(&self, task: TaskId, index: CellId) -> Result<CellContent>;
     fn update_own_task_cell(&self, task: TaskId, index: CellId, content: CellContent);
     fn mark_own_task_as_finished(&self, task: TaskId);
+    fn mark_own_task_as_session_dependent(&self, task: TaskId);
 
     fn connect_task(&self, task: TaskId);
+
+    /// Returns `true` when task executions are recorded, so file reads should
+    /// be reported with [TurboTasksApi::record_file_read].
+    fn is_recording(&self) -> bool {
+        false
+    }
+
+    /// Adds the content of a file read to the recording. `None` when the file
+    /// doesn't exist.
+    fn record_file_read(&self, _path: &str, _content: Option<Vec<u8>>) {}
+
+    /// When replaying a recording, returns the recorded content of a file
+    /// read instead of the current content on disk.
+    fn replayed_file_read(&self, _path: &str) -> Option<Option<Vec<u8>>> {
+        None
+    }
 }
 
 /// The type of stats reporting.
@@ -208,6 +232,11 @@ pub trait TurboTasksBackendApi<B: Backend + 'static>:
     fn program_duration_until(&self, instant: Instant) -> Duration;
     /// Returns a reference to the backend.
     fn backend(&self) -> &B;
+    /// Returns the priority of the task execution in the current context.
+    fn current_task_priority(&self) -> TaskPriority;
+    /// Lets scheduled normal tasks check their priority again. Needs to be
+    /// called after the backend raised the priority of tasks.
+    fn task_priorities_changed(&self);
 }
 
 impl StatsType {
@@ -250,6 +279,10 @@ pub struct UpdateInfo {
     placeholder_for_future_fields: (),
 }
 
+/// How long executions of normal tasks are held back at most while
+/// [TaskPriority::Interactive] tasks are executing.
+pub const MAX_INTERACTIVE_DELAY: Duration = Duration::from_secs(1);
+
 pub struct TurboTasks<B: Backend + 'static> {
     this: Weak<Self>,
     backend: B,
@@ -258,6 +291,18 @@ pub struct TurboTasks<B: Backend + 'static> {
     currently_scheduled_tasks: AtomicUsize,
     currently_scheduled_foreground_jobs: AtomicUsize,
     currently_scheduled_background_jobs: AtomicUsize,
+    /// Number of executing [TaskPriority::Interactive] tasks. Executions of
+    /// normal tasks are held back while this is non-zero, for at most
+    /// [MAX_INTERACTIVE_DELAY].
+    currently_scheduled_interactive_tasks: AtomicUsize,
+    /// Set once a root task priority has been changed. Priorities are ignored
+    /// until then.
+    priority_scheduling: AtomicBool,
+    /// Tasks an interactive task is waiting for. They are executed with
+    /// interactive priority.
+    boosted_tasks: DashSet<TaskId, BuildNoHashHasher<TaskId>>,
+    /// Set when task executions are recorded or replayed
+    recorder: OnceCell<Recorder>,
     scheduled_tasks: AtomicUsize,
     start: Mutex<Option<Instant>>,
     aggregated_update: Mutex<(Option<(Duration, usize)>, InvalidationReasonSet)>,
@@ -265,6 +310,7 @@ pub struct TurboTasks<B: Backend + 'static> {
     event_start: Event,
     event_foreground: Event,
     event_background: Event,
+    event_interactive: Event,
     // NOTE(alexkirsz) We use an atomic bool instead of a lock around `StatsType` to avoid the
     // locking overhead.
     enable_full_stats: AtomicBool,
@@ -280,6 +326,9 @@ struct CurrentTaskState {
 
     // true, if the current task has state in cells
     stateful: bool,
+
+    /// The priority of the current task execution
+    priority: TaskPriority,
 }
 
 // TODO implement our own thread pool and make these thread locals instead
@@ -311,6 +360,10 @@ impl<B: Backend + 'static> TurboTasks<B> {
             currently_scheduled_tasks: AtomicUsize::new(0),
             currently_scheduled_background_jobs: AtomicUsize::new(0),
             currently_scheduled_foreground_jobs: AtomicUsize::new(0),
+            currently_scheduled_interactive_tasks: AtomicUsize::new(0),
+            priority_scheduling: AtomicBool::new(false),
+            boosted_tasks: Default::default(),
+            recorder: OnceCell::new(),
             scheduled_tasks: AtomicUsize::new(0),
             start: Default::default(),
             aggregated_update: Default::default(),
@@ -318,6 +371,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
             event_start: Event::new(|| "TurboTasks::event_start".to_string()),
             event_foreground: Event::new(|| "TurboTasks::event_foreground".to_string()),
             event_background: Event::new(|| "TurboTasks::event_background".to_string()),
+            event_interactive: Event::new(|| "TurboTasks::event_interactive".to_string()),
             enable_full_stats: AtomicBool::new(false),
             program_start: Instant::now(),
         });
@@ -336,14 +390,43 @@ impl<B: Backend + 'static> TurboTasks<B> {
             + Sync
             + Send
             + 'static,
+    ) -> TaskId {
+        self.spawn_root_task_with_priority(TaskPriority::Normal, functor)
+    }
+
+    /// Creates a new root task with a priority. See
+    /// [TurboTasks::set_root_task_priority].
+    pub fn spawn_root_task_with_priority(
+        &self,
+        priority: TaskPriority,
+        functor: impl Fn() -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>
+            + Sync
+            + Send
+            + 'static,
     ) -> TaskId {
         let id = self
             .backend
             .create_transient_task(TransientTaskType::Root(Box::new(functor)), self);
+        if let Some(recorder) = self.recorder.get() {
+            recorder.task_created(id, recorder.root_key("root"), None);
+        }
+        if priority != TaskPriority::Normal {
+            self.set_root_task_priority(id, priority);
+        }
         self.schedule(id);
         id
     }
 
+    /// Sets the priority of a root task. All transitive subtasks of the root
+    /// task inherit the priority. Executions of normal tasks are held back
+    /// while interactive tasks are executing, but for at most
+    /// [MAX_INTERACTIVE_DELAY] so they can't be starved.
+    pub fn set_root_task_priority(&self, task: TaskId, priority: TaskPriority) {
+        self.priority_scheduling.store(true, Ordering::Release);
+        self.backend.set_root_task_priority(task, priority, self);
+        self.event_interactive.notify(usize::MAX);
+    }
+
     // TODO make sure that all dependencies settle before reading them
     /// Creates a new root task, that is only executed once.
     /// Dependencies will not invalidate the task.
@@ -355,6 +438,9 @@ impl<B: Backend + 'static> TurboTasks<B> {
         let id = self
             .backend
             .create_transient_task(TransientTaskType::Once(Box::pin(future)), self);
+        if let Some(recorder) = self.recorder.get() {
+            recorder.task_created(id, recorder.root_key("once"), None);
+        }
         self.schedule(id);
         id
     }
@@ -381,11 +467,18 @@ impl<B: Backend + 'static> TurboTasks<B> {
     /// Call a native function with arguments.
     /// All inputs must be resolved.
     pub(crate) fn native_call(&self, func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
-        RawVc::TaskOutput(self.backend.get_or_create_persistent_task(
+        let key = self
+            .recorder
+            .get()
+            .map(|recorder| recorder.call_key(&registry::get_function(func).name, &inputs));
+        let parent = current_task("turbo_function calls");
+        let task = self.backend.get_or_create_persistent_task(
             PersistentTaskType::Native(func, inputs),
-            current_task("turbo_function calls"),
+            parent,
             self,
-        ))
+        );
+        self.record_task_created(task, key, parent);
+        RawVc::TaskOutput(task)
     }
 
     /// Calls a native function with arguments. Resolves arguments when needed
@@ -394,11 +487,20 @@ impl<B: Backend + 'static> TurboTasks<B> {
         if inputs.iter().all(|i| i.is_resolved() && !i.is_nothing()) {
             self.native_call(func, inputs)
         } else {
-            RawVc::TaskOutput(self.backend.get_or_create_persistent_task(
+            let key = self.recorder.get().map(|recorder| {
+                recorder.call_key(
+                    &format!("[resolve] {}", registry::get_function(func).name),
+                    &inputs,
+                )
+            });
+            let parent = current_task("turbo_function calls");
+            let task = self.backend.get_or_create_persistent_task(
                 PersistentTaskType::ResolveNative(func, inputs),
-                current_task("turbo_function calls"),
+                parent,
                 self,
-            ))
+            );
+            self.record_task_created(task, key, parent);
+            RawVc::TaskOutput(task)
         }
     }
 
@@ -424,11 +526,62 @@ impl<B: Backend + 'static> TurboTasks<B> {
         }
 
         // create a wrapper task to resolve all inputs
-        RawVc::TaskOutput(self.backend.get_or_create_persistent_task(
+        let key = self.recorder.get().map(|recorder| {
+            recorder.call_key(
+                &format!(
+                    "[resolve trait] {}::{}",
+                    registry::get_trait(trait_type).name,
+                    trait_fn_name
+                ),
+                &inputs,
+            )
+        });
+        let parent = current_task("turbo_function calls");
+        let task = self.backend.get_or_create_persistent_task(
             PersistentTaskType::ResolveTrait(trait_type, trait_fn_name, inputs),
-            current_task("turbo_function calls"),
+            parent,
             self,
-        ))
+        );
+        self.record_task_created(task, key, parent);
+        RawVc::TaskOutput(task)
+    }
+
+    fn record_task_created(&self, task: TaskId, key: Option<String>, parent: TaskId) {
+        if let (Some(recorder), Some(key)) = (self.recorder.get(), key) {
+            recorder.task_created(task, key, Some(parent));
+        }
+    }
+
+    /// Records task creations, executions, cell reads and updates and
+    /// invalidations to a file at `path`. Needs to be called before any task
+    /// is spawned. See [crate::record] for the format. Recordings of two runs
+    /// can be compared with [Recording::diff].
+    pub fn start_recording(&self, path: &Path) -> Result<()> {
+        self.set_recorder(Recorder::new(path, None)?)
+    }
+
+    /// Records to a file at `path` like [TurboTasks::start_recording], but
+    /// starts task executions in the order of `recording` and serves file
+    /// reads from the file system snapshot of `recording`. Run on a single
+    /// threaded runtime to get a deterministic execution order.
+    pub fn start_replay(&self, recording: Recording, path: &Path) -> Result<()> {
+        self.set_recorder(Recorder::new(path, Some(recording))?)
+    }
+
+    fn set_recorder(&self, recorder: Recorder) -> Result<()> {
+        self.recorder
+            .set(recorder)
+            .map_err(|_| anyhow!("recording has already been started"))
+    }
+
+    /// Writes all recorded events to the recording file. Fails when any event
+    /// couldn't be written, so call this after [TurboTasks::stop_and_wait] to
+    /// make sure the recording is complete.
+    pub fn flush_recording(&self) -> Result<()> {
+        if let Some(recorder) = self.recorder.get() {
+            recorder.flush()?;
+        }
+        Ok(())
     }
 
     #[track_caller]
@@ -439,6 +592,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
         #[cfg(feature = "tokio_tracing")]
         let description = self.backend.get_task_description(task_id);
 
+        let mut priority = self.begin_task_priority(task_id);
         let this = self.pin();
         let future = async move {
             #[allow(clippy::blocks_in_if_conditions)]
@@ -448,15 +602,45 @@ impl<B: Backend + 'static> TurboTasks<B> {
                         return false;
                     }
 
+                    this.wait_for_task_priority(task_id, &mut priority).await;
+                    CURRENT_TASK_STATE.with(|cell| cell.borrow_mut().priority = priority);
+
                     // Setup thread locals
                     let execution_future = CELL_COUNTERS.scope(Default::default(), async {
-                        let execution = this.backend.try_start_task_execution(task_id, &*this)?;
+                        let TaskExecutionSpec { future, cancel } =
+                            this.backend.try_start_task_execution(task_id, &*this)?;
+                        if let Some(recorder) = this.recorder.get() {
+                            recorder.wait_for_turn(task_id).await;
+                            recorder.execution_started(task_id);
+                        }
+                        let mut future = AssertUnwindSafe(future).catch_unwind();
                         Some(
-                            TimedFuture::new(AssertUnwindSafe(execution.future).catch_unwind())
-                                .await,
+                            TimedFuture::new(async move {
+                                if let Some(cancel) = cancel {
+                                    select! {
+                                        result = &mut future => Some(result),
+                                        () = cancel => {
+                                            // The state of a stateful task would be lost, so
+                                            // it's finished and executed again instead.
+                                            if CURRENT_TASK_STATE
+                                                .with(|cell| cell.borrow().stateful)
+                                            {
+                                                Some(future.await)
+                                            } else {
+                                                None
+                                            }
+                                        }
+                                    }
+                                } else {
+                                    Some(future.await)
+                                }
+                            })
+                            .await,
                         )
                     });
-                    if let Some((result, duration, instant)) = execution_future.await {
+                    let reexecute = if let Some((result, duration, instant)) =
+                        execution_future.await
+                    {
                         if cfg!(feature = "log_function_stats") && duration.as_millis() > 1000 {
                             println!(
                                 "{} took {}",
@@ -464,28 +648,34 @@ impl<B: Backend + 'static> TurboTasks<B> {
                                 FormatDuration(duration)
                             )
                         }
-                        let result = result.map_err(|any| match any.downcast::<String>() {
-                            Ok(owned) => Some(Cow::Owned(*owned)),
-                            Err(any) => match any.downcast::<&'static str>() {
-                                Ok(str) => Some(Cow::Borrowed(*str)),
-                                Err(_) => None,
-                            },
-                        });
+                        let result = match result {
+                            Some(Ok(result)) => TaskExecutionResult::Finished(result),
+                            Some(Err(any)) => {
+                                TaskExecutionResult::Panicked(match any.downcast::<String>() {
+                                    Ok(owned) => Some(Cow::Owned(*owned)),
+                                    Err(any) => match any.downcast::<&'static str>() {
+                                        Ok(str) => Some(Cow::Borrowed(*str)),
+                                        Err(_) => None,
+                                    },
+                                })
+                            }
+                            None => TaskExecutionResult::Cancelled,
+                        };
                         this.backend.task_execution_result(task_id, result, &*this);
-                        let stateful = this.finish_current_task_state();
-                        let reexecute = this
-                            .backend
-                            .task_execution_completed(task_id, duration, instant, stateful, &*this);
-                        if !reexecute {
-                            return false;
+                        if let Some(recorder) = this.recorder.get() {
+                            recorder.execution_completed(task_id);
                         }
+                        let stateful = this.finish_current_task_state();
+                        this.backend
+                            .task_execution_completed(task_id, duration, instant, stateful, &*this)
                     } else {
-                        return false;
-                    }
-                    true
+                        false
+                    };
+                    reexecute
                 })
                 .await
             {}
+            this.finish_task_priority(task_id, priority);
             this.finish_primary_job();
             anyhow::Ok(())
         };
@@ -504,6 +694,98 @@ impl<B: Backend + 'static> TurboTasks<B> {
         tokio::task::spawn(future);
     }
 
+    fn get_task_priority(&self, task_id: TaskId) -> TaskPriority {
+        if self.boosted_tasks.contains(&task_id) {
+            TaskPriority::Interactive
+        } else {
+            self.backend.get_task_priority(task_id)
+        }
+    }
+
+    /// Returns the priority of a task that is being scheduled. Scheduled
+    /// interactive tasks hold back the execution of normal tasks.
+    fn begin_task_priority(&self, task_id: TaskId) -> TaskPriority {
+        if !self.priority_scheduling.load(Ordering::Acquire) {
+            return TaskPriority::Normal;
+        }
+        let priority = self.get_task_priority(task_id);
+        if priority == TaskPriority::Interactive {
+            self.currently_scheduled_interactive_tasks
+                .fetch_add(1, Ordering::AcqRel);
+        }
+        priority
+    }
+
+    /// Waits until a normal task is allowed to execute, which is when no
+    /// interactive task is scheduled or it has waited for
+    /// [MAX_INTERACTIVE_DELAY]. Updates `priority` when the task became
+    /// interactive in the meantime.
+    async fn wait_for_task_priority(&self, task_id: TaskId, priority: &mut TaskPriority) {
+        if *priority == TaskPriority::Interactive
+            || !self.priority_scheduling.load(Ordering::Acquire)
+        {
+            return;
+        }
+        let deadline = Instant::now() + MAX_INTERACTIVE_DELAY;
+        loop {
+            if self.get_task_priority(task_id) == TaskPriority::Interactive {
+                self.currently_scheduled_interactive_tasks
+                    .fetch_add(1, Ordering::AcqRel);
+                *priority = TaskPriority::Interactive;
+                return;
+            }
+            if self
+                .currently_scheduled_interactive_tasks
+                .load(Ordering::Acquire)
+                == 0
+            {
+                return;
+            }
+            let listener = self
+                .event_interactive
+                .listen_with_note(|| "wait for interactive tasks".to_string());
+            // The priority or the number of interactive tasks might have changed
+            // in between
+            if self.get_task_priority(task_id) == TaskPriority::Normal
+                && self
+                    .currently_scheduled_interactive_tasks
+                    .load(Ordering::Acquire)
+                    != 0
+            {
+                let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                    return;
+                };
+                let _ = tokio::time::timeout(remaining, listener).await;
+            }
+        }
+    }
+
+    fn finish_task_priority(&self, task_id: TaskId, priority: TaskPriority) {
+        if !self.priority_scheduling.load(Ordering::Acquire) {
+            return;
+        }
+        self.boosted_tasks.remove(&task_id);
+        if priority == TaskPriority::Interactive
+            && self
+                .currently_scheduled_interactive_tasks
+                .fetch_sub(1, Ordering::AcqRel)
+                == 1
+        {
+            self.event_interactive.notify(usize::MAX);
+        }
+    }
+
+    /// Executes a task that the current interactive task is waiting for with
+    /// interactive priority too.
+    fn boost_task_for_current_task(&self, task_id: TaskId) {
+        if self.priority_scheduling.load(Ordering::Acquire)
+            && self.current_task_priority() == TaskPriority::Interactive
+            && self.boosted_tasks.insert(task_id)
+        {
+            self.event_interactive.notify(usize::MAX);
+        }
+    }
+
     fn begin_primary_job(&self) {
         if self
             .currently_scheduled_tasks
@@ -579,10 +861,18 @@ impl<B: Backend + 'static> TurboTasks<B> {
         self.currently_scheduled_tasks.load(Ordering::Acquire)
     }
 
+    /// Waits until the task and the tasks its output links to have fresh
+    /// outputs. Executions that are cancelled because they became stale don't
+    /// resolve this, only the execution replacing them does.
     pub async fn wait_task_completion(&self, id: TaskId, fully_settled: bool) -> Result<()> {
         // INVALIDATION: This doesn't return a value, only waits for it to be ready.
-        let result = read_task_output_untracked(self, id, fully_settled).await;
-        result.map(|_| ())
+        let mut current = read_task_output_untracked(self, id, fully_settled).await?;
+        // A task that has finished can still link to the output of a task whose
+        // execution has been cancelled and is executed again.
+        while let RawVc::TaskOutput(task) = current {
+            current = read_task_output_untracked(self, task, fully_settled).await?;
+        }
+        Ok(())
     }
 
     #[deprecated(note = "Use get_or_wait_aggregated_update_info instead")]
@@ -778,6 +1068,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
             let CurrentTaskState {
                 tasks_to_notify,
                 stateful,
+                ..
             } = &mut *cell.borrow_mut();
             let tasks = take(tasks_to_notify);
             if !tasks.is_empty() {
@@ -852,15 +1143,22 @@ impl<B: Backend + 'static> TurboTasksCallApi for TurboTasks<B> {
 
 impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
     fn invalidate(&self, task: TaskId) {
+        if let Some(recorder) = self.recorder.get() {
+            recorder.invalidated(task, None);
+        }
         self.backend.invalidate_task(task, self);
     }
 
     fn invalidate_with_reason(&self, task: TaskId, reason: StaticOrArc<dyn InvalidationReason>) {
+        if let Some(recorder) = self.recorder.get() {
+            recorder.invalidated(task, Some(reason.to_string()));
+        }
         {
             let (_, reason_set) = &mut *self.aggregated_update.lock().unwrap();
-            reason_set.insert(reason);
+            reason_set.insert(reason.clone());
         }
-        self.invalidate(task);
+        self.backend
+            .invalidate_task_with_reason(task, &reason, self);
     }
 
     fn notify_scheduled_tasks(&self) {
@@ -881,12 +1179,16 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         strongly_consistent: bool,
     ) -> Result<Result<RawVc, EventListener>> {
-        self.backend.try_read_task_output(
+        let result = self.backend.try_read_task_output(
             task,
             current_task("reading Vcs"),
             strongly_consistent,
             self,
-        )
+        );
+        if matches!(result, Ok(Err(_))) {
+            self.boost_task_for_current_task(task);
+        }
+        result
     }
 
     fn try_read_task_output_untracked(
@@ -894,8 +1196,13 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         strongly_consistent: bool,
     ) -> Result<Result<RawVc, EventListener>> {
-        self.backend
-            .try_read_task_output_untracked(task, strongly_consistent, self)
+        let result = self
+            .backend
+            .try_read_task_output_untracked(task, strongly_consistent, self);
+        if matches!(result, Ok(Err(_))) {
+            self.boost_task_for_current_task(task);
+        }
+        result
     }
 
     fn try_read_task_cell(
@@ -903,8 +1210,18 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<CellContent, EventListener>> {
-        self.backend
-            .try_read_task_cell(task, index, current_task("reading Vcs"), self)
+        let reader = current_task("reading Vcs");
+        let result = self.backend.try_read_task_cell(task, index, reader, self);
+        match &result {
+            Ok(Ok(content)) => {
+                if let Some(recorder) = self.recorder.get() {
+                    recorder.cell_read(reader, task, index, content);
+                }
+            }
+            Ok(Err(_)) => self.boost_task_for_current_task(task),
+            Err(_) => {}
+        }
+        result
     }
 
     fn try_read_task_cell_untracked(
@@ -912,7 +1229,11 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<CellContent, EventListener>> {
-        self.backend.try_read_task_cell_untracked(task, index, self)
+        let result = self.backend.try_read_task_cell_untracked(task, index, self);
+        if matches!(result, Ok(Err(_))) {
+            self.boost_task_for_current_task(task);
+        }
+        result
     }
 
     fn try_read_own_task_cell_untracked(
@@ -968,6 +1289,9 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
     }
 
     fn update_own_task_cell(&self, task: TaskId, index: CellId, content: CellContent) {
+        if let Some(recorder) = self.recorder.get() {
+            recorder.cell_updated(task, index, &content);
+        }
         self.backend.update_task_cell(task, index, content, self);
     }
 
@@ -976,9 +1300,27 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
             .connect_task(task, current_task("connecting task"), self);
     }
 
+    fn is_recording(&self) -> bool {
+        self.recorder.get().is_some()
+    }
+
+    fn record_file_read(&self, path: &str, content: Option<Vec<u8>>) {
+        if let Some(recorder) = self.recorder.get() {
+            recorder.file_read(path, content);
+        }
+    }
+
+    fn replayed_file_read(&self, path: &str) -> Option<Option<Vec<u8>>> {
+        self.recorder.get()?.replayed_file_read(path)
+    }
+
     fn mark_own_task_as_finished(&self, task: TaskId) {
         self.backend.mark_own_task_as_finished(task, self);
     }
+
+    fn mark_own_task_as_session_dependent(&self, task: TaskId) {
+        self.backend.mark_own_task_as_session_dependent(task, self);
+    }
 }
 
 impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
@@ -1088,6 +1430,16 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
     fn program_duration_until(&self, instant: Instant) -> Duration {
         instant - self.program_start
     }
+
+    fn current_task_priority(&self) -> TaskPriority {
+        CURRENT_TASK_STATE
+            .try_with(|cell| cell.borrow().priority)
+            .unwrap_or_default()
+    }
+
+    fn task_priorities_changed(&self) {
+        self.event_interactive.notify(usize::MAX);
+    }
 }
 
 impl<B: Backend + 'static> TaskIdProvider for TurboTasks<B> {
@@ -1306,10 +1658,14 @@ pub fn current_task_for_testing() -> TaskId {
 
 /// Get an [Invalidator] that can be used to invalidate the current [Task]
 /// based on external events.
+///
+/// This also marks the current [Task] as session dependent.
 pub fn get_invalidator() -> Invalidator {
     let handle = Handle::current();
+    let task = current_task("turbo_tasks::get_invalidator()");
+    with_turbo_tasks(|tt| tt.mark_own_task_as_session_dependent(task));
     Invalidator {
-        task: current_task("turbo_tasks::get_invalidator()"),
+        task,
         turbo_tasks: weak_turbo_tasks(),
         handle,
     }
@@ -1323,6 +1679,14 @@ pub fn mark_finished() {
     });
 }
 
+/// Marks the current task as session dependent. Its results depend on state
+/// outside of turbo-tasks and can't be restored from a persistent cache.
+pub fn mark_session_dependent() {
+    with_turbo_tasks(|tt| {
+        tt.mark_own_task_as_session_dependent(current_task("turbo_tasks::mark_session_dependent()"))
+    });
+}
+
 /// Marks the current task as stateful. This prevents the tasks from being
 /// dropped without persisting the state.
 pub fn mark_stateful() {
@@ -0,0 +1,413 @@
+//! Recording of task executions and deterministic replay, to debug
+//! invalidation issues by comparing the recordings of two runs.
+//!
+//! A recording is a file with one JSON encoded [RecordedEvent] per line. Tasks
+//! are identified by a key derived from the function and its inputs, so keys
+//! are stable between runs, while [TaskId]s are not. File reads are recorded
+//! as well, so a replay sees the same file system as the recorded run.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use turbo_tasks_hash::hash_xxh3_hash64;
+
+use crate::{
+    backend::CellContent, event::Event, with_task_id_mapping, CellId, IdMapping, TaskId, TaskInput,
+};
+
+/// How long a task waits for its turn during replay before the expected task
+/// is considered missing.
+const REPLAY_STALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    /// A task has been called for the first time.
+    TaskCreated {
+        task: String,
+        parent: Option<String>,
+    },
+    /// An execution of a task has started.
+    ExecutionStarted { task: String },
+    /// An execution of a task has completed.
+    ExecutionCompleted { task: String },
+    /// A task has read a cell. The hash is missing for values that are not
+    /// serializable.
+    CellRead {
+        reader: String,
+        task: String,
+        cell: String,
+        hash: Option<u64>,
+    },
+    /// A task has updated one of its cells.
+    CellUpdated {
+        task: String,
+        cell: String,
+        hash: Option<u64>,
+    },
+    /// A task has been invalidated.
+    Invalidated {
+        task: String,
+        reason: Option<String>,
+    },
+    /// A file has been read. The content is missing when the file doesn't
+    /// exist.
+    FileRead {
+        path: String,
+        content: Option<Vec<u8>>,
+    },
+}
+
+/// A list of [RecordedEvent]s read from a recording file.
+#[derive(Debug, Default, Clone)]
+pub struct Recording {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("unable to open recording {}", path.display()))?;
+        let events = BufReader::new(file)
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(&line?).with_context(|| {
+                    format!("invalid event in {} at line {}", path.display(), i + 1)
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { events })
+    }
+
+    /// Compares the cell updates of two recordings and returns the first cell
+    /// update of `self` that is missing or has a different value in `other`,
+    /// or the first additional update in `other`.
+    pub fn diff(&self, other: &Recording) -> Option<Divergence> {
+        let mut actual_updates: HashMap<(&str, &str), Vec<Option<u64>>> = HashMap::new();
+        for (task, cell, hash) in other.cell_updates() {
+            actual_updates.entry((task, cell)).or_default().push(hash);
+        }
+        let mut counts: HashMap<(&str, &str), usize> = HashMap::new();
+        for (task, cell, hash) in self.cell_updates() {
+            let count = counts.entry((task, cell)).or_default();
+            let update = *count;
+            *count += 1;
+            let actual = actual_updates
+                .get(&(task, cell))
+                .and_then(|updates| updates.get(update));
+            if actual != Some(&hash) {
+                return Some(Divergence {
+                    task: task.to_string(),
+                    cell: cell.to_string(),
+                    update,
+                    expected: Some(hash),
+                    actual: actual.copied(),
+                });
+            }
+        }
+        for (task, cell, hash) in other.cell_updates() {
+            let count = counts.entry((task, cell)).or_default();
+            if *count == 0 {
+                return Some(Divergence {
+                    task: task.to_string(),
+                    cell: cell.to_string(),
+                    update: 0,
+                    expected: None,
+                    actual: Some(hash),
+                });
+            }
+            *count -= 1;
+        }
+        None
+    }
+
+    fn cell_updates(&self) -> impl Iterator<Item = (&str, &str, Option<u64>)> {
+        self.events.iter().filter_map(|event| match event {
+            RecordedEvent::CellUpdated { task, cell, hash } => {
+                Some((task.as_str(), cell.as_str(), *hash))
+            }
+            _ => None,
+        })
+    }
+
+    fn execution_order(&self) -> VecDeque<String> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                RecordedEvent::ExecutionStarted { task } => Some(task.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The contents of all file reads, in the order they were read.
+    fn file_snapshot(&self) -> HashMap<String, VecDeque<Option<Vec<u8>>>> {
+        let mut files: HashMap<_, VecDeque<_>> = HashMap::new();
+        for event in &self.events {
+            if let RecordedEvent::FileRead { path, content } = event {
+                files
+                    .entry(path.clone())
+                    .or_default()
+                    .push_back(content.clone());
+            }
+        }
+        files
+    }
+}
+
+/// The first cell that has different values in two recordings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub task: String,
+    pub cell: String,
+    /// The number of previous updates of the cell in the same recording.
+    pub update: usize,
+    /// The hash of the value in the expected recording. `None` when the update
+    /// is missing there, `Some(None)` when the value is not serializable.
+    pub expected: Option<Option<u64>>,
+    /// The hash of the value in the actual recording.
+    pub actual: Option<Option<u64>>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn format_hash(hash: Option<Option<u64>>) -> String {
+            match hash {
+                None => "no update".to_string(),
+                Some(None) => "not serializable".to_string(),
+                Some(Some(hash)) => format!("{:016x}", hash),
+            }
+        }
+        write!(
+            f,
+            "update {} of {} in {} differs: expected {}, actual {}",
+            self.update,
+            self.cell,
+            self.task,
+            format_hash(self.expected),
+            format_hash(self.actual)
+        )
+    }
+}
+
+/// Writes [RecordedEvent]s to a file and keeps track of task keys.
+pub(crate) struct Recorder {
+    writer: Mutex<RecordWriter>,
+    keys: DashMap<TaskId, String>,
+    roots: AtomicUsize,
+    replay: Option<ReplayState>,
+}
+
+/// The execution order and file system snapshot of a recording that is being
+/// replayed.
+struct ReplayState {
+    order: Mutex<VecDeque<String>>,
+    event: Event,
+    files: Mutex<HashMap<String, VecDeque<Option<Vec<u8>>>>>,
+}
+
+struct RecordWriter {
+    file: BufWriter<File>,
+    /// The first error while writing. No further events are written after an
+    /// error, as the recording would be incomplete anyway.
+    error: Option<anyhow::Error>,
+}
+
+/// Maps task ids to the hash of their keys, to serialize values with stable
+/// ids.
+struct KeyMapping<'a>(&'a Recorder);
+
+impl<'a> IdMapping<TaskId> for KeyMapping<'a> {
+    fn forward(&self, id: TaskId) -> usize {
+        hash_xxh3_hash64(self.0.key(id)) as usize
+    }
+
+    fn backward(&self, _id: usize) -> TaskId {
+        unreachable!("recorded values are never deserialized")
+    }
+}
+
+impl Recorder {
+    pub fn new(path: &Path, replay: Option<Recording>) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("unable to create recording {}", path.display()))?;
+        Ok(Self {
+            writer: Mutex::new(RecordWriter {
+                file: BufWriter::new(file),
+                error: None,
+            }),
+            keys: DashMap::new(),
+            roots: AtomicUsize::new(0),
+            replay: replay.map(|recording| ReplayState {
+                order: Mutex::new(recording.execution_order()),
+                event: Event::new(|| "Recorder::replay".to_string()),
+                files: Mutex::new(recording.file_snapshot()),
+            }),
+        })
+    }
+
+    pub fn key(&self, task: TaskId) -> String {
+        self.keys
+            .get(&task)
+            .map(|key| key.clone())
+            .unwrap_or_else(|| format!("unknown {}", task))
+    }
+
+    fn hash<T: Serialize>(&self, value: &T) -> Option<u64> {
+        with_task_id_mapping(KeyMapping(self), || serde_json::to_vec(value).ok())
+            .map(hash_xxh3_hash64)
+    }
+
+    /// Computes the key for a call of a function with inputs.
+    pub fn call_key(&self, name: &str, inputs: &[TaskInput]) -> String {
+        match self.hash(&inputs) {
+            Some(hash) => format!("{} {:016x}", name, hash),
+            None => format!("{} (unserializable inputs)", name),
+        }
+    }
+
+    pub fn root_key(&self, name: &str) -> String {
+        format!("{} {}", name, self.roots.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Assigns a key to a task. Records the creation when the task is new.
+    pub fn task_created(&self, task: TaskId, key: String, parent: Option<TaskId>) {
+        if self.keys.contains_key(&task) {
+            return;
+        }
+        self.keys.insert(task, key.clone());
+        let parent = parent.map(|parent| self.key(parent));
+        self.write(RecordedEvent::TaskCreated { task: key, parent });
+    }
+
+    pub fn execution_started(&self, task: TaskId) {
+        self.write(RecordedEvent::ExecutionStarted {
+            task: self.key(task),
+        });
+    }
+
+    pub fn execution_completed(&self, task: TaskId) {
+        self.write(RecordedEvent::ExecutionCompleted {
+            task: self.key(task),
+        });
+    }
+
+    pub fn cell_read(&self, reader: TaskId, task: TaskId, cell: CellId, content: &CellContent) {
+        self.write(RecordedEvent::CellRead {
+            reader: self.key(reader),
+            task: self.key(task),
+            cell: cell.to_string(),
+            hash: self.hash(content),
+        });
+    }
+
+    pub fn cell_updated(&self, task: TaskId, cell: CellId, content: &CellContent) {
+        self.write(RecordedEvent::CellUpdated {
+            task: self.key(task),
+            cell: cell.to_string(),
+            hash: self.hash(content),
+        });
+    }
+
+    pub fn invalidated(&self, task: TaskId, reason: Option<String>) {
+        self.write(RecordedEvent::Invalidated {
+            task: self.key(task),
+            reason,
+        });
+    }
+
+    pub fn file_read(&self, path: &str, content: Option<Vec<u8>>) {
+        self.write(RecordedEvent::FileRead {
+            path: path.to_string(),
+            content,
+        });
+    }
+
+    /// When replaying, returns the content of the next recorded read of the
+    /// file. The last recorded content is returned for all further reads.
+    /// Returns `None` when the file wasn't read in the recording.
+    pub fn replayed_file_read(&self, path: &str) -> Option<Option<Vec<u8>>> {
+        let replay = self.replay.as_ref()?;
+        let mut files = replay.files.lock().unwrap();
+        let contents = files.get_mut(path)?;
+        if contents.len() > 1 {
+            contents.pop_front()
+        } else {
+            contents.front().cloned()
+        }
+    }
+
+    fn write(&self, event: RecordedEvent) {
+        let mut writer = self.writer.lock().unwrap();
+        if writer.error.is_some() {
+            return;
+        }
+        let result = serde_json::to_writer(&mut writer.file, &event)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| Ok(writer.file.write_all(b"\n")?));
+        if let Err(err) = result {
+            writer.error = Some(err);
+        }
+    }
+
+    /// Writes all buffered events to the file. Fails when any event couldn't
+    /// be written.
+    pub fn flush(&self) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        if let Some(err) = writer.error.take() {
+            return Err(err.context("unable to write recorded event"));
+        }
+        writer.file.flush()?;
+        Ok(())
+    }
+    /// When replaying, waits until the task is the next one in the recorded
+    /// execution order. Tasks that are not part of the remaining order start
+    /// immediately. When the expected task doesn't show up in time, it is
+    /// skipped.
+    pub async fn wait_for_turn(&self, task: TaskId) {
+        let Some(replay) = &self.replay else {
+            return;
+        };
+        let key = self.key(task);
+        loop {
+            let (expected, listener) = {
+                let mut order = replay.order.lock().unwrap();
+                if order.front() == Some(&key) {
+                    order.pop_front();
+                    replay.event.notify(usize::MAX);
+                    return;
+                }
+                if !order.contains(&key) {
+                    return;
+                }
+                (order.front().cloned(), replay.event.listen())
+            };
+            if tokio::time::timeout(REPLAY_STALL_TIMEOUT, listener)
+                .await
+                .is_err()
+            {
+                let mut order = replay.order.lock().unwrap();
+                if order.front() == expected.as_ref() {
+                    if let Some(missing) = order.pop_front() {
+                        println!("replay diverged: {} was not executed", missing);
+                    }
+                    replay.event.notify(usize::MAX);
+                }
+            }
+        }
+    }
+}
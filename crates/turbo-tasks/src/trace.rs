@@ -35,6 +35,12 @@ impl TraceRawVcsContext {
 ///
 /// `#[derive(TraceRawVcs)]` is available.
 /// `#[trace_ignore]` can be used on fields to skip tracing for them.
+#[rustc_on_unimplemented(
+    message = "`{Self}` doesn't implement TraceRawVcs",
+    label = "this field can't be traced",
+    note = "derive or implement TraceRawVcs for `{Self}`, or add `#[turbo_tasks(trace_ignore)]` \
+            to the field when it doesn't contain Vcs"
+)]
 pub trait TraceRawVcs {
     fn trace_raw_vcs(&self, context: &mut TraceRawVcsContext);
     fn get_raw_vcs(&self) -> Vec<RawVc> {
@@ -3,7 +3,11 @@ use std::{fmt::Debug, future::Future, marker::PhantomData, sync::Arc};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use crate::{manager::find_cell_by_type, RawVc, SharedReference, ValueTraitVc};
+use crate::{
+    manager::find_cell_by_type,
+    trace::{TraceRawVcs, TraceRawVcsContext},
+    RawVc, SharedReference, ValueTraitVc,
+};
 
 /// Similar to a [`ReadRef<T>`], but contains a value trait object instead. The
 /// only way to interact with a `TraitRef<T>` is by passing it around or turning
@@ -19,6 +23,11 @@ where
     _t: PhantomData<Arc<T>>,
 }
 
+/// The referenced value is type erased, so Vcs inside of it can't be traced.
+impl<T: ?Sized> TraceRawVcs for TraitRef<T> {
+    fn trace_raw_vcs(&self, _context: &mut TraceRawVcsContext) {}
+}
+
 impl<T> TraitRef<T> {
     pub(crate) fn new(shared_reference: SharedReference) -> Self {
         Self {
@@ -12,7 +12,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     id::{FunctionId, TraitTypeId},
-    magic_any::{AnyDeserializeSeed, MagicAny, MagicAnyDeserializeSeed},
+    magic_any::{
+        AnyDeserializeSeed, AnyMigrationSeed, AnyMigrationSeedFunctor, MagicAny,
+        MagicAnyDeserializeSeed,
+    },
     registry::{register_trait_type, register_value_type},
     CollectiblesSource, RawVc, ValueTypeId,
 };
@@ -95,6 +98,26 @@ where
 /// or avoid Value<...> in favor of a real Vc
 pub trait TypedForInput: Typed {}
 
+/// Converts persisted values of older versions of a value type. Use
+/// `#[turbo_tasks::value(version = 2, migrate)]` to opt into migration.
+pub trait MigrateValue: Sized {
+    /// Converts a value that has been persisted by `version` of the value
+    /// type. When `None` is returned, the value is discarded and recomputed.
+    fn migrate(version: u32, value: PersistedValue<'_, '_>) -> Option<Self>;
+}
+
+/// A value that has been persisted by an older version of a value type.
+pub struct PersistedValue<'a, 'de> {
+    deserializer: &'a mut dyn erased_serde::Deserializer<'de>,
+}
+
+impl<'a, 'de> PersistedValue<'a, 'de> {
+    /// Deserializes the value as the type it has been persisted with.
+    pub fn deserialize<T: Deserialize<'de>>(self) -> Result<T, erased_serde::Error> {
+        erased_serde::deserialize(self.deserializer)
+    }
+}
+
 type MagicSerializationFn = fn(&dyn MagicAny) -> &dyn erased_serde::Serialize;
 type AnySerializationFn = fn(&(dyn Any + Sync + Send)) -> &dyn erased_serde::Serialize;
 
@@ -114,10 +137,15 @@ pub struct ValueType {
     pub traits: AutoSet<TraitTypeId, BuildNoHashHasher<TraitTypeId>>,
     /// List of trait methods available
     pub trait_methods: AutoMap<(TraitTypeId, Cow<'static, str>), FunctionId>,
+    /// The version of the serialized format, declared with
+    /// `#[turbo_tasks::value(version = N)]`. Persisted values of other versions
+    /// are migrated or discarded.
+    pub version: u32,
 
     /// Functors for serialization
     magic_serialization: Option<(MagicSerializationFn, MagicAnyDeserializeSeed)>,
     any_serialization: Option<(AnySerializationFn, AnyDeserializeSeed)>,
+    migration: Option<AnyMigrationSeedFunctor>,
 }
 
 impl Hash for ValueType {
@@ -175,8 +203,10 @@ impl ValueType {
             name: std::any::type_name::<T>().to_string(),
             traits: AutoSet::default(),
             trait_methods: AutoMap::new(),
+            version: 0,
             magic_serialization: None,
             any_serialization: None,
+            migration: None,
         }
     }
 
@@ -188,11 +218,13 @@ impl ValueType {
             name: std::any::type_name::<T>().to_string(),
             traits: AutoSet::default(),
             trait_methods: AutoMap::new(),
+            version: 0,
             magic_serialization: Some((
                 <dyn MagicAny>::as_serialize::<T>,
                 MagicAnyDeserializeSeed::new::<T>(),
             )),
             any_serialization: Some((any_as_serialize::<T>, AnyDeserializeSeed::new::<T>())),
+            migration: None,
         }
     }
 
@@ -204,9 +236,30 @@ impl ValueType {
             name: std::any::type_name::<T>().to_string(),
             traits: AutoSet::default(),
             trait_methods: AutoMap::new(),
+            version: 0,
             magic_serialization: None,
             any_serialization: Some((any_as_serialize::<T>, AnyDeserializeSeed::new::<T>())),
+            migration: None,
+        }
+    }
+
+    /// This is internally used by `#[turbo_tasks::value]`
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// This is internally used by `#[turbo_tasks::value]`
+    pub fn with_migration<T: MigrateValue + Any + Send + Sync + 'static>(mut self) -> Self {
+        fn migrate<T: MigrateValue + Any + Send + Sync + 'static>(
+            version: u32,
+            deserializer: &mut dyn erased_serde::Deserializer<'_>,
+        ) -> Result<Option<Box<dyn Any + Sync + Send>>, erased_serde::Error> {
+            Ok(T::migrate(version, PersistedValue { deserializer })
+                .map(|value| Box::new(value) as Box<dyn Any + Sync + Send>))
         }
+        self.migration = Some(migrate::<T>);
+        self
     }
 
     pub fn magic_as_serializable<'a>(
@@ -240,6 +293,13 @@ impl ValueType {
         self.any_serialization.map(|s| s.1)
     }
 
+    /// Returns a seed to deserialize a value that has been serialized by
+    /// `version` of this value type, when the type supports migration.
+    pub fn get_migration_seed(&self, version: u32) -> Option<AnyMigrationSeed> {
+        self.migration
+            .map(|migration| AnyMigrationSeed::new(migration, version))
+    }
+
     /// This is internally used by `#[turbo_tasks::value_impl]`
     pub fn register_trait_method(
         &mut self,
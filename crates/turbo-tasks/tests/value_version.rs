@@ -0,0 +1,99 @@
+#![feature(min_specialization)]
+
+use bincode::Options;
+use serde::{de::DeserializeSeed, Deserialize, Serialize};
+use turbo_tasks::{registry, MigrateValue, PersistedValue, Typed};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[test]
+fn versions_are_declared_on_value_types() {
+    lazy_static::initialize(&REGISTER);
+    let unversioned = registry::get_value_type(Unversioned::get_value_type_id());
+    assert_eq!(unversioned.version, 0);
+    assert!(unversioned.get_migration_seed(0).is_none());
+
+    let config = registry::get_value_type(Config::get_value_type_id());
+    assert_eq!(config.version, 2);
+}
+
+#[test]
+fn older_versions_are_migrated() {
+    lazy_static::initialize(&REGISTER);
+    let opt = bincode::DefaultOptions::new();
+    let config = registry::get_value_type(Config::get_value_type_id());
+
+    let data = opt
+        .serialize(&ConfigV1 {
+            name: "app".to_string(),
+        })
+        .unwrap();
+    let value = opt
+        .deserialize_seed(config.get_migration_seed(1).unwrap(), &data)
+        .unwrap()
+        .unwrap();
+    assert!(
+        value.downcast_ref::<Config>()
+            == Some(&Config {
+                name: "app".to_string(),
+                minify: false,
+            })
+    );
+
+    // Unknown versions are discarded
+    let seed = config.get_migration_seed(0).unwrap();
+    assert!(seed
+        .deserialize(&mut bincode::Deserializer::from_slice(&data, opt))
+        .unwrap()
+        .is_none());
+
+    // The current version is serialized with the value type
+    let current = Config {
+        name: "app".to_string(),
+        minify: true,
+    };
+    let data = opt
+        .serialize(
+            config
+                .any_as_serializable(&(std::sync::Arc::new(current.clone()) as _))
+                .unwrap(),
+        )
+        .unwrap();
+    let value = opt
+        .deserialize_seed(config.get_any_deserialize_seed().unwrap(), &data)
+        .unwrap();
+    assert!(value.downcast_ref::<Config>() == Some(&current));
+}
+
+#[turbo_tasks::value]
+struct Unversioned {
+    name: String,
+}
+
+#[turbo_tasks::value(shared, version = 2, migrate)]
+#[derive(Clone)]
+struct Config {
+    name: String,
+    minify: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ConfigV1 {
+    name: String,
+}
+
+impl MigrateValue for Config {
+    fn migrate(version: u32, value: PersistedValue<'_, '_>) -> Option<Self> {
+        match version {
+            1 => {
+                let ConfigV1 { name } = value.deserialize().ok()?;
+                Some(Config {
+                    name,
+                    minify: false,
+                })
+            }
+            _ => None,
+        }
+    }
+}
@@ -23,7 +23,7 @@ use turbopack::evaluate_context::node_build_environment;
 use turbopack_cli_utils::issue::{ConsoleUiVc, LogOptions};
 use turbopack_core::{
     environment::ServerAddr,
-    issue::{IssueReporterVc, IssueSeverity},
+    issue::{watch::emit_watch_fallback_issue, IssueReporterVc, IssueSeverity},
     resolve::{parse::RequestVc, pattern::QueryMapVc},
     server_fs::ServerFileSystemVc,
 };
@@ -233,6 +233,7 @@ impl TurbopackDevServerBuilder {
 async fn project_fs(project_dir: &str) -> Result<FileSystemVc> {
     let disk_fs = DiskFileSystemVc::new("project".to_string(), project_dir.to_string());
     disk_fs.await?.start_watching()?;
+    emit_watch_fallback_issue(disk_fs).await?;
     Ok(disk_fs.into())
 }
 
@@ -240,6 +241,7 @@ async fn project_fs(project_dir: &str) -> Result<FileSystemVc> {
 async fn output_fs(project_dir: &str) -> Result<FileSystemVc> {
     let disk_fs = DiskFileSystemVc::new("output".to_string(), project_dir.to_string());
     disk_fs.await?.start_watching()?;
+    emit_watch_fallback_issue(disk_fs).await?;
     Ok(disk_fs.into())
 }
 
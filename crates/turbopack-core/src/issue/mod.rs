@@ -3,6 +3,7 @@ pub mod code_gen;
 pub mod package_json;
 pub mod resolve;
 pub mod unsupported_module;
+pub mod watch;
 
 use std::{
     cmp::Ordering,
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use turbo_tasks::{primitives::StringVc, CompletionVc};
+use turbo_tasks_fs::{DiskFileSystemVc, FileSystem, FileSystemPathVc, FileSystemVc};
+
+use super::{Issue, IssueSeverity, IssueSeverityVc, IssueVc};
+
+#[turbo_tasks::value(shared)]
+pub struct WatchFallbackIssue {
+    pub path: FileSystemPathVc,
+    pub reason: String,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for WatchFallbackIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> IssueSeverityVc {
+        IssueSeverity::Warning.into()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> StringVc {
+        StringVc::cell("File watching fell back to polling".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn category(&self) -> StringVc {
+        StringVc::cell("watch".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn context(&self) -> FileSystemPathVc {
+        self.path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> StringVc {
+        StringVc::cell(format!(
+            "Changes are detected by polling, which is slower and uses more CPU. On Linux this \
+             usually means that the inotify watch limit has been reached, which can be raised \
+             with `sysctl fs.inotify.max_user_watches`.\n\n{}",
+            self.reason
+        ))
+    }
+}
+
+/// Emits a [WatchFallbackIssue] when watching `fs` has switched to polling.
+/// The returned completion doesn't change, so awaiting it doesn't invalidate
+/// the caller.
+#[turbo_tasks::function]
+pub async fn emit_watch_fallback_issue(fs: DiskFileSystemVc) -> Result<CompletionVc> {
+    if let Some(reason) = &*fs.watch_fallback().await? {
+        WatchFallbackIssue {
+            path: FileSystemVc::from(fs).root(),
+            reason: reason.clone(),
+        }
+        .cell()
+        .as_issue()
+        .emit();
+    }
+    Ok(CompletionVc::immutable())
+}
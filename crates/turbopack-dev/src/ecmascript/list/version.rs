@@ -13,7 +13,6 @@ pub(super) struct EcmascriptDevChunkListVersion {
     #[turbo_tasks(trace_ignore)]
     pub by_path: IndexMap<String, TraitRef<VersionVc>>,
     /// A map from chunk merger to the version of the merged contents of chunks.
-    #[turbo_tasks(trace_ignore)]
     pub by_merger: IndexMap<VersionedContentMergerVc, TraitRef<VersionVc>>,
 }
 
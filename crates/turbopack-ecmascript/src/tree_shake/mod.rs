@@ -278,7 +278,7 @@ pub(crate) enum SplitResult {
         #[turbo_tasks(debug_ignore, trace_ignore)]
         entrypoints: FxHashMap<Key, u32>,
 
-        #[turbo_tasks(debug_ignore, trace_ignore)]
+        #[turbo_tasks(debug_ignore)]
         modules: Vec<ParseResultVc>,
 
         #[turbo_tasks(debug_ignore, trace_ignore)]
@@ -1,11 +1,11 @@
 use anyhow::Result;
 use indexmap::indexmap;
 use turbo_tasks_env::{
-    CommandLineProcessEnvVc, CustomProcessEnvVc, EnvMapVc, ProcessEnv, ProcessEnvVc,
+    dotenv_files, CommandLineProcessEnvVc, CustomProcessEnvVc, EnvMapVc, ProcessEnv, ProcessEnvVc,
 };
 use turbo_tasks_fs::FileSystemPathVc;
 
-use crate::TryDotenvProcessEnvVc;
+use crate::{TryDotenvProcessEnvVc, ValidatedProcessEnvVc};
 
 /// Loads a series of dotenv files according to the precedence rules set by
 /// https://nextjs.org/docs/basic-features/environment-variables#environment-variable-load-order
@@ -13,34 +13,44 @@ use crate::TryDotenvProcessEnvVc;
 pub async fn load_env(project_path: FileSystemPathVc) -> Result<ProcessEnvVc> {
     let env = CommandLineProcessEnvVc::new().as_process_env();
 
-    let node_env = env.read("NODE_ENV").await?;
-    let node_env = node_env.as_deref().unwrap_or("development");
+    let node_env = node_env(env).await?;
 
     let env = CustomProcessEnvVc::new(
         env,
         EnvMapVc::cell(indexmap! {
-            "NODE_ENV".to_string() => node_env.to_string(),
+            "NODE_ENV".to_string() => node_env.clone(),
         }),
     )
     .as_process_env();
 
-    let files = [
-        Some(format!(".env.{node_env}.local")),
-        if node_env == "test" {
-            None
-        } else {
-            Some(".env.local".into())
-        },
-        Some(format!(".env.{node_env}")),
-        Some(".env".into()),
-    ]
-    .into_iter()
-    .flatten();
-
-    let env = files.fold(env, |prior, f| {
-        let path = project_path.join(&f);
-        TryDotenvProcessEnvVc::new(prior, path).as_process_env()
-    });
+    let env = dotenv_files(project_path, &node_env)
+        .into_iter()
+        .fold(env, |prior, path| {
+            TryDotenvProcessEnvVc::new(prior, path).as_process_env()
+        });
 
     Ok(env)
 }
+
+/// Loads the env like [load_env] and validates it against the env schema at
+/// `schema_path`, see [ValidatedProcessEnvVc].
+#[turbo_tasks::function]
+pub async fn load_validated_env(
+    project_path: FileSystemPathVc,
+    schema_path: FileSystemPathVc,
+) -> Result<ProcessEnvVc> {
+    let env = load_env(project_path);
+    // The files are selected by the NODE_ENV of the command line, like in
+    // [load_env], as a dotenv file can't change which files are loaded
+    let node_env = node_env(CommandLineProcessEnvVc::new().as_process_env()).await?;
+    Ok(
+        ValidatedProcessEnvVc::new(env, schema_path, dotenv_files(project_path, &node_env))
+            .as_process_env(),
+    )
+}
+
+/// Reads NODE_ENV from `env`, defaulting to `development`.
+async fn node_env(env: ProcessEnvVc) -> Result<String> {
+    let node_env = env.read("NODE_ENV").await?;
+    Ok(node_env.as_deref().unwrap_or("development").to_string())
+}
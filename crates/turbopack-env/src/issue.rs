@@ -1,7 +1,8 @@
 use anyhow::Result;
 use turbo_tasks::primitives::StringVc;
+use turbo_tasks_env::EnvVarError;
 use turbo_tasks_fs::FileSystemPathVc;
-use turbopack_core::issue::{Issue, IssueVc};
+use turbopack_core::issue::{Issue, IssueVc, OptionIssueSourceVc};
 
 /// An issue that occurred while resolving the parsing or evaluating the .env.
 #[turbo_tasks::value(shared)]
@@ -32,3 +33,44 @@ impl Issue for ProcessEnvIssue {
         self.description
     }
 }
+
+/// An env variable that doesn't match the env schema.
+#[turbo_tasks::value(shared)]
+pub struct EnvVarIssue {
+    /// The dotenv file that defines the variable, or the env schema.
+    pub path: FileSystemPathVc,
+    pub name: String,
+    pub error: EnvVarError,
+    pub source: OptionIssueSourceVc,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for EnvVarIssue {
+    #[turbo_tasks::function]
+    fn title(&self) -> StringVc {
+        StringVc::cell(match self.error {
+            EnvVarError::Missing => format!("Missing environment variable {}", self.name),
+            EnvVarError::Invalid { .. } => format!("Invalid environment variable {}", self.name),
+        })
+    }
+
+    #[turbo_tasks::function]
+    fn category(&self) -> StringVc {
+        StringVc::cell("env".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn context(&self) -> FileSystemPathVc {
+        self.path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> StringVc {
+        StringVc::cell(format!("{} {}", self.name, self.error))
+    }
+
+    #[turbo_tasks::function]
+    fn source(&self) -> OptionIssueSourceVc {
+        self.source
+    }
+}
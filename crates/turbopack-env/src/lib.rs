@@ -17,11 +17,13 @@ pub mod dotenv;
 mod embeddable;
 mod issue;
 mod try_env;
+mod validated;
 
 pub use asset::{ProcessEnvAsset, ProcessEnvAssetVc};
 pub use embeddable::EmbeddableProcessEnvVc;
-pub use issue::{ProcessEnvIssue, ProcessEnvIssueVc};
+pub use issue::{EnvVarIssue, EnvVarIssueVc, ProcessEnvIssue, ProcessEnvIssueVc};
 pub use try_env::TryDotenvProcessEnvVc;
+pub use validated::ValidatedProcessEnvVc;
 
 pub fn register() {
     turbo_tasks::register();
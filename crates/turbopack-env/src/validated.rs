@@ -0,0 +1,135 @@
+use anyhow::Result;
+use turbo_tasks_env::{EnvMapVc, EnvSchemaVc, EnvVarError, ProcessEnv, ProcessEnvVc};
+use turbo_tasks_fs::{FileContent, FileSystemPathVc};
+use turbopack_core::{
+    issue::{IssueSource, IssueSourceVc, OptionIssueSourceVc},
+    source_asset::SourceAssetVc,
+    source_pos::SourcePos,
+};
+
+use crate::EnvVarIssue;
+
+/// Validates the env variables of a prior [ProcessEnv] against an env schema
+/// (see [turbo_tasks_env::EnvSchema]). Missing and invalid variables are
+/// reported as issues, and the defaults of undefined variables are added.
+#[turbo_tasks::value]
+pub struct ValidatedProcessEnv {
+    prior: ProcessEnvVc,
+    schema: FileSystemPathVc,
+    /// The dotenv files that are read by `prior`, in order of precedence.
+    /// Issues about invalid values point at the line that defines them.
+    dotenv_files: Vec<FileSystemPathVc>,
+}
+
+#[turbo_tasks::value_impl]
+impl ValidatedProcessEnvVc {
+    #[turbo_tasks::function]
+    pub fn new(
+        prior: ProcessEnvVc,
+        schema: FileSystemPathVc,
+        dotenv_files: Vec<FileSystemPathVc>,
+    ) -> Self {
+        ValidatedProcessEnv {
+            prior,
+            schema,
+            dotenv_files,
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ProcessEnv for ValidatedProcessEnv {
+    #[turbo_tasks::function]
+    async fn read_all(&self) -> Result<EnvMapVc> {
+        let env = self.prior.read_all().await?;
+        let schema = EnvSchemaVc::read(self.schema).await?;
+        let (validated, errors) = schema.validate(&env);
+
+        for (name, error) in errors {
+            let definition = match &error {
+                EnvVarError::Missing => None,
+                EnvVarError::Invalid { value, .. } => {
+                    find_definition(&self.dotenv_files, &name, value).await?
+                }
+            };
+            let (path, source) = match definition {
+                Some((path, source)) => (path, OptionIssueSourceVc::some(source)),
+                // The value comes from the process env or the variable is
+                // missing, so the schema is the best place to point at.
+                None => (self.schema, OptionIssueSourceVc::none()),
+            };
+            EnvVarIssue {
+                path,
+                name,
+                error,
+                source,
+            }
+            .cell()
+            .as_issue()
+            .emit();
+        }
+
+        Ok(EnvMapVc::cell(validated))
+    }
+}
+
+/// Finds the line that defines the variable `name` in the first of the
+/// dotenv `files` that defines it. Returns `None` when that line doesn't
+/// define `value`, as the value comes from the process env then.
+async fn find_definition(
+    files: &[FileSystemPathVc],
+    name: &str,
+    value: &str,
+) -> Result<Option<(FileSystemPathVc, IssueSourceVc)>> {
+    for &path in files {
+        let FileContent::Content(file) = &*path.read().await? else {
+            continue;
+        };
+        let Ok(content) = file.content().to_str() else {
+            continue;
+        };
+        for (line_number, line) in content.lines().enumerate() {
+            let Some((key, line_value)) = parse_assignment(line) else {
+                continue;
+            };
+            if key.eq_ignore_ascii_case(name) {
+                if line_value != value {
+                    return Ok(None);
+                }
+                let source = IssueSource {
+                    asset: SourceAssetVc::new(path).into(),
+                    start: SourcePos {
+                        line: line_number,
+                        column: 0,
+                    },
+                    end: SourcePos {
+                        line: line_number,
+                        column: line.len(),
+                    },
+                }
+                .cell();
+                return Ok(Some((path, source)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Splits a dotenv line into the key and the unquoted value. References to
+/// other variables are not expanded, so values using them don't match the
+/// value that has been read.
+fn parse_assignment(line: &str) -> Option<(&str, &str)> {
+    let assignment = line.trim_start();
+    let assignment = assignment.strip_prefix("export ").unwrap_or(assignment);
+    let (key, value) = assignment.split_once('=')?;
+    let value = value.trim();
+    let value = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let quoted = &value[1..];
+            &quoted[..quoted.rfind(quote).unwrap_or(quoted.len())]
+        }
+        _ => value.split(" #").next().unwrap_or_default().trim_end(),
+    };
+    Some((key.trim(), value))
+}
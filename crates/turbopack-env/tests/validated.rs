@@ -0,0 +1,120 @@
+#![feature(min_specialization)]
+
+use indexmap::indexmap;
+use turbo_tasks::{TryJoinIterExt, TurboTasks};
+use turbo_tasks_env::{CustomProcessEnvVc, DotenvProcessEnvVc, EnvMapVc, ProcessEnv};
+use turbo_tasks_fs::{memory::MemoryFileSystemVc, FileSystem, FileSystemVc};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+use turbopack_core::issue::{IssueVc, OptionIssueProcessingPathItemsVc};
+use turbopack_env::ValidatedProcessEnvVc;
+
+register!();
+
+const SCHEMA: &str = r#"{
+  "variables": {
+    "APP_PORT": { "type": "number" },
+    "APP_URL": { "type": "url" },
+    "APP_MODE": { "type": "enum", "values": ["debug", "release"] },
+    "APP_NAME": { "required": true },
+    "APP_LEVEL": { "default": "info" }
+  }
+}"#;
+
+#[tokio::test]
+async fn reports_invalid_variables() {
+    lazy_static::initialize(&REGISTER);
+    turbopack_env::register();
+    let tt = TurboTasks::new(MemoryBackend::default());
+    let fs = tt
+        .run_once(async {
+            MemoryFileSystemVc::new("memory".to_string())
+                .resolve()
+                .await
+        })
+        .await
+        .unwrap();
+    let memory = tt.run_once(async move { fs.await }).await.unwrap();
+    memory.write_file("env.schema.json", SCHEMA).unwrap();
+    memory
+        .write_file(
+            ".env",
+            "# invalid values\nAPP_PORT=not-a-number\nexport \
+             APP_URL=\"relative/path\"\nAPP_MODE=fast # overridden by the process env\n",
+        )
+        .unwrap();
+
+    let (env, issues) = tt
+        .run_once(async move {
+            let root = FileSystemVc::from(fs).root();
+            let process_env = CustomProcessEnvVc::new(
+                EnvMapVc::empty().as_process_env(),
+                EnvMapVc::cell(indexmap! {
+                    "APP_MODE".to_string() => "slow".to_string(),
+                }),
+            )
+            .as_process_env();
+            let env =
+                DotenvProcessEnvVc::new(Some(process_env), root.join(".env")).as_process_env();
+            let env = ValidatedProcessEnvVc::new(
+                env,
+                root.join("env.schema.json"),
+                vec![root.join(".env.local"), root.join(".env")],
+            )
+            .as_process_env()
+            .read_all();
+
+            let captured = IssueVc::peek_issues_with_path(env)
+                .await?
+                .strongly_consistent()
+                .await?;
+            let mut issues = captured
+                .iter()
+                .map(|issue| async move {
+                    let issue = issue
+                        .into_plain(OptionIssueProcessingPathItemsVc::none())
+                        .await?;
+                    anyhow::Ok((
+                        issue.title.clone(),
+                        issue.context.clone(),
+                        issue.source.as_ref().map(|source| source.start.line),
+                    ))
+                })
+                .try_join()
+                .await?;
+            issues.sort();
+            Ok((env.await?.clone_value(), issues))
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(env.get("APP_LEVEL").map(String::as_str), Some("info"));
+    assert_eq!(env.get("APP_MODE").map(String::as_str), Some("slow"));
+    assert_eq!(
+        issues,
+        vec![
+            // The invalid value comes from the process env, not from the
+            // line in the .env file
+            (
+                "Invalid environment variable APP_MODE".to_string(),
+                "[memory]/env.schema.json".to_string(),
+                None
+            ),
+            (
+                "Invalid environment variable APP_PORT".to_string(),
+                "[memory]/.env".to_string(),
+                Some(1)
+            ),
+            (
+                "Invalid environment variable APP_URL".to_string(),
+                "[memory]/.env".to_string(),
+                Some(2)
+            ),
+            (
+                "Missing environment variable APP_NAME".to_string(),
+                "[memory]/env.schema.json".to_string(),
+                None
+            ),
+        ]
+    );
+}
@@ -7,7 +7,10 @@ use std::{
 
 use serde::Serialize;
 
-use crate::{AnchoredSystemPathBuf, IntoSystem, PathValidationError, RelativeSystemPathBuf};
+use crate::{
+    eq_ignore_case, AnchoredSystemPathBuf, Glob, IntoSystem, PathValidationError,
+    RelativeSystemPathBuf, Walk,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 pub struct AbsoluteSystemPathBuf(PathBuf);
@@ -117,6 +120,33 @@ impl AbsoluteSystemPathBuf {
         AbsoluteSystemPathBuf(self.0.join(path.as_path()))
     }
 
+    /// Recursively walks the directory at `self`, yielding every file and
+    /// directory below it anchored at `self`.
+    pub fn walk(&self) -> Walk {
+        Walk::new(self)
+    }
+
+    /// Walks the directory at `self`, yielding the anchored paths that match
+    /// any of the `include` globs and none of the `exclude` globs.
+    ///
+    /// # Arguments
+    ///
+    /// * `include`: Globs a path must match one of to be yielded
+    /// * `exclude`: Globs that filter out any path they match
+    ///
+    /// returns: Glob
+    pub fn glob(
+        &self,
+        include: impl IntoIterator<Item = impl Into<String>>,
+        exclude: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Glob {
+        Glob::new(
+            self,
+            include.into_iter().map(Into::into).collect(),
+            exclude.into_iter().map(Into::into).collect(),
+        )
+    }
+
     pub fn as_path(&self) -> &Path {
         self.0.as_path()
     }
@@ -164,6 +194,10 @@ impl AbsoluteSystemPathBuf {
     pub fn extension(&self) -> Option<&OsStr> {
         self.0.extension()
     }
+    /// Compares paths ignoring case, for use on case-insensitive filesystems.
+    pub fn eq_ignore_case(&self, other: &Self) -> bool {
+        eq_ignore_case(&self.0, &other.0)
+    }
 }
 
 impl Into<PathBuf> for AbsoluteSystemPathBuf {
@@ -1,8 +1,14 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fmt,
+    path::{Components, Path, PathBuf},
+};
 
 use serde::Serialize;
 
-use crate::{AbsoluteSystemPathBuf, IntoSystem, PathValidationError};
+use crate::{
+    clean_components, eq_ignore_case, AbsoluteSystemPathBuf, IntoSystem, PathValidationError,
+    RelativeUnixPathBuf,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 pub struct AnchoredSystemPathBuf(PathBuf);
@@ -33,10 +39,64 @@ impl AnchoredSystemPathBuf {
         Ok(AnchoredSystemPathBuf(stripped_path))
     }
 
+    /// Converts a unix path into a system path anchored at the same root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use turbopath::{AnchoredSystemPathBuf, RelativeUnixPathBuf};
+    /// let unix_path = RelativeUnixPathBuf::new("apps/web").unwrap();
+    /// let anchored_path = AnchoredSystemPathBuf::from_unix(&unix_path).unwrap();
+    /// #[cfg(windows)]
+    /// assert_eq!(anchored_path.as_path(), Path::new("apps\\web"));
+    /// #[cfg(not(windows))]
+    /// assert_eq!(anchored_path.as_path(), Path::new("apps/web"));
+    /// assert_eq!(anchored_path.to_unix().unwrap(), unix_path);
+    /// ```
+    pub fn from_unix(path: &RelativeUnixPathBuf) -> Result<Self, PathValidationError> {
+        path.as_path().try_into()
+    }
+
+    /// Converts `self` into a unix path, e.g. for use in globs or lockfiles.
+    pub fn to_unix(&self) -> Result<RelativeUnixPathBuf, PathValidationError> {
+        RelativeUnixPathBuf::new(self.0.as_path())
+    }
+
+    /// Collapses `.` and `..` components without touching the filesystem.
+    /// Errors if the path would resolve to somewhere outside of its anchor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use turbopath::AnchoredSystemPathBuf;
+    /// let path: AnchoredSystemPathBuf = Path::new("apps/./web/../docs").try_into().unwrap();
+    /// let expected: AnchoredSystemPathBuf = Path::new("apps/docs").try_into().unwrap();
+    /// assert_eq!(path.clean().unwrap(), expected);
+    ///
+    /// let escaping: AnchoredSystemPathBuf = Path::new("apps/../..").try_into().unwrap();
+    /// assert!(escaping.clean().is_err());
+    /// ```
+    pub fn clean(&self) -> Result<Self, PathValidationError> {
+        Ok(AnchoredSystemPathBuf(
+            clean_components(&self.0)?.into_iter().collect(),
+        ))
+    }
+
+    /// Compares paths ignoring case, for use on case-insensitive filesystems.
+    pub fn eq_ignore_case(&self, other: &Self) -> bool {
+        eq_ignore_case(&self.0, &other.0)
+    }
+
     pub fn as_path(&self) -> &Path {
         self.0.as_path()
     }
 
+    pub fn components(&self) -> Components<'_> {
+        self.0.components()
+    }
+
     pub fn to_str(&self) -> Result<&str, PathValidationError> {
         self.0
             .to_str()
@@ -49,3 +109,68 @@ impl Into<PathBuf> for AnchoredSystemPathBuf {
         self.0
     }
 }
+
+impl fmt::Display for AnchoredSystemPathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.display().fmt(f)
+    }
+}
+
+impl AsRef<Path> for AnchoredSystemPathBuf {
+    fn as_ref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{assert_matches::assert_matches, path::Path};
+
+    use crate::{AnchoredSystemPathBuf, PathValidationError, RelativeUnixPathBuf};
+
+    fn anchored(path: &str) -> AnchoredSystemPathBuf {
+        Path::new(path).try_into().unwrap()
+    }
+
+    #[test]
+    fn test_clean() {
+        let tests = [
+            ("apps/web", "apps/web"),
+            ("./apps/web/", "apps/web"),
+            ("apps/web/../docs", "apps/docs"),
+            ("apps/web/../../packages/ui/./src", "packages/ui/src"),
+            ("apps/..", ""),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(anchored(input).clean().unwrap(), anchored(expected));
+        }
+    }
+
+    #[test]
+    fn test_clean_refuses_to_escape() {
+        for input in ["..", "apps/../../web", "./../apps"] {
+            assert_matches!(
+                anchored(input).clean(),
+                Err(PathValidationError::EscapesAnchor(_))
+            );
+        }
+    }
+
+    #[test]
+    fn test_unix_round_trip() {
+        for input in ["apps/web", "packages/ui/src/index.ts", ""] {
+            let unix_path = RelativeUnixPathBuf::new(input).unwrap();
+            let anchored_path = AnchoredSystemPathBuf::from_unix(&unix_path).unwrap();
+            assert_eq!(anchored_path.to_unix().unwrap(), unix_path);
+        }
+    }
+
+    #[test]
+    fn test_eq_ignore_case() {
+        assert!(anchored("Apps/Web").eq_ignore_case(&anchored("apps/web")));
+        assert!(anchored("apps/web/").eq_ignore_case(&anchored("apps/web")));
+        assert!(anchored("ÄPPS/web").eq_ignore_case(&anchored("äpps/web")));
+        assert!(!anchored("apps/web").eq_ignore_case(&anchored("apps/docs")));
+        assert!(!anchored("apps/web").eq_ignore_case(&anchored("apps/web/src")));
+    }
+}
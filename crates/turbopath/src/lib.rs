@@ -4,8 +4,12 @@ mod absolute_system_path_buf;
 mod anchored_system_path_buf;
 mod relative_system_path_buf;
 mod relative_unix_path_buf;
+mod walk;
 
-use std::path::{Path, PathBuf};
+use std::{
+    ffi::OsStr,
+    path::{Component, Path, PathBuf},
+};
 
 pub use absolute_system_path_buf::AbsoluteSystemPathBuf;
 pub use anchored_system_path_buf::AnchoredSystemPathBuf;
@@ -13,6 +17,7 @@ use path_slash::{PathBufExt, PathExt};
 pub use relative_system_path_buf::RelativeSystemPathBuf;
 pub use relative_unix_path_buf::RelativeUnixPathBuf;
 use thiserror::Error;
+pub use walk::{Glob, Walk, WalkError};
 
 // Custom error type for path validation errors
 #[derive(Debug, Error)]
@@ -25,6 +30,8 @@ pub enum PathValidationError {
     NotRelative(PathBuf),
     #[error("Path {0} is not parent of {1}")]
     NotParent(String, String),
+    #[error("Path {0} escapes its anchor")]
+    EscapesAnchor(PathBuf),
 }
 
 trait IntoSystem {
@@ -57,3 +64,44 @@ impl IntoUnix for &Path {
         ))
     }
 }
+
+/// Collapses `.` and `..` components of a relative path, erroring if a `..`
+/// would climb above the start of the path.
+fn clean_components(path: &Path) -> Result<Vec<&OsStr>, PathValidationError> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if components.pop().is_none() {
+                    return Err(PathValidationError::EscapesAnchor(path.to_owned()));
+                }
+            }
+            Component::Normal(segment) => components.push(segment),
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(PathValidationError::NotRelative(path.to_owned()));
+            }
+        }
+    }
+
+    Ok(components)
+}
+
+/// Compares two paths component by component, ignoring case.
+fn eq_ignore_case(a: &Path, b: &Path) -> bool {
+    let mut a = a.components();
+    let mut b = b.components();
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(a), Some(b)) => {
+                let a = a.as_os_str().to_string_lossy();
+                let b = b.as_os_str().to_string_lossy();
+                if a != b && a.to_lowercase() != b.to_lowercase() {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
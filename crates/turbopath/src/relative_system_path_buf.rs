@@ -6,7 +6,7 @@ use std::{
 
 use serde::Serialize;
 
-use crate::{IntoSystem, PathValidationError};
+use crate::{eq_ignore_case, IntoSystem, PathValidationError};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 pub struct RelativeSystemPathBuf(PathBuf);
@@ -86,6 +86,10 @@ impl RelativeSystemPathBuf {
     pub fn extension(&self) -> Option<&OsStr> {
         self.0.extension()
     }
+    /// Compares paths ignoring case, for use on case-insensitive filesystems.
+    pub fn eq_ignore_case(&self, other: &Self) -> bool {
+        eq_ignore_case(&self.0, &other.0)
+    }
 }
 
 impl fmt::Display for RelativeSystemPathBuf {
@@ -1,11 +1,11 @@
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     path::{Components, Path, PathBuf},
 };
 
 use serde::Serialize;
 
-use crate::{IntoUnix, PathValidationError};
+use crate::{clean_components, eq_ignore_case, IntoUnix, PathValidationError};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 pub struct RelativeUnixPathBuf(PathBuf);
@@ -79,6 +79,25 @@ impl RelativeUnixPathBuf {
     pub fn into_path_buf(self) -> PathBuf {
         self.0
     }
+
+    /// Collapses `.` and `..` components, keeping `/` as the separator on
+    /// every platform. Errors if the path would escape its anchor.
+    pub fn clean(&self) -> Result<Self, PathValidationError> {
+        let mut cleaned = OsString::new();
+        for (i, segment) in clean_components(&self.0)?.into_iter().enumerate() {
+            if i > 0 {
+                cleaned.push("/");
+            }
+            cleaned.push(segment);
+        }
+
+        Ok(RelativeUnixPathBuf(cleaned.into()))
+    }
+
+    /// Compares paths ignoring case, for use on case-insensitive filesystems.
+    pub fn eq_ignore_case(&self, other: &Self) -> bool {
+        eq_ignore_case(&self.0, &other.0)
+    }
 }
 
 #[cfg(test)]
@@ -121,6 +140,23 @@ mod tests {
         assert!(RelativeUnixPathBuf::new(PathBuf::from("C:\\foo\\bar")).is_err());
     }
 
+    #[test]
+    fn test_relative_unix_path_buf_clean() {
+        let path = RelativeUnixPathBuf::new("apps/web/../../packages/./ui").unwrap();
+        assert_eq!(path.clean().unwrap().to_str().unwrap(), "packages/ui");
+        assert!(RelativeUnixPathBuf::new("apps/../../ui")
+            .unwrap()
+            .clean()
+            .is_err());
+    }
+
+    #[test]
+    fn test_relative_unix_path_buf_eq_ignore_case() {
+        let path = RelativeUnixPathBuf::new("Apps/Web").unwrap();
+        assert!(path.eq_ignore_case(&RelativeUnixPathBuf::new("apps/web").unwrap()));
+        assert!(!path.eq_ignore_case(&RelativeUnixPathBuf::new("apps/docs").unwrap()));
+    }
+
     #[cfg(windows)]
     #[test]
     fn test_convert_from_windows_path() {
@@ -0,0 +1,260 @@
+use thiserror::Error;
+use walkdir::WalkDir;
+
+use crate::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, PathValidationError};
+
+#[derive(Debug, Error)]
+pub enum WalkError {
+    #[error("Unable to walk directory: {0}")]
+    Io(#[from] walkdir::Error),
+    #[error(transparent)]
+    Path(#[from] PathValidationError),
+}
+
+/// Iterates over every file and directory below a root, in file name order.
+/// Paths are anchored at the root, which itself is not yielded.
+///
+/// Created by [`AbsoluteSystemPathBuf::walk`].
+pub struct Walk {
+    root: AbsoluteSystemPathBuf,
+    inner: walkdir::IntoIter,
+    current_is_dir: bool,
+}
+
+impl Walk {
+    pub(crate) fn new(root: &AbsoluteSystemPathBuf) -> Self {
+        let inner = WalkDir::new(root.as_path())
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter();
+        Walk {
+            root: root.clone(),
+            inner,
+            current_is_dir: false,
+        }
+    }
+
+    /// Stops the walk from descending into the most recently yielded path if
+    /// it is a directory.
+    pub fn skip_current_dir(&mut self) {
+        if self.current_is_dir {
+            self.inner.skip_current_dir();
+        }
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<AnchoredSystemPathBuf, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.inner.next()? {
+            Ok(entry) => entry,
+            Err(err) => return Some(Err(err.into())),
+        };
+        self.current_is_dir = entry.file_type().is_dir();
+
+        Some(
+            AbsoluteSystemPathBuf::new(entry.into_path())
+                .and_then(|path| self.root.anchor(&path))
+                .map_err(WalkError::from),
+        )
+    }
+}
+
+/// Iterates over the paths below a root that match at least one of the
+/// `include` globs and none of the `exclude` globs. Globs are matched against
+/// the unix form of the anchored path, so they behave the same on every
+/// platform.
+///
+/// Directories matched by the prefix of an exclude glob ending in `/**` are
+/// excluded themselves and not descended into, as nothing below them could be
+/// yielded.
+///
+/// Created by [`AbsoluteSystemPathBuf::glob`].
+pub struct Glob {
+    walk: Walk,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Glob {
+    pub(crate) fn new(
+        root: &AbsoluteSystemPathBuf,
+        include: Vec<String>,
+        exclude: Vec<String>,
+    ) -> Self {
+        Glob {
+            walk: Walk::new(root),
+            include,
+            exclude,
+        }
+    }
+
+    fn is_match(&self, path: &AnchoredSystemPathBuf) -> Result<bool, PathValidationError> {
+        let unix_path = path.to_unix()?;
+        let unix_path = unix_path.to_str()?;
+        let matches = |globs: &[String]| {
+            globs
+                .iter()
+                .any(|glob| glob_match::glob_match(glob, unix_path))
+        };
+
+        Ok(matches(&self.include) && !matches(&self.exclude))
+    }
+
+    fn is_excluded_dir(&self, path: &AnchoredSystemPathBuf) -> Result<bool, PathValidationError> {
+        let unix_path = path.to_unix()?;
+        let unix_path = unix_path.to_str()?;
+        Ok(self.exclude.iter().any(|glob| {
+            glob.strip_suffix("/**")
+                .map_or(false, |prefix| glob_match::glob_match(prefix, unix_path))
+        }))
+    }
+}
+
+impl Iterator for Glob {
+    type Item = Result<AnchoredSystemPathBuf, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let path = match self.walk.next()? {
+                Ok(path) => path,
+                Err(err) => return Some(Err(err)),
+            };
+            if self.walk.current_is_dir {
+                match self.is_excluded_dir(&path) {
+                    Ok(true) => {
+                        self.walk.skip_current_dir();
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(err) => return Some(Err(err.into())),
+                }
+            }
+            match self.is_match(&path) {
+                Ok(true) => return Some(Ok(path)),
+                Ok(false) => continue,
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+
+    fn setup() -> (tempfile::TempDir, AbsoluteSystemPathBuf) {
+        let tmp = tempfile::tempdir().unwrap();
+        for file in [
+            "package.json",
+            "apps/web/package.json",
+            "apps/web/src/index.ts",
+            "apps/docs/package.json",
+            "node_modules/lodash/package.json",
+        ] {
+            let path = tmp.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "{}").unwrap();
+        }
+        let root = AbsoluteSystemPathBuf::new(tmp.path()).unwrap();
+        (tmp, root)
+    }
+
+    fn to_unix(paths: Vec<AnchoredSystemPathBuf>) -> Vec<String> {
+        paths
+            .into_iter()
+            .map(|path| path.to_unix().unwrap().to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_walk() {
+        let (_tmp, root) = setup();
+        let paths = root.walk().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            to_unix(paths),
+            vec![
+                "apps",
+                "apps/docs",
+                "apps/docs/package.json",
+                "apps/web",
+                "apps/web/package.json",
+                "apps/web/src",
+                "apps/web/src/index.ts",
+                "node_modules",
+                "node_modules/lodash",
+                "node_modules/lodash/package.json",
+                "package.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_resolves_to_root() {
+        let (_tmp, root) = setup();
+        for path in root.walk() {
+            assert!(root.resolve(&path.unwrap()).exists());
+        }
+    }
+
+    #[test]
+    fn test_glob() {
+        let (_tmp, root) = setup();
+        let paths = root
+            .glob(["**/package.json"], ["node_modules/**"])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            to_unix(paths),
+            vec![
+                "apps/docs/package.json",
+                "apps/web/package.json",
+                "package.json"
+            ]
+        );
+
+        let paths = root
+            .glob(["**"], ["**/node_modules/**", "apps/**"])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(to_unix(paths), vec!["package.json"]);
+
+        let paths = root
+            .glob(["apps/*"], Vec::<String>::new())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected: Vec<_> = ["apps/docs", "apps/web"]
+            .into_iter()
+            .map(|path| {
+                AnchoredSystemPathBuf::from_unix(&RelativeUnixPathBuf::new(path).unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(paths, expected);
+        assert_eq!(paths[1].as_path(), Path::new("apps").join("web"));
+    }
+
+    #[test]
+    fn test_glob_skips_excluded_dirs() {
+        let (_tmp, root) = setup();
+        let paths = root
+            .glob(["**"], ["node_modules/**"])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let paths = to_unix(paths);
+        assert!(paths.iter().all(|path| !path.starts_with("node_modules")));
+        assert!(paths.contains(&"apps/web/src/index.ts".to_string()));
+
+        // nested excluded directories are skipped at any depth
+        let paths = root
+            .glob(["**"], ["**/src/**"])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let paths = to_unix(paths);
+        assert!(paths.contains(&"apps/web/package.json".to_string()));
+        assert!(!paths.contains(&"apps/web/src".to_string()));
+        assert!(!paths.contains(&"apps/web/src/index.ts".to_string()));
+    }
+}
@@ -1,10 +1,10 @@
-use std::{env, future::Future};
+use std::{env, future::Future, time::Duration};
 
 use anyhow::{anyhow, Result};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
-use crate::retry::retry_future;
+use crate::retry::{poll_future, retry_future, Poll};
 
 mod retry;
 
@@ -21,6 +21,37 @@ pub struct VerificationResponse {
     pub team_id: Option<String>,
 }
 
+/// Response to starting a device authorization, see
+/// https://www.rfc-editor.org/rfc/rfc8628#section-3.2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    pub interval: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceTokenResponse {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceTokenError {
+    AuthorizationPending,
+    SlowDown,
+    AccessDenied,
+    ExpiredToken,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceTokenErrorResponse {
+    pub error: DeviceTokenError,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CachingStatus {
@@ -241,6 +272,78 @@ impl APIClient {
         })
     }
 
+    /// Starts a device authorization. The user has to visit the returned
+    /// verification uri and enter the user code, while the CLI polls
+    /// `poll_device_token` with the device code.
+    pub async fn create_device_code(&self, token_name: &str) -> Result<DeviceCodeResponse> {
+        let response = self
+            .make_retryable_request(|| {
+                let request_builder = self
+                    .client
+                    .post(self.make_url("/registration/device/code"))
+                    .query(&[("tokenName", token_name)])
+                    .header("User-Agent", self.user_agent.clone());
+
+                request_builder.send()
+            })
+            .await?
+            .error_for_status()?;
+
+        response.json().await.map_err(|err| {
+            anyhow!(
+                "Error starting device authorization: {}",
+                err.status()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or(&err.to_string())
+            )
+        })
+    }
+
+    /// Polls for the token of a device authorization until the user approves
+    /// or denies it, or `expires_in` has elapsed.
+    pub async fn poll_device_token(
+        &self,
+        device_code: &str,
+        interval: Duration,
+        expires_in: Duration,
+    ) -> Result<String> {
+        poll_future(
+            interval,
+            expires_in,
+            || self.get_device_token(device_code),
+            |err| {
+                err.downcast_ref::<reqwest::Error>()
+                    .map_or(false, Self::should_retry_request)
+            },
+        )
+        .await
+    }
+
+    async fn get_device_token(&self, device_code: &str) -> Result<Poll<String>> {
+        let response = self
+            .client
+            .post(self.make_url("/registration/device/token"))
+            .query(&[("deviceCode", device_code)])
+            .header("User-Agent", self.user_agent.clone())
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::BAD_REQUEST {
+            let error_response: DeviceTokenErrorResponse = response.json().await?;
+            return match error_response.error {
+                DeviceTokenError::AuthorizationPending => Ok(Poll::Pending),
+                DeviceTokenError::SlowDown => Ok(Poll::SlowDown),
+                DeviceTokenError::AccessDenied => Err(anyhow!("authorization was denied")),
+                DeviceTokenError::ExpiredToken => {
+                    Err(anyhow!("device code expired, please try again"))
+                }
+            };
+        }
+
+        let token_response: DeviceTokenResponse = response.error_for_status()?.json().await?;
+        Ok(Poll::Ready(token_response.token))
+    }
+
     const RETRY_MAX: u32 = 2;
 
     async fn make_retryable_request<
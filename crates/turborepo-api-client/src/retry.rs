@@ -1,10 +1,13 @@
-use std::future::Future;
+use std::{future::Future, time::Duration};
 
 use anyhow::anyhow;
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
 
 const MIN_SLEEP_TIME_SECS: u64 = 2;
 const MAX_SLEEP_TIME_SECS: u64 = 10;
+/// How much to increase the polling interval by when the server asks us to
+/// slow down. See https://www.rfc-editor.org/rfc/rfc8628#section-3.5
+const SLOW_DOWN_INCREMENT: Duration = Duration::from_secs(5);
 
 /// Retries a future until `max_retries` is reached, the `should_retry` function
 /// returns false, or the future succeeds. Uses an exponential backoff with a
@@ -47,3 +50,133 @@ pub async fn retry_future<T, E: Into<anyhow::Error>, F: Future<Output = Result<T
         last_error.unwrap().into()
     ))
 }
+
+/// The outcome of a single attempt made by [`poll_future`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Poll<T> {
+    /// The operation completed with a value
+    Ready(T),
+    /// The operation is still pending, poll again after the current interval
+    Pending,
+    /// The operation is still pending and the interval should be increased
+    SlowDown,
+}
+
+/// Polls a future until it is ready or `timeout` has elapsed. Waits
+/// `interval` between attempts, increasing it whenever the poll asks us to
+/// slow down. Errors for which `should_retry` returns true are retried with
+/// the same exponential backoff as [`retry_future`], any other error is
+/// returned immediately.
+///
+/// # Arguments
+///
+/// * `interval`: Initial delay between polls
+/// * `timeout`: Total time to wait before giving up
+/// * `future_generator`: Function to call to generate the future for each poll
+/// * `should_retry`: Determines if an error is transient
+///
+/// returns: Result<T, Error>
+pub async fn poll_future<T, E: Into<anyhow::Error>, F: Future<Output = Result<Poll<T>, E>>>(
+    mut interval: Duration,
+    timeout: Duration,
+    future_generator: impl Fn() -> F,
+    should_retry: impl Fn(&E) -> bool,
+) -> Result<T, anyhow::Error> {
+    let deadline = Instant::now() + timeout;
+    let mut failure_count = 0;
+    loop {
+        let sleep_period = match future_generator().await {
+            Ok(Poll::Ready(value)) => return Ok(value),
+            Ok(Poll::Pending) => {
+                failure_count = 0;
+                interval
+            }
+            Ok(Poll::SlowDown) => {
+                failure_count = 0;
+                interval += SLOW_DOWN_INCREMENT;
+                interval
+            }
+            Err(err) if should_retry(&err) => {
+                let backoff = Duration::from_secs(
+                    (2_u64)
+                        .pow(failure_count)
+                        .clamp(MIN_SLEEP_TIME_SECS, MAX_SLEEP_TIME_SECS),
+                );
+                failure_count += 1;
+                interval.max(backoff)
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if Instant::now() + sleep_period >= deadline {
+            return Err(anyhow!("timed out after {}s", timeout.as_secs()));
+        }
+        sleep(sleep_period).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use anyhow::anyhow;
+
+    use super::{poll_future, Poll};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_until_ready() {
+        let attempts = AtomicU32::new(0);
+        let result = poll_future(
+            Duration::from_secs(1),
+            Duration::from_secs(60),
+            || async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 => Ok(Poll::Pending),
+                    1 => Ok(Poll::SlowDown),
+                    2 => Err(anyhow!("transient")),
+                    _ => Ok(Poll::Ready("done")),
+                }
+            },
+            |_| true,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, "done");
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_times_out() {
+        let result = poll_future(
+            Duration::from_secs(5),
+            Duration::from_secs(12),
+            || async { Ok::<Poll<()>, anyhow::Error>(Poll::SlowDown) },
+            |_| true,
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_stops_on_fatal_error() {
+        let attempts = AtomicU32::new(0);
+        let result = poll_future(
+            Duration::from_secs(1),
+            Duration::from_secs(60),
+            || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err::<Poll<()>, _>(anyhow!("denied"))
+            },
+            |_| false,
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -6,7 +6,10 @@ mod lockfile;
 
 use std::{mem::ManuallyDrop, path::PathBuf};
 
-pub use lockfile::{npm_subgraph, npm_transitive_closure};
+pub use lockfile::{
+    lockfile_encode, lockfile_parse, lockfile_subgraph, lockfile_transitive_closure, npm_subgraph,
+    npm_transitive_closure,
+};
 
 mod proto {
     include!(concat!(env!("OUT_DIR"), "/_.rs"));
@@ -22,6 +22,50 @@ enum Error {
     LockfileError(#[from] turborepo_lockfiles::Error),
     #[error("error decoding protobuf")]
     ProtobufError(#[from] prost::DecodeError),
+    #[error("unsupported lockfile kind: {0}")]
+    UnsupportedKind(i32),
+}
+
+impl From<Error> for proto::LockfileError {
+    fn from(value: Error) -> Self {
+        use proto::LockfileErrorCode as Code;
+        use turborepo_lockfiles::Error as LockfileError;
+
+        let (code, message) = match value {
+            Error::LockfileError(err) => {
+                let code = match &err {
+                    LockfileError::MissingWorkspace(_) => Code::MissingWorkspace,
+                    LockfileError::MissingPackage(_) => Code::MissingPackage,
+                    LockfileError::MissingVersion(_) => Code::MissingVersion,
+                    LockfileError::JsonError(_) => Code::InvalidLockfile,
+                    LockfileError::UnsupportedNpmVersion => Code::UnsupportedLockfileVersion,
+                };
+                (code, err.to_string())
+            }
+            Error::ProtobufError(err) => (Code::InvalidRequest, err.to_string()),
+            err @ Error::UnsupportedKind(_) => (Code::UnsupportedLockfile, err.to_string()),
+        };
+        proto::LockfileError {
+            code: code as i32,
+            message,
+        }
+    }
+}
+
+fn load_lockfile(kind: i32, contents: &[u8]) -> Result<NpmLockfile, Error> {
+    match proto::LockfileKind::from_i32(kind) {
+        Some(proto::LockfileKind::Npm) => Ok(NpmLockfile::load(contents)?),
+        None => Err(Error::UnsupportedKind(kind)),
+    }
+}
+
+fn package_list(packages: impl IntoIterator<Item = Package>) -> proto::LockfilePackageList {
+    let mut list: Vec<_> = packages
+        .into_iter()
+        .map(proto::LockfilePackage::from)
+        .collect();
+    list.sort_by(|a, b| a.key.cmp(&b.key));
+    proto::LockfilePackageList { list }
 }
 
 #[no_mangle]
@@ -67,3 +111,255 @@ fn npm_subgraph_inner(buf: Buffer) -> Result<Vec<u8>, Error> {
     let contents = real_npm_subgraph(&request.contents, &request.workspaces, &request.packages)?;
     Ok(contents)
 }
+
+#[no_mangle]
+pub extern "C" fn lockfile_parse(buf: Buffer) -> Buffer {
+    use proto::lockfile_parse_response::Response;
+    proto::LockfileParseResponse {
+        response: Some(match lockfile_parse_inner(buf) {
+            Ok(list) => Response::Packages(list),
+            Err(err) => Response::Error(err.into()),
+        }),
+    }
+    .into()
+}
+
+fn lockfile_parse_inner(buf: Buffer) -> Result<proto::LockfilePackageList, Error> {
+    let request: proto::LockfileParseRequest = buf.into_proto()?;
+    let lockfile = load_lockfile(request.kind, &request.contents)?;
+    Ok(package_list(lockfile.packages()))
+}
+
+#[no_mangle]
+pub extern "C" fn lockfile_transitive_closure(buf: Buffer) -> Buffer {
+    use proto::lockfile_transitive_closure_response::Response;
+    proto::LockfileTransitiveClosureResponse {
+        response: Some(match lockfile_transitive_closure_inner(buf) {
+            Ok(list) => Response::Packages(list),
+            Err(err) => Response::Error(err.into()),
+        }),
+    }
+    .into()
+}
+
+fn lockfile_transitive_closure_inner(buf: Buffer) -> Result<proto::LockfilePackageList, Error> {
+    let request: proto::LockfileTransitiveClosureRequest = buf.into_proto()?;
+    let lockfile = load_lockfile(request.kind, &request.contents)?;
+    let transitive_deps =
+        transitive_closure(&lockfile, request.workspace_dir, request.unresolved_deps)?;
+    Ok(package_list(transitive_deps))
+}
+
+#[no_mangle]
+pub extern "C" fn lockfile_subgraph(buf: Buffer) -> Buffer {
+    contents_response(lockfile_subgraph_inner(buf))
+}
+
+fn lockfile_subgraph_inner(buf: Buffer) -> Result<Vec<u8>, Error> {
+    let request: proto::LockfileSubgraphRequest = buf.into_proto()?;
+    let lockfile = load_lockfile(request.kind, &request.contents)?;
+    let subgraph = lockfile.subgraph(&request.workspaces, &request.packages)?;
+    Ok(subgraph.encode()?)
+}
+
+#[no_mangle]
+pub extern "C" fn lockfile_encode(buf: Buffer) -> Buffer {
+    contents_response(lockfile_encode_inner(buf))
+}
+
+fn lockfile_encode_inner(buf: Buffer) -> Result<Vec<u8>, Error> {
+    let request: proto::LockfileEncodeRequest = buf.into_proto()?;
+    let lockfile = load_lockfile(request.kind, &request.contents)?;
+    Ok(lockfile.encode()?)
+}
+
+fn contents_response(result: Result<Vec<u8>, Error>) -> Buffer {
+    use proto::lockfile_contents_response::Response;
+    proto::LockfileContentsResponse {
+        response: Some(match result {
+            Ok(contents) => Response::Contents(contents),
+            Err(err) => Response::Error(err.into()),
+        }),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::free_buffer;
+
+    const NPM_LOCKFILE: &[u8] = include_bytes!("../../turborepo-lockfiles/fixtures/npm-lock.json");
+
+    // Mimics a foreign caller: encode the request, invoke the exported function
+    // and decode the response, freeing both buffers afterwards.
+    fn call<Req: prost::Message, Resp: prost::Message + Default>(
+        func: extern "C" fn(Buffer) -> Buffer,
+        request: Req,
+    ) -> Resp {
+        let request = Buffer::from(request);
+        let (len, data) = (request.len, request.data);
+        let response = func(request);
+        free_buffer(Buffer { len, data });
+
+        let (len, data) = (response.len, response.data);
+        let decoded = response.into_proto().expect("response should decode");
+        free_buffer(Buffer { len, data });
+        decoded
+    }
+
+    fn npm_contents(response: proto::LockfileContentsResponse) -> Vec<u8> {
+        match response.response {
+            Some(proto::lockfile_contents_response::Response::Contents(contents)) => contents,
+            other => panic!("expected contents, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let response: proto::LockfileParseResponse = call(
+            lockfile_parse,
+            proto::LockfileParseRequest {
+                kind: proto::LockfileKind::Npm as i32,
+                contents: NPM_LOCKFILE.to_vec(),
+            },
+        );
+        let Some(proto::lockfile_parse_response::Response::Packages(packages)) = response.response
+        else {
+            panic!("expected packages, got {:?}", response)
+        };
+
+        let expected = package_list(NpmLockfile::load(NPM_LOCKFILE).unwrap().packages());
+        assert_eq!(packages, expected);
+        assert!(packages
+            .list
+            .iter()
+            .any(|pkg| pkg.key == "node_modules/turbo"));
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let encode = |contents: Vec<u8>| -> Vec<u8> {
+            npm_contents(call(
+                lockfile_encode,
+                proto::LockfileEncodeRequest {
+                    kind: proto::LockfileKind::Npm as i32,
+                    contents,
+                },
+            ))
+        };
+
+        let encoded = encode(NPM_LOCKFILE.to_vec());
+        assert_eq!(encode(encoded.clone()), encoded);
+    }
+
+    #[test]
+    fn test_transitive_closure() {
+        let response: proto::LockfileTransitiveClosureResponse = call(
+            lockfile_transitive_closure,
+            proto::LockfileTransitiveClosureRequest {
+                kind: proto::LockfileKind::Npm as i32,
+                contents: NPM_LOCKFILE.to_vec(),
+                workspace_dir: "apps/web".into(),
+                unresolved_deps: HashMap::from([("lodash".into(), "^4.17.21".into())]),
+            },
+        );
+        let Some(proto::lockfile_transitive_closure_response::Response::Packages(packages)) =
+            response.response
+        else {
+            panic!("expected packages, got {:?}", response)
+        };
+
+        assert_eq!(
+            packages.list,
+            vec![proto::LockfilePackage {
+                key: "apps/web/node_modules/lodash".into(),
+                version: "4.17.21".into(),
+                found: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_subgraph_round_trip() {
+        let workspaces = vec!["apps/web".to_string()];
+        let packages = vec![
+            "apps/web/node_modules/lodash".to_string(),
+            "node_modules/turbo".to_string(),
+        ];
+        let subgraph = npm_contents(call(
+            lockfile_subgraph,
+            proto::LockfileSubgraphRequest {
+                kind: proto::LockfileKind::Npm as i32,
+                contents: NPM_LOCKFILE.to_vec(),
+                workspaces: workspaces.clone(),
+                packages: packages.clone(),
+            },
+        ));
+        assert_eq!(
+            subgraph,
+            real_npm_subgraph(NPM_LOCKFILE, &workspaces, &packages).unwrap()
+        );
+
+        let response: proto::LockfileParseResponse = call(
+            lockfile_parse,
+            proto::LockfileParseRequest {
+                kind: proto::LockfileKind::Npm as i32,
+                contents: subgraph,
+            },
+        );
+        let Some(proto::lockfile_parse_response::Response::Packages(parsed)) = response.response
+        else {
+            panic!("expected packages, got {:?}", response)
+        };
+        let keys: Vec<_> = parsed.list.iter().map(|pkg| pkg.key.as_str()).collect();
+        assert!(keys.contains(&"apps/web/node_modules/lodash"));
+        assert!(keys.contains(&"node_modules/turbo"));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        use proto::{lockfile_contents_response::Response, LockfileErrorCode as Code};
+
+        let tests = [
+            (i32::MAX, NPM_LOCKFILE.to_vec(), Code::UnsupportedLockfile),
+            (
+                proto::LockfileKind::Npm as i32,
+                b"not json".to_vec(),
+                Code::InvalidLockfile,
+            ),
+            (
+                proto::LockfileKind::Npm as i32,
+                br#"{"lockfileVersion": 1, "packages": {}}"#.to_vec(),
+                Code::UnsupportedLockfileVersion,
+            ),
+        ];
+        for (kind, contents, expected) in tests {
+            let response: proto::LockfileContentsResponse = call(
+                lockfile_encode,
+                proto::LockfileEncodeRequest { kind, contents },
+            );
+            let Some(Response::Error(err)) = response.response else {
+                panic!("expected error, got {:?}", response)
+            };
+            assert_eq!(err.code, expected as i32);
+        }
+
+        let response: proto::LockfileContentsResponse = call(
+            lockfile_subgraph,
+            proto::LockfileSubgraphRequest {
+                kind: proto::LockfileKind::Npm as i32,
+                contents: NPM_LOCKFILE.to_vec(),
+                workspaces: vec!["apps/missing".into()],
+                packages: vec![],
+            },
+        );
+        let Some(Response::Error(err)) = response.response else {
+            panic!("expected error, got {:?}", response)
+        };
+        assert_eq!(err.code, Code::MissingPackage as i32);
+        assert_eq!(err.message, "No lockfile entry found for 'apps/missing'");
+    }
+}
@@ -13,7 +13,7 @@ use log::{debug, error};
 use serde::Serialize;
 
 use crate::{
-    commands::{bin, daemon, link, login, logout, unlink, CommandBase},
+    commands::{bin, daemon, generate, link, link::LinkMode, login, logout, unlink, CommandBase},
     get_version,
     shim::{RepoMode, RepoState},
     ui::UI,
@@ -177,6 +177,33 @@ pub enum DaemonCommand {
     Stop,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, ValueEnum)]
+pub enum WorkspaceType {
+    App,
+    Package,
+}
+
+#[derive(Subcommand, Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "command")]
+pub enum GenerateCommand {
+    /// Add a new app or package to your monorepo
+    Workspace {
+        /// Name of the new workspace, used for its package.json name
+        #[clap(long)]
+        name: String,
+        /// Whether the new workspace is an app or a package
+        #[clap(long = "type", value_enum, default_value_t = WorkspaceType::Package)]
+        workspace_type: WorkspaceType,
+        /// Name or path of an existing workspace to copy
+        #[clap(long)]
+        copy: Option<String>,
+        /// Print the file operations that would be performed without
+        /// performing them
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
 impl Args {
     pub fn new() -> Result<Self> {
         let mut clap_args = match Args::try_parse() {
@@ -244,17 +271,34 @@ pub enum Command {
         #[serde(flatten)]
         command: Option<DaemonCommand>,
     },
+    /// Generate a new app or package in your monorepo
+    Generate {
+        #[clap(subcommand)]
+        #[serde(flatten)]
+        command: GenerateCommand,
+    },
     /// Link your local directory to a Vercel organization and enable remote
     /// caching.
     Link {
         /// Do not create or modify .gitignore (default false)
         #[clap(long)]
         no_gitignore: bool,
+        /// Do not prompt. Links the team given by --team, or your personal
+        /// account if --team is not set
+        #[clap(long, short)]
+        yes: bool,
+        /// Do not prompt and report the result as JSON. Implies --yes
+        #[clap(long)]
+        json: bool,
     },
     /// Login to your Vercel account
     Login {
         #[clap(long = "sso-team")]
         sso_team: Option<String>,
+        /// Login by entering a code on another device instead of opening a
+        /// browser. Useful over SSH or in containers
+        #[clap(long, conflicts_with = "sso_team")]
+        device: bool,
     },
     /// Logout to your Vercel account
     Logout {},
@@ -488,38 +532,87 @@ pub async fn run(repo_state: Option<RepoState>) -> Result<Payload> {
 
             Ok(Payload::Rust(Ok(0)))
         }
-        Command::Login { sso_team } => {
+        Command::Login { sso_team, device } => {
             if clap_args.test_run {
                 println!("Login test run successful");
                 return Ok(Payload::Rust(Ok(0)));
             }
 
             let sso_team = sso_team.clone();
+            let device = *device;
 
             let mut base = CommandBase::new(clap_args, repo_root, version)?;
 
             if let Some(sso_team) = sso_team {
                 login::sso_login(&mut base, &sso_team).await?;
+            } else if device {
+                login::device_login(&mut base).await?;
             } else {
                 login::login(&mut base).await?;
             }
 
             Ok(Payload::Rust(Ok(0)))
         }
-        Command::Link { no_gitignore } => {
+        Command::Generate {
+            command:
+                GenerateCommand::Workspace {
+                    name,
+                    workspace_type,
+                    copy,
+                    dry_run,
+                },
+        } => {
+            if clap_args.test_run {
+                println!("Generate test run successful");
+                return Ok(Payload::Rust(Ok(0)));
+            }
+
+            let options = generate::WorkspaceOptions {
+                name: name.clone(),
+                workspace_type: *workspace_type,
+                copy: copy.clone(),
+                dry_run: *dry_run,
+            };
+            let base = CommandBase::new(clap_args, repo_root, version)?;
+            generate::workspace(&base, &options)?;
+
+            Ok(Payload::Rust(Ok(0)))
+        }
+        Command::Link {
+            no_gitignore,
+            yes,
+            json,
+        } => {
             if clap_args.test_run {
                 println!("Link test run successful");
                 return Ok(Payload::Rust(Ok(0)));
             }
 
             let modify_gitignore = !*no_gitignore;
-            let mut base = CommandBase::new(clap_args, repo_root, version)?;
-
-            if let Err(err) = link::link(&mut base, modify_gitignore).await {
-                error!("error: {}", err.to_string())
+            let mode = if *yes || *json {
+                LinkMode::NonInteractive { json: *json }
+            } else {
+                LinkMode::Interactive
             };
+            let mut base = CommandBase::new(clap_args, repo_root, version)?;
 
-            Ok(Payload::Rust(Ok(0)))
+            match link::link(&mut base, modify_gitignore, mode).await {
+                Ok(()) => Ok(Payload::Rust(Ok(0))),
+                Err(err) if mode.is_json() => {
+                    link::print_json_error(&err)?;
+                    Ok(Payload::Rust(Ok(err.exit_code())))
+                }
+                Err(err) => {
+                    error!("error: {}", err.to_string());
+                    // Interactive linking has always exited successfully, scripts rely on the
+                    // exit code only in non-interactive mode
+                    let exit_code = match mode {
+                        LinkMode::Interactive => 0,
+                        LinkMode::NonInteractive { .. } => err.exit_code(),
+                    };
+                    Ok(Payload::Rust(Ok(exit_code)))
+                }
+            }
         }
         Command::Unlink { .. } => {
             if clap_args.test_run {
@@ -623,7 +716,10 @@ mod test {
 
     use anyhow::Result;
 
-    use crate::cli::{Args, Command, DryRunMode, EnvMode, OutputLogsMode, RunArgs, Verbosity};
+    use crate::cli::{
+        Args, Command, DryRunMode, EnvMode, GenerateCommand, OutputLogsMode, RunArgs, Verbosity,
+        WorkspaceType,
+    };
 
     #[test]
     fn test_parse_run() -> Result<()> {
@@ -1147,12 +1243,93 @@ mod test {
         .test();
     }
 
+    #[test]
+    fn test_parse_link() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "link"]).unwrap(),
+            Args {
+                command: Some(Command::Link {
+                    no_gitignore: false,
+                    yes: false,
+                    json: false,
+                }),
+                ..Args::default()
+            }
+        );
+
+        CommandTestCase {
+            command: "link",
+            command_args: vec![vec!["--yes"], vec!["--json"]],
+            global_args: vec![vec!["--team", "my-team"]],
+            expected_output: Args {
+                command: Some(Command::Link {
+                    no_gitignore: false,
+                    yes: true,
+                    json: true,
+                }),
+                team: Some("my-team".to_string()),
+                ..Args::default()
+            },
+        }
+        .test();
+    }
+
+    #[test]
+    fn test_parse_generate() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "generate", "workspace", "--name", "ui"]).unwrap(),
+            Args {
+                command: Some(Command::Generate {
+                    command: GenerateCommand::Workspace {
+                        name: "ui".to_string(),
+                        workspace_type: WorkspaceType::Package,
+                        copy: None,
+                        dry_run: false,
+                    }
+                }),
+                ..Args::default()
+            }
+        );
+
+        assert_eq!(
+            Args::try_parse_from([
+                "turbo",
+                "generate",
+                "workspace",
+                "--name",
+                "admin",
+                "--type",
+                "app",
+                "--copy",
+                "web",
+                "--dry-run"
+            ])
+            .unwrap(),
+            Args {
+                command: Some(Command::Generate {
+                    command: GenerateCommand::Workspace {
+                        name: "admin".to_string(),
+                        workspace_type: WorkspaceType::App,
+                        copy: Some("web".to_string()),
+                        dry_run: true,
+                    }
+                }),
+                ..Args::default()
+            }
+        );
+
+        assert!(Args::try_parse_from(["turbo", "generate", "workspace"]).is_err());
+    }
+
     #[test]
     fn test_parse_login() {
         assert_eq!(
             Args::try_parse_from(["turbo", "login"]).unwrap(),
             Args {
-                command: Some(Command::Login { sso_team: None }),
+                command: Some(Command::Login {
+                    sso_team: None,
+                    device: false,
+                }),
                 ..Args::default()
             }
         );
@@ -1162,7 +1339,10 @@ mod test {
             command_args: vec![],
             global_args: vec![vec!["--cwd", "../examples/with-yarn"]],
             expected_output: Args {
-                command: Some(Command::Login { sso_team: None }),
+                command: Some(Command::Login {
+                    sso_team: None,
+                    device: false,
+                }),
                 cwd: Some(PathBuf::from("../examples/with-yarn")),
                 ..Args::default()
             },
@@ -1176,12 +1356,32 @@ mod test {
             expected_output: Args {
                 command: Some(Command::Login {
                     sso_team: Some("my-team".to_string()),
+                    device: false,
                 }),
                 cwd: Some(PathBuf::from("../examples/with-yarn")),
                 ..Args::default()
             },
         }
         .test();
+
+        CommandTestCase {
+            command: "login",
+            command_args: vec![vec!["--device"]],
+            global_args: vec![vec!["--cwd", "../examples/with-yarn"]],
+            expected_output: Args {
+                command: Some(Command::Login {
+                    sso_team: None,
+                    device: true,
+                }),
+                cwd: Some(PathBuf::from("../examples/with-yarn")),
+                ..Args::default()
+            },
+        }
+        .test();
+
+        assert!(
+            Args::try_parse_from(["turbo", "login", "--device", "--sso-team", "my-team"]).is_err()
+        );
     }
 
     #[test]
@@ -0,0 +1,622 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+
+use super::CommandBase;
+use crate::{
+    cli::WorkspaceType,
+    package_manager::{Globs, PackageManager},
+    ui::{BOLD, GREY},
+};
+
+/// Never copied into a new workspace, in addition to the pipeline outputs
+/// declared in turbo.json
+const ALWAYS_EXCLUDED: [&str; 3] = ["node_modules/**", "**/node_modules/**", ".turbo/**"];
+const DEPENDENCY_FIELDS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+const WORKSPACE_PROTOCOL: &str = "workspace:";
+const INITIAL_VERSION: &str = "0.0.0";
+
+pub struct WorkspaceOptions {
+    pub name: String,
+    pub workspace_type: WorkspaceType,
+    pub copy: Option<String>,
+    pub dry_run: bool,
+}
+
+/// A single step of generating a workspace. Paths are anchored at the repo
+/// root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOperation {
+    CreateDir(AnchoredSystemPathBuf),
+    Copy {
+        from: AnchoredSystemPathBuf,
+        to: AnchoredSystemPathBuf,
+    },
+    Write {
+        path: AnchoredSystemPathBuf,
+        contents: String,
+    },
+}
+
+impl FileOperation {
+    fn apply(&self, root: &AbsoluteSystemPathBuf) -> Result<()> {
+        match self {
+            FileOperation::CreateDir(path) => fs::create_dir_all(root.resolve(path))?,
+            FileOperation::Copy { from, to } => {
+                let to = root.resolve(to);
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(root.resolve(from), to)?;
+            }
+            FileOperation::Write { path, contents } => fs::write(root.resolve(path), contents)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for FileOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileOperation::CreateDir(path) => write!(f, "create {}", path),
+            FileOperation::Copy { from, to } => write!(f, "copy   {} -> {}", from, to),
+            FileOperation::Write { path, .. } => write!(f, "write  {}", path),
+        }
+    }
+}
+
+/// A JSON value that keeps the order of object keys, so that rewriting a
+/// package.json doesn't reorder its fields.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum OrderedJson {
+    Object(IndexMap<String, OrderedJson>),
+    Array(Vec<OrderedJson>),
+    Other(Value),
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageName {
+    name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TurboJson {
+    #[serde(default)]
+    pipeline: BTreeMap<String, PipelineTask>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PipelineTask {
+    #[serde(default)]
+    outputs: Vec<String>,
+}
+
+/// Creates a new workspace, or prints what would be created if `dry_run` is
+/// set.
+pub fn workspace(base: &CommandBase, options: &WorkspaceOptions) -> Result<()> {
+    let root = AbsoluteSystemPathBuf::new(base.repo_root.clone())?;
+    let (location, operations) = plan_workspace(&root, options)?;
+
+    if options.dry_run {
+        println!(
+            "{}",
+            base.ui.apply(BOLD.apply_to(format!(
+                "Dry run: would create {} in {}",
+                options.name, location
+            )))
+        );
+        for operation in &operations {
+            println!("  {}", operation);
+        }
+        return Ok(());
+    }
+
+    for operation in &operations {
+        operation.apply(&root)?;
+        println!(
+            "{}",
+            base.ui.apply(GREY.apply_to(format!("> {}", operation)))
+        );
+    }
+    println!(
+        "{}",
+        base.ui
+            .apply(BOLD.apply_to(format!("Created {} in {}", options.name, location)))
+    );
+
+    Ok(())
+}
+
+fn plan_workspace(
+    root: &AbsoluteSystemPathBuf,
+    options: &WorkspaceOptions,
+) -> Result<(AnchoredSystemPathBuf, Vec<FileOperation>)> {
+    let globs = PackageManager::detect(root.as_path())?
+        .get_workspace_globs(root.as_path())?
+        .ok_or_else(|| anyhow!("No workspaces are configured for this repository"))?;
+
+    if let Some(existing) = find_workspace(root, &globs, &options.name)? {
+        bail!(
+            "A workspace named {} already exists at {}",
+            options.name,
+            existing
+        );
+    }
+
+    let location = workspace_location(root, &globs, &options.name, options.workspace_type)?;
+    if root.resolve(&location).exists() {
+        bail!("{} already exists", location);
+    }
+
+    let mut operations = vec![FileOperation::CreateDir(location.clone())];
+    let package_json = match &options.copy {
+        Some(copy) => {
+            let source = find_workspace(root, &globs, copy)?
+                .ok_or_else(|| anyhow!("Unable to find workspace {} to copy", copy))?;
+            operations.extend(copy_operations(root, &source, &location)?);
+
+            let contents = fs::read_to_string(root.resolve(&package_json_path(&source)?))?;
+            let internal = workspace_names(root, &globs)?;
+            rewrite_package_json(&contents, &options.name, &internal)?
+        }
+        None => new_package_json(&options.name)?,
+    };
+    operations.push(FileOperation::Write {
+        path: package_json_path(&location)?,
+        contents: package_json,
+    });
+
+    Ok((location, operations))
+}
+
+fn package_json_path(workspace: &AnchoredSystemPathBuf) -> Result<AnchoredSystemPathBuf> {
+    Ok(workspace
+        .as_path()
+        .join("package.json")
+        .as_path()
+        .try_into()?)
+}
+
+/// Iterates over the package.json files of the workspaces matched by `globs`.
+fn workspace_package_jsons(root: &AbsoluteSystemPathBuf, globs: &Globs) -> turbopath::Glob {
+    root.glob(
+        globs
+            .inclusions
+            .iter()
+            .map(|glob| format!("{}/package.json", glob.trim_end_matches('/'))),
+        globs
+            .exclusions
+            .iter()
+            .map(String::as_str)
+            .chain(ALWAYS_EXCLUDED)
+            .map(String::from),
+    )
+}
+
+fn read_package_name(
+    root: &AbsoluteSystemPathBuf,
+    package_json: &AnchoredSystemPathBuf,
+) -> Result<Option<String>> {
+    let contents = fs::read_to_string(root.resolve(package_json))?;
+    let package: PackageName = serde_json::from_str(&contents)
+        .with_context(|| format!("Unable to parse {}", package_json))?;
+    Ok(package.name)
+}
+
+/// Finds a workspace either by the name in its package.json or by its path
+/// relative to the repo root.
+fn find_workspace(
+    root: &AbsoluteSystemPathBuf,
+    globs: &Globs,
+    name_or_path: &str,
+) -> Result<Option<AnchoredSystemPathBuf>> {
+    for package_json in workspace_package_jsons(root, globs) {
+        let package_json = package_json?;
+        let Some(workspace) = package_json.as_path().parent() else {
+            continue;
+        };
+        let workspace = AnchoredSystemPathBuf::try_from(workspace)?;
+        if workspace.to_unix()?.to_str()? == name_or_path.trim_end_matches('/') {
+            return Ok(Some(workspace));
+        }
+
+        if read_package_name(root, &package_json)?.as_deref() == Some(name_or_path) {
+            return Ok(Some(workspace));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the names of all workspaces in the repository.
+fn workspace_names(root: &AbsoluteSystemPathBuf, globs: &Globs) -> Result<HashSet<String>> {
+    let mut names = HashSet::new();
+    for package_json in workspace_package_jsons(root, globs) {
+        if let Some(name) = read_package_name(root, &package_json?)? {
+            names.insert(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Picks a directory for the new workspace from the parent directories of
+/// the workspace globs, preferring one that looks like it holds apps or
+/// packages depending on `workspace_type`.
+fn workspace_location(
+    root: &AbsoluteSystemPathBuf,
+    globs: &Globs,
+    name: &str,
+    workspace_type: WorkspaceType,
+) -> Result<AnchoredSystemPathBuf> {
+    // Scoped names such as @acme/ui live in a directory named ui
+    let dir_name = name.rsplit('/').next().unwrap_or(name);
+    if dir_name.is_empty() || dir_name.starts_with('.') {
+        bail!("{} is not a valid workspace name", name);
+    }
+
+    let parents: Vec<_> = globs
+        .inclusions
+        .iter()
+        .filter_map(|glob| {
+            glob.strip_suffix("/*")
+                .or_else(|| glob.strip_suffix("/**"))
+                .filter(|parent| !parent.contains(['*', '?', '[', '{']))
+        })
+        .collect();
+    let hint = match workspace_type {
+        WorkspaceType::App => "app",
+        WorkspaceType::Package => "package",
+    };
+    let parent = parents
+        .iter()
+        .find(|parent| parent.contains(hint))
+        .or_else(|| parents.first())
+        .ok_or_else(|| {
+            anyhow!(
+                "Unable to pick a directory for the new workspace from the workspace globs: {}",
+                globs.inclusions.join(", ")
+            )
+        })?;
+
+    let location = RelativeUnixPathBuf::new(format!("{}/{}", parent, dir_name))?.clean()?;
+    let location = AnchoredSystemPathBuf::from_unix(&location)?;
+    if !globs.test(root.as_path().into(), root.resolve(&location).into())? {
+        bail!("{} is not included in the workspace globs", location);
+    }
+
+    Ok(location)
+}
+
+fn copy_operations(
+    root: &AbsoluteSystemPathBuf,
+    source: &AnchoredSystemPathBuf,
+    destination: &AnchoredSystemPathBuf,
+) -> Result<Vec<FileOperation>> {
+    let source_root = root.resolve(source);
+    let mut exclude: Vec<String> = ALWAYS_EXCLUDED
+        .iter()
+        .map(|glob| glob.to_string())
+        .collect();
+    exclude.extend(output_globs(root)?);
+    // Written separately once the name and version have been rewritten
+    exclude.push("package.json".to_string());
+
+    // Only files are copied, directories are created as they are needed
+    let mut operations = Vec::new();
+    for path in source_root.glob(["**"], exclude) {
+        let path = path?;
+        if source_root.resolve(&path).as_path().is_dir() {
+            continue;
+        }
+        let from = source.as_path().join(path.as_path());
+        let to = destination.as_path().join(path.as_path());
+        operations.push(FileOperation::Copy {
+            from: from.as_path().try_into()?,
+            to: to.as_path().try_into()?,
+        });
+    }
+
+    Ok(operations)
+}
+
+/// Returns the outputs of every pipeline task so that build artifacts of the
+/// copied workspace aren't carried over.
+fn output_globs(root: &AbsoluteSystemPathBuf) -> Result<Vec<String>> {
+    let turbo_json_path = root.as_path().join("turbo.json");
+    if !turbo_json_path.exists() {
+        return Ok(Vec::new());
+    }
+    let turbo_json: TurboJson = serde_json::from_str(&fs::read_to_string(&turbo_json_path)?)
+        .context("Unable to parse turbo.json")?;
+
+    let mut outputs: Vec<_> = turbo_json
+        .pipeline
+        .into_values()
+        .flat_map(|task| task.outputs)
+        // Negated outputs are carved out of other outputs, so they are
+        // already covered
+        .filter(|output| !output.starts_with('!'))
+        .collect();
+    outputs.sort();
+    outputs.dedup();
+
+    Ok(outputs)
+}
+
+/// Gives the copied package.json a new name and initial version. Internal
+/// dependencies are loosened so the new workspace doesn't pin the versions
+/// the source workspace happened to depend on: those using the `workspace:`
+/// protocol become `workspace:*`, and those on any other workspace in
+/// `internal` (as npm and yarn v1 have no protocol for them) become `*`.
+fn rewrite_package_json(contents: &str, name: &str, internal: &HashSet<String>) -> Result<String> {
+    let mut package_json: OrderedJson = serde_json::from_str(contents)?;
+    let OrderedJson::Object(fields) = &mut package_json else {
+        bail!("package.json must contain an object");
+    };
+    fields.insert("name".to_string(), OrderedJson::Other(name.into()));
+    fields.insert(
+        "version".to_string(),
+        OrderedJson::Other(INITIAL_VERSION.into()),
+    );
+
+    for field in DEPENDENCY_FIELDS {
+        let Some(OrderedJson::Object(dependencies)) = fields.get_mut(field) else {
+            continue;
+        };
+        for (dependency, specifier) in dependencies.iter_mut() {
+            if let OrderedJson::Other(Value::String(s)) = specifier {
+                if s.starts_with(WORKSPACE_PROTOCOL) {
+                    *s = format!("{}*", WORKSPACE_PROTOCOL);
+                } else if internal.contains(dependency) {
+                    *s = "*".to_string();
+                }
+            }
+        }
+    }
+
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string_pretty(&package_json)?
+    ))
+}
+
+fn new_package_json(name: &str) -> Result<String> {
+    let package_json = serde_json::json!({
+        "name": name,
+        "version": INITIAL_VERSION,
+        "private": true,
+    });
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string_pretty(&package_json)?
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, fs, path::Path};
+
+    use anyhow::Result;
+    use tempfile::TempDir;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::{plan_workspace, rewrite_package_json, FileOperation, WorkspaceOptions};
+    use crate::cli::WorkspaceType;
+
+    fn setup() -> Result<(TempDir, AbsoluteSystemPathBuf)> {
+        let tmp = tempfile::tempdir()?;
+        let files = [
+            (
+                "package.json",
+                r#"{"packageManager": "npm@8.19.2", "workspaces": ["apps/*", "packages/*"]}"#,
+            ),
+            (
+                "turbo.json",
+                r#"{"pipeline": {"build": {"outputs": ["dist/**", ".next/**", "!.next/cache/**"]}}}"#,
+            ),
+            (
+                "apps/web/package.json",
+                r#"{"name": "web", "version": "1.2.3", "dependencies": {"ui": "workspace:^1.0.0", "react": "18.2.0"}}"#,
+            ),
+            ("apps/web/src/index.ts", "export {};"),
+            ("apps/web/dist/index.js", "export {};"),
+            ("apps/web/.next/cache/entry", ""),
+            ("apps/web/node_modules/react/index.js", ""),
+            (
+                "packages/ui/package.json",
+                r#"{"name": "ui", "version": "1.0.0"}"#,
+            ),
+        ];
+        for (path, contents) in files {
+            let path = tmp.path().join(path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, contents)?;
+        }
+        let root = AbsoluteSystemPathBuf::new(tmp.path())?;
+        Ok((tmp, root))
+    }
+
+    fn options(name: &str, workspace_type: WorkspaceType, copy: Option<&str>) -> WorkspaceOptions {
+        WorkspaceOptions {
+            name: name.to_string(),
+            workspace_type,
+            copy: copy.map(String::from),
+            dry_run: false,
+        }
+    }
+
+    fn describe(operations: &[FileOperation]) -> Vec<String> {
+        operations
+            .iter()
+            .map(|operation| operation.to_string().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_copy() -> Result<()> {
+        let (_tmp, root) = setup()?;
+        let (location, operations) =
+            plan_workspace(&root, &options("admin", WorkspaceType::App, Some("web")))?;
+
+        assert_eq!(location.as_path(), Path::new("apps").join("admin"));
+        assert_eq!(
+            describe(&operations),
+            vec![
+                "create apps/admin",
+                "copy   apps/web/src/index.ts -> apps/admin/src/index.ts",
+                "write  apps/admin/package.json",
+            ]
+        );
+
+        let FileOperation::Write { contents, .. } = operations.last().unwrap() else {
+            panic!("expected package.json to be written last");
+        };
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(contents)?,
+            serde_json::json!({
+                "name": "admin",
+                "version": "0.0.0",
+                "dependencies": {"ui": "workspace:*", "react": "18.2.0"},
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_copy_by_path() -> Result<()> {
+        let (_tmp, root) = setup()?;
+        let (location, _) = plan_workspace(
+            &root,
+            &options("@acme/config", WorkspaceType::Package, Some("packages/ui")),
+        )?;
+        assert_eq!(location.as_path(), Path::new("packages").join("config"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_new_package() -> Result<()> {
+        let (_tmp, root) = setup()?;
+        let (_, operations) =
+            plan_workspace(&root, &options("utils", WorkspaceType::Package, None))?;
+        assert_eq!(
+            describe(&operations),
+            vec![
+                "create packages/utils",
+                "write  packages/utils/package.json"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_pnpm_workspace() -> Result<()> {
+        let (tmp, root) = setup()?;
+        fs::write(
+            tmp.path().join("package.json"),
+            r#"{"packageManager": "pnpm@7.29.1"}"#,
+        )?;
+        fs::write(
+            tmp.path().join("pnpm-workspace.yaml"),
+            "packages:\n  - \"libs/*\"\n",
+        )?;
+        let (location, _) = plan_workspace(&root, &options("utils", WorkspaceType::Package, None))?;
+        assert_eq!(location.as_path(), Path::new("libs").join("utils"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_errors() -> Result<()> {
+        let (_tmp, root) = setup()?;
+        let err = plan_workspace(&root, &options("ui", WorkspaceType::Package, None)).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{}", err);
+
+        let err = plan_workspace(&root, &options("docs", WorkspaceType::App, Some("missing")))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Unable to find workspace missing to copy");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_operations() -> Result<()> {
+        let (_tmp, root) = setup()?;
+        let (location, operations) =
+            plan_workspace(&root, &options("admin", WorkspaceType::App, Some("web")))?;
+        for operation in &operations {
+            operation.apply(&root)?;
+        }
+
+        let workspace = root.resolve(&location);
+        assert!(workspace.as_path().join("src/index.ts").exists());
+        assert!(!workspace.as_path().join("dist").exists());
+        assert!(!workspace.as_path().join("node_modules").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_preserves_field_order() -> Result<()> {
+        let rewritten = rewrite_package_json(
+            r#"{"name": "web", "private": true, "version": "1.0.0", "scripts": {"lint": "eslint", "build": "next build"}, "dependencies": {"ui": "workspace:^", "next": "13.0.0"}}"#,
+            "admin",
+            &HashSet::new(),
+        )?;
+        assert_eq!(
+            rewritten,
+            r#"{
+  "name": "admin",
+  "private": true,
+  "version": "0.0.0",
+  "scripts": {
+    "lint": "eslint",
+    "build": "next build"
+  },
+  "dependencies": {
+    "ui": "workspace:*",
+    "next": "13.0.0"
+  }
+}
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_internal_versions() -> Result<()> {
+        let (tmp, root) = setup()?;
+        fs::write(
+            tmp.path().join("apps/web/package.json"),
+            r#"{"name": "web", "version": "1.2.3", "dependencies": {"ui": "^1.0.0", "react": "18.2.0"}, "devDependencies": {"web": "1.2.3"}}"#,
+        )?;
+        let (_, operations) =
+            plan_workspace(&root, &options("admin", WorkspaceType::App, Some("web")))?;
+
+        let FileOperation::Write { contents, .. } = operations.last().unwrap() else {
+            panic!("expected package.json to be written last");
+        };
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(contents)?,
+            serde_json::json!({
+                "name": "admin",
+                "version": "0.0.0",
+                "dependencies": {"ui": "*", "react": "18.2.0"},
+                "devDependencies": {"web": "*"},
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -16,6 +16,8 @@ use dialoguer::{theme::ColorfulTheme, Confirm};
 use dirs_next::home_dir;
 #[cfg(test)]
 use rand::Rng;
+use serde::Serialize;
+use thiserror::Error;
 use turborepo_api_client::{APIClient, CachingStatus, Team};
 
 #[cfg(not(test))]
@@ -31,6 +33,111 @@ pub(crate) enum SelectedTeam<'a> {
     Team(&'a Team),
 }
 
+/// How `turbo link` should interact with the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    /// Prompt for confirmation and team selection.
+    Interactive,
+    /// Never prompt. The team comes from `--team` (or the user's personal
+    /// scope if it is unset) and failures are reported as [`LinkError`]s.
+    NonInteractive {
+        /// Print the result as JSON instead of human readable text
+        json: bool,
+    },
+}
+
+impl LinkMode {
+    pub fn is_json(&self) -> bool {
+        matches!(self, LinkMode::NonInteractive { json: true })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LinkError {
+    #[error("User not found. Please login to Turborepo first by running `npx turbo login`.")]
+    NotLoggedIn,
+    #[error("no teams are available for this user")]
+    NoTeams,
+    #[error("unable to find team {0}")]
+    TeamNotFound(String),
+    #[error(
+        "Remote Caching is disabled for {team}. Visit {url} to enable it, then run `npx turbo \
+         link` again"
+    )]
+    CachingDisabled { team: String, url: String },
+    #[error(
+        "Remote Caching is disabled for {0} and only a team owner can enable it. Ask an owner to \
+         enable Remote Caching, then run `npx turbo link` again"
+    )]
+    InsufficientRole(String),
+    #[error("usage limit")]
+    OverLimit,
+    #[error("spending paused")]
+    Paused,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl LinkError {
+    /// A stable, machine readable identifier for the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            LinkError::NotLoggedIn => "not_logged_in",
+            LinkError::NoTeams => "no_teams",
+            LinkError::TeamNotFound(_) => "team_not_found",
+            LinkError::CachingDisabled { .. } => "caching_disabled",
+            LinkError::InsufficientRole(_) => "insufficient_role",
+            LinkError::OverLimit => "over_limit",
+            LinkError::Paused => "paused",
+            LinkError::Other(_) => "unknown",
+        }
+    }
+
+    /// The process exit code used for this error in non-interactive mode
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LinkError::Other(_) => 1,
+            LinkError::NotLoggedIn => 2,
+            LinkError::NoTeams => 3,
+            LinkError::TeamNotFound(_) => 4,
+            LinkError::CachingDisabled { .. } => 5,
+            LinkError::InsufficientRole(_) => 6,
+            LinkError::OverLimit => 7,
+            LinkError::Paused => 8,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkErrorOutput<'a> {
+    code: &'a str,
+    exit_code: i32,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkOutput<'a> {
+    team_id: &'a str,
+    team_slug: Option<&'a str>,
+    team_name: &'a str,
+}
+
+/// Prints `err` as a JSON object on stdout
+pub fn print_json_error(err: &LinkError) -> Result<()> {
+    let output = serde_json::json!({
+        "error": LinkErrorOutput {
+            code: err.code(),
+            exit_code: err.exit_code(),
+            message: err.to_string(),
+        }
+    });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+
+    Ok(())
+}
+
 pub(crate) const REMOTE_CACHING_INFO: &str = "  Remote Caching shares your cached Turborepo task \
                                               outputs and logs across
   all your team’s Vercel projects. It also can share outputs
@@ -100,40 +207,45 @@ pub(crate) async fn verify_caching_enabled<'a>(
     }
 }
 
-pub async fn link(base: &mut CommandBase, modify_gitignore: bool) -> Result<()> {
+pub async fn link(
+    base: &mut CommandBase,
+    modify_gitignore: bool,
+    mode: LinkMode,
+) -> Result<(), LinkError> {
     let homedir_path = home_dir().ok_or_else(|| anyhow!("could not find home directory."))?;
     let homedir = homedir_path.to_string_lossy();
-    println!(
-        ">>> Remote Caching
+    if !mode.is_json() {
+        println!(
+            ">>> Remote Caching
 
 {}
   For more info, see {}
   ",
-        REMOTE_CACHING_INFO,
-        base.ui.apply(UNDERLINE.apply_to(REMOTE_CACHING_URL))
-    );
+            REMOTE_CACHING_INFO,
+            base.ui.apply(UNDERLINE.apply_to(REMOTE_CACHING_URL))
+        );
+    }
 
     let repo_root_with_tilde = base.repo_root.to_string_lossy().replacen(&*homedir, "~", 1);
 
-    if !should_link(base, &repo_root_with_tilde)? {
-        return Err(anyhow!("canceled"));
+    if mode == LinkMode::Interactive && !should_link(base, &repo_root_with_tilde)? {
+        return Err(anyhow!("canceled").into());
     }
 
     let api_client = base.api_client()?;
-    let token = base.user_config()?.token().ok_or_else(|| {
-        anyhow!(
-            "User not found. Please login to Turborepo first by running {}.",
-            BOLD.apply_to("`npx turbo login`")
-        )
-    })?;
+    let token = base
+        .user_config()?
+        .token()
+        .ok_or(LinkError::NotLoggedIn)?
+        .to_string();
 
     let teams_response = api_client
-        .get_teams(token)
+        .get_teams(&token)
         .await
         .context("could not get team information")?;
 
     let user_response = api_client
-        .get_user(token)
+        .get_user(&token)
         .await
         .context("could not get user information")?;
 
@@ -143,14 +255,33 @@ pub async fn link(base: &mut CommandBase, modify_gitignore: bool) -> Result<()>
         .as_deref()
         .unwrap_or(user_response.user.username.as_str());
 
-    let selected_team = select_team(base, &teams_response.teams, user_display_name)?;
+    let resolved_team;
+    let selected_team = match mode {
+        LinkMode::Interactive => select_team(base, &teams_response.teams, user_display_name)?,
+        LinkMode::NonInteractive { .. } => match base.args.team.as_deref() {
+            Some(team) => {
+                resolved_team =
+                    resolve_team(&api_client, &token, &teams_response.teams, team).await?;
+                SelectedTeam::Team(&resolved_team)
+            }
+            None => SelectedTeam::User,
+        },
+    };
 
     let team_id = match selected_team {
         SelectedTeam::User => user_response.user.id.as_str(),
         SelectedTeam::Team(team) => team.id.as_str(),
     };
 
-    verify_caching_enabled(&api_client, team_id, token, Some(selected_team.clone())).await?;
+    match mode {
+        LinkMode::Interactive => {
+            verify_caching_enabled(&api_client, team_id, &token, Some(selected_team.clone()))
+                .await?
+        }
+        LinkMode::NonInteractive { .. } => {
+            check_caching_enabled(&api_client, team_id, &token, &selected_team).await?
+        }
+    }
 
     fs::create_dir_all(base.repo_root.join(".turbo"))
         .context("could not create .turbo directory")?;
@@ -166,6 +297,22 @@ pub async fn link(base: &mut CommandBase, modify_gitignore: bool) -> Result<()>
         add_turbo_to_gitignore(base)?;
     }
 
+    if mode.is_json() {
+        let output = LinkOutput {
+            team_id,
+            team_slug: match selected_team {
+                SelectedTeam::User => None,
+                SelectedTeam::Team(team) => Some(team.slug.as_str()),
+            },
+            team_name: chosen_team_name,
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output).context("could not serialize output")?
+        );
+        return Ok(());
+    }
+
     println!(
         "
 {}  Turborepo CLI authorized for {}
@@ -179,6 +326,69 @@ pub async fn link(base: &mut CommandBase, modify_gitignore: bool) -> Result<()>
     Ok(())
 }
 
+/// Finds the team matching `team` (either a slug or an id) without prompting.
+/// Teams that the user can see but that aren't part of the first page of
+/// `get_teams` are looked up by id.
+async fn resolve_team(
+    api_client: &APIClient,
+    token: &str,
+    teams: &[Team],
+    team: &str,
+) -> Result<Team, LinkError> {
+    if teams.is_empty() {
+        return Err(LinkError::NoTeams);
+    }
+    if let Some(found) = find_team(teams, team) {
+        return Ok(found.clone());
+    }
+
+    api_client
+        .get_team(token, team)
+        .await?
+        .ok_or_else(|| LinkError::TeamNotFound(team.to_string()))
+}
+
+fn find_team<'a>(teams: &'a [Team], team: &str) -> Option<&'a Team> {
+    teams
+        .iter()
+        .find(|candidate| candidate.slug == team || candidate.id == team)
+}
+
+/// Non-interactive counterpart of [`verify_caching_enabled`]: rather than
+/// offering to enable Remote Caching, a disabled cache is reported as an
+/// error.
+async fn check_caching_enabled(
+    api_client: &APIClient,
+    team_id: &str,
+    token: &str,
+    selected_team: &SelectedTeam<'_>,
+) -> Result<(), LinkError> {
+    let team_slug = match selected_team {
+        SelectedTeam::Team(team) => Some(team.slug.as_str()),
+        SelectedTeam::User => None,
+    };
+    let response = api_client
+        .get_caching_status(token, team_id, team_slug)
+        .await?;
+
+    match response.status {
+        CachingStatus::Enabled => Ok(()),
+        CachingStatus::OverLimit => Err(LinkError::OverLimit),
+        CachingStatus::Paused => Err(LinkError::Paused),
+        CachingStatus::Disabled => match selected_team {
+            SelectedTeam::Team(team) if team.is_owner() => Err(LinkError::CachingDisabled {
+                team: team.slug.clone(),
+                url: format!("https://vercel.com/teams/{}/settings/billing", team.slug),
+            }),
+            SelectedTeam::Team(team) => Err(LinkError::InsufficientRole(team.slug.clone())),
+            SelectedTeam::User => Err(LinkError::CachingDisabled {
+                team: team_id.to_string(),
+                url: "https://vercel.com/account/billing".to_string(),
+            }),
+        },
+    }
+}
+
 fn should_enable_caching() -> Result<bool> {
     let theme = ColorfulTheme::default();
     Ok(Confirm::with_theme(&theme)
@@ -300,32 +510,31 @@ fn add_turbo_to_gitignore(base: &CommandBase) -> Result<()> {
 mod test {
     use std::fs;
 
-    use tempfile::NamedTempFile;
+    use tempfile::{NamedTempFile, TempDir};
     use tokio::sync::OnceCell;
+    use turborepo_api_client::APIClient;
     use vercel_api_mock::start_test_server;
 
     use crate::{
-        commands::{link, CommandBase},
+        commands::{
+            link,
+            link::{LinkError, LinkMode},
+            CommandBase,
+        },
         config::{ClientConfigLoader, RepoConfigLoader, UserConfigLoader},
         ui::UI,
         Args,
     };
 
-    #[tokio::test]
-    async fn test_link() {
-        let user_config_file = NamedTempFile::new().unwrap();
-        fs::write(user_config_file.path(), r#"{ "token": "hello" }"#).unwrap();
-        let repo_config_file = NamedTempFile::new().unwrap();
-        fs::write(
-            repo_config_file.path(),
-            r#"{ "apiurl": "http://localhost:3000" }"#,
-        )
-        .unwrap();
-
-        let port = port_scanner::request_open_port().unwrap();
-        let handle = tokio::spawn(start_test_server(port));
-        let mut base = CommandBase {
-            repo_root: Default::default(),
+    fn test_base(
+        port: u16,
+        team: Option<&str>,
+        repo_root: &TempDir,
+        user_config_file: &NamedTempFile,
+        repo_config_file: &NamedTempFile,
+    ) -> CommandBase {
+        CommandBase {
+            repo_root: repo_root.path().to_path_buf(),
             ui: UI::new(false),
             client_config: OnceCell::from(ClientConfigLoader::new().load().unwrap()),
             user_config: OnceCell::from(
@@ -341,11 +550,39 @@ mod test {
                     .load()
                     .unwrap(),
             ),
-            args: Args::default(),
+            args: Args {
+                team: team.map(|team| team.to_string()),
+                ..Args::default()
+            },
             version: "",
-        };
+        }
+    }
 
-        link::link(&mut base, false).await.unwrap();
+    fn config_files() -> (NamedTempFile, NamedTempFile) {
+        let user_config_file = NamedTempFile::new().unwrap();
+        fs::write(user_config_file.path(), r#"{ "token": "hello" }"#).unwrap();
+        let repo_config_file = NamedTempFile::new().unwrap();
+        fs::write(
+            repo_config_file.path(),
+            r#"{ "apiurl": "http://localhost:3000" }"#,
+        )
+        .unwrap();
+
+        (user_config_file, repo_config_file)
+    }
+
+    #[tokio::test]
+    async fn test_link() {
+        let (user_config_file, repo_config_file) = config_files();
+        let repo_root = TempDir::new().unwrap();
+
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let mut base = test_base(port, None, &repo_root, &user_config_file, &repo_config_file);
+
+        link::link(&mut base, false, LinkMode::Interactive)
+            .await
+            .unwrap();
 
         handle.abort();
         let team_id = base.repo_config().unwrap().team_id();
@@ -354,4 +591,91 @@ mod test {
                 || team_id == Some(vercel_api_mock::EXPECTED_TEAM_ID)
         );
     }
+
+    #[tokio::test]
+    async fn test_link_non_interactive() {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let mode = LinkMode::NonInteractive { json: true };
+
+        for (team, expected_team_id) in [
+            (None, vercel_api_mock::EXPECTED_USER_ID),
+            (
+                Some(vercel_api_mock::EXPECTED_TEAM_SLUG),
+                vercel_api_mock::EXPECTED_TEAM_ID,
+            ),
+            (
+                Some(vercel_api_mock::EXPECTED_TEAM_ID),
+                vercel_api_mock::EXPECTED_TEAM_ID,
+            ),
+        ] {
+            let (user_config_file, repo_config_file) = config_files();
+            let repo_root = TempDir::new().unwrap();
+            let mut base = test_base(port, team, &repo_root, &user_config_file, &repo_config_file);
+
+            link::link(&mut base, true, mode).await.unwrap();
+
+            assert_eq!(
+                base.repo_config().unwrap().team_id(),
+                Some(expected_team_id)
+            );
+            assert_eq!(
+                fs::read_to_string(repo_root.path().join(".gitignore")).unwrap(),
+                ".turbo\n"
+            );
+        }
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_link_non_interactive_errors() {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let mode = LinkMode::NonInteractive { json: false };
+
+        for (team, expected_code, expected_exit_code) in [
+            ("missing_team", "team_not_found", 4),
+            (
+                vercel_api_mock::EXPECTED_DISABLED_TEAM_ID,
+                "caching_disabled",
+                5,
+            ),
+            (
+                vercel_api_mock::EXPECTED_MEMBER_TEAM_ID,
+                "insufficient_role",
+                6,
+            ),
+        ] {
+            let (user_config_file, repo_config_file) = config_files();
+            let repo_root = TempDir::new().unwrap();
+            let mut base = test_base(
+                port,
+                Some(team),
+                &repo_root,
+                &user_config_file,
+                &repo_config_file,
+            );
+
+            let err = link::link(&mut base, false, mode).await.unwrap_err();
+
+            assert_eq!(err.code(), expected_code, "{}", err);
+            assert_eq!(err.exit_code(), expected_exit_code);
+            assert_eq!(base.repo_config().unwrap().team_id(), None);
+        }
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_resolve_team_without_teams() {
+        let api_client = APIClient::new("http://localhost:0", None, "").unwrap();
+
+        let err = link::resolve_team(&api_client, "token", &[], "my-team")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, LinkError::NoTeams));
+        assert_eq!(err.exit_code(), 3);
+    }
 }
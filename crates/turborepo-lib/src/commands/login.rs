@@ -1,6 +1,6 @@
 #[cfg(not(test))]
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Context, Result};
 #[cfg(not(test))]
@@ -24,6 +24,10 @@ use crate::{
 const DEFAULT_HOST_NAME: &str = "127.0.0.1";
 const DEFAULT_PORT: u16 = 9789;
 const DEFAULT_SSO_PROVIDER: &str = "SAML/OIDC Single Sign-On";
+const DEVICE_AUTHORIZATION_PROVIDER: &str = "Device Authorization";
+// Polling interval to use if the server doesn't specify one
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.2
+const DEFAULT_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 pub async fn sso_login(base: &mut CommandBase, sso_team: &str) -> Result<()> {
     let redirect_url = format!("http://{DEFAULT_HOST_NAME}:{DEFAULT_PORT}");
@@ -46,7 +50,8 @@ pub async fn sso_login(base: &mut CommandBase, sso_team: &str) -> Result<()> {
         .get()
         .ok_or_else(|| anyhow!("no token auth token found"))?;
 
-    let token_name = make_token_name().context("failed to make sso token name")?;
+    let token_name =
+        make_token_name(DEFAULT_SSO_PROVIDER).context("failed to make sso token name")?;
 
     let api_client = base.api_client()?;
     let verified_user = api_client.verify_sso_token(token, &token_name).await?;
@@ -100,15 +105,78 @@ pub async fn sso_login(base: &mut CommandBase, sso_team: &str) -> Result<()> {
     Ok(())
 }
 
-fn make_token_name() -> Result<String> {
+fn make_token_name(provider: &str) -> Result<String> {
     let host = hostname::get()?;
 
     Ok(format!(
-        "Turbo CLI on {} via {DEFAULT_SSO_PROVIDER}",
+        "Turbo CLI on {} via {provider}",
         host.to_string_lossy()
     ))
 }
 
+/// Logs in without a browser or a local callback server, for use over SSH or
+/// in containers. The user authorizes the CLI by entering a code on another
+/// device while we poll for the resulting token.
+pub async fn device_login(base: &mut CommandBase) -> Result<()> {
+    let api_client = base.api_client()?;
+    let token_name = make_token_name(DEVICE_AUTHORIZATION_PROVIDER)
+        .context("failed to make device token name")?;
+    let device_code = api_client.create_device_code(&token_name).await?;
+
+    println!(
+        ">>> To authorize Turborepo, visit {} and enter the code {}",
+        base.ui
+            .apply(UNDERLINE.apply_to(&device_code.verification_uri)),
+        base.ui.apply(BOLD.apply_to(&device_code.user_code))
+    );
+    if let Some(verification_uri_complete) = &device_code.verification_uri_complete {
+        println!(
+            "{}",
+            base.ui.apply(GREY.apply_to(format!(
+                "  Or open {verification_uri_complete} to skip entering the code"
+            )))
+        );
+    }
+
+    let spinner = start_spinner("Waiting for your authorization...");
+    let interval = device_code
+        .interval
+        .map_or(DEFAULT_DEVICE_POLL_INTERVAL, Duration::from_secs);
+    let token = api_client
+        .poll_device_token(
+            &device_code.device_code,
+            interval,
+            Duration::from_secs(device_code.expires_in),
+        )
+        .await;
+    spinner.finish_and_clear();
+    let token = token.context("failed to authorize device")?;
+
+    base.user_config_mut()?.set_token(Some(token.clone()))?;
+
+    let user_response = api_client.get_user(&token).await?;
+
+    let ui = &base.ui;
+
+    println!(
+        "
+{} Turborepo CLI authorized for {}
+
+{}
+
+{}
+
+",
+        ui.rainbow(">>> Success!"),
+        user_response.user.email,
+        ui.apply(
+            CYAN.apply_to("To connect to your Remote Cache, run the following in any turborepo:")
+        ),
+        ui.apply(BOLD.apply_to("  npx turbo link"))
+    );
+    Ok(())
+}
+
 pub async fn login(base: &mut CommandBase) -> Result<()> {
     let repo_config = base.repo_config()?;
     let login_url_base = repo_config.login_url();
@@ -362,6 +430,49 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_device_login() {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let user_config_file = NamedTempFile::new().unwrap();
+        fs::write(user_config_file.path(), r#"{ "token": "hello" }"#).unwrap();
+        let repo_config_file = NamedTempFile::new().unwrap();
+        fs::write(repo_config_file.path(), "{}").unwrap();
+
+        let mut base = CommandBase {
+            repo_root: Default::default(),
+            ui: UI::new(false),
+            client_config: OnceCell::from(ClientConfigLoader::new().load().unwrap()),
+            user_config: OnceCell::from(
+                UserConfigLoader::new(user_config_file.path().to_path_buf())
+                    .load()
+                    .unwrap(),
+            ),
+            repo_config: OnceCell::from(
+                RepoConfigLoader::new(repo_config_file.path().to_path_buf())
+                    .with_api(Some(format!("http://localhost:{}", port)))
+                    .load()
+                    .unwrap(),
+            ),
+            args: Args::default(),
+            version: "",
+        };
+
+        login::device_login(&mut base).await.unwrap();
+
+        handle.abort();
+
+        assert_eq!(
+            base.user_config().unwrap().token().unwrap(),
+            vercel_api_mock::EXPECTED_TOKEN
+        );
+        // The token is persisted, not just held in memory
+        assert!(fs::read_to_string(user_config_file.path())
+            .unwrap()
+            .contains(vercel_api_mock::EXPECTED_TOKEN));
+    }
+
     #[derive(Debug, Clone, Deserialize)]
     struct TokenRequest {
         #[cfg(not(test))]
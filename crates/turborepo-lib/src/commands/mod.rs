@@ -16,6 +16,7 @@ use crate::{
 
 pub(crate) mod bin;
 pub(crate) mod daemon;
+pub(crate) mod generate;
 pub(crate) mod link;
 pub(crate) mod login;
 pub(crate) mod logout;
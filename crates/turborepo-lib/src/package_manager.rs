@@ -1,9 +1,11 @@
 use std::{
     fs,
     path::{Path, PathBuf},
+    process::Command,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use semver::Version;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -41,14 +43,18 @@ impl From<Workspaces> for Vec<String> {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct PackageJsonPackageManager {
+    #[serde(rename = "packageManager")]
+    package_manager: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PackageManager {
-    #[allow(dead_code)]
     Berry,
     Npm,
     Pnpm,
-    #[allow(dead_code)]
     Pnpm6,
-    #[allow(dead_code)]
     Yarn,
 }
 
@@ -80,6 +86,76 @@ impl Globs {
 }
 
 impl PackageManager {
+    /// Detects the package manager of the repository at `repo_root`. The
+    /// `packageManager` field of the root package.json takes precedence over
+    /// the lockfiles present in the root.
+    pub fn detect(repo_root: &Path) -> Result<Self> {
+        let package_json_text = fs::read_to_string(repo_root.join("package.json"))?;
+        let package_json: PackageJsonPackageManager = serde_json::from_str(&package_json_text)?;
+        if let Some(package_manager) = package_json.package_manager {
+            return Self::from_package_manager_field(&package_manager);
+        }
+
+        Self::detect_from_lockfile(repo_root)
+    }
+
+    /// Parses a `packageManager` field such as `pnpm@7.29.1`.
+    fn from_package_manager_field(package_manager: &str) -> Result<Self> {
+        let parse_error = || {
+            anyhow!(
+                "We could not parse packageManager field in package.json, expected: \
+                 (npm|pnpm|yarn)@<version>, received: {}",
+                package_manager
+            )
+        };
+        let (manager, version) = package_manager.split_once('@').ok_or_else(parse_error)?;
+        // Corepack allows a hash after the version, e.g. yarn@3.5.0+sha224.abc
+        let version = version.split('+').next().unwrap_or(version);
+        let version = Version::parse(version).map_err(|_| parse_error())?;
+
+        Self::from_version(manager, &version).ok_or_else(parse_error)
+    }
+
+    fn from_version(manager: &str, version: &Version) -> Option<Self> {
+        // Pre-releases count as their major version, e.g. yarn 2.0.0-rc.1 is berry
+        match manager {
+            "npm" => Some(PackageManager::Npm),
+            "pnpm" if version.major >= 7 => Some(PackageManager::Pnpm),
+            "pnpm" => Some(PackageManager::Pnpm6),
+            "yarn" if version.major >= 2 => Some(PackageManager::Berry),
+            "yarn" => Some(PackageManager::Yarn),
+            _ => None,
+        }
+    }
+
+    fn detect_from_lockfile(repo_root: &Path) -> Result<Self> {
+        if repo_root.join("yarn.lock").exists() {
+            // yarn.lock is used by both yarn and berry, so ask yarn which one it is
+            let output = Command::new("yarn")
+                .arg("--version")
+                .current_dir(repo_root)
+                .output()
+                .context("could not detect yarn version")?;
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = Version::parse(version.trim()).context("could not parse yarn version")?;
+            return Self::from_version("yarn", &version)
+                .ok_or_else(|| anyhow!("unsupported yarn version {}", version));
+        }
+        if repo_root.join("package-lock.json").exists() {
+            return Ok(PackageManager::Npm);
+        }
+        if repo_root.join("pnpm-lock.yaml").exists() {
+            return Ok(PackageManager::Pnpm);
+        }
+
+        bail!(
+            "We did not detect an in-use package manager for your project. Please set the \
+             \"packageManager\" property in your root package.json \
+             (https://nodejs.org/api/packages.html#packagemanager) or run `npx @turbo/codemod \
+             add-package-manager` in the root of your monorepo."
+        )
+    }
+
     /// Returns a list of globs for the package workspace.
     /// NOTE: We return a `Vec<PathBuf>` instead of a `GlobSet` because we
     /// may need to iterate through these globs and a `GlobSet` doesn't allow
@@ -180,6 +256,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_detect_package_manager_field() -> Result<()> {
+        let tests = [
+            ("npm@8.19.2", PackageManager::Npm),
+            ("pnpm@7.29.1", PackageManager::Pnpm),
+            ("pnpm@6.35.1", PackageManager::Pnpm6),
+            ("yarn@1.22.19", PackageManager::Yarn),
+            ("yarn@3.5.0+sha224.abc", PackageManager::Berry),
+            ("yarn@4.0.0-rc.42", PackageManager::Berry),
+        ];
+        for (field, expected) in tests {
+            let root = tempfile::tempdir()?;
+            fs::write(
+                root.path().join("package.json"),
+                format!("{{\"packageManager\": \"{}\"}}", field),
+            )?;
+            // The packageManager field wins over lockfiles
+            fs::write(root.path().join("package-lock.json"), "{}")?;
+            assert_eq!(PackageManager::detect(root.path())?, expected, "{}", field);
+        }
+
+        let root = tempfile::tempdir()?;
+        fs::write(
+            root.path().join("package.json"),
+            "{\"packageManager\": \"bun@0.5.0\"}",
+        )?;
+        assert!(PackageManager::detect(root.path()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_lockfile() -> Result<()> {
+        let tests = [
+            ("package-lock.json", PackageManager::Npm),
+            ("pnpm-lock.yaml", PackageManager::Pnpm),
+        ];
+        for (lockfile, expected) in tests {
+            let root = tempfile::tempdir()?;
+            fs::write(root.path().join("package.json"), "{}")?;
+            fs::write(root.path().join(lockfile), "")?;
+            assert_eq!(
+                PackageManager::detect(root.path())?,
+                expected,
+                "{}",
+                lockfile
+            );
+        }
+
+        let root = tempfile::tempdir()?;
+        fs::write(root.path().join("package.json"), "{}")?;
+        assert!(PackageManager::detect(root.path()).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_nested_workspace_globs() -> Result<()> {
         let top_level: PackageJsonWorkspaces =
@@ -118,6 +118,22 @@ impl NpmLockfile {
         }
     }
 
+    /// Serializes the lockfile back into the format npm writes to disk
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec_pretty(&self)?)
+    }
+
+    /// Returns every entry in the lockfile except for the root package
+    pub fn packages(&self) -> impl Iterator<Item = Package> + '_ {
+        self.packages
+            .iter()
+            .filter(|(key, _)| !key.is_empty())
+            .map(|(key, pkg)| Package {
+                key: key.clone(),
+                version: pkg.version.clone().unwrap_or_default(),
+            })
+    }
+
     fn get_package(&self, package: impl AsRef<str>) -> Result<&NpmPackage, Error> {
         let pkg_str = package.as_ref();
         self.packages
@@ -200,9 +216,7 @@ pub fn npm_subgraph(
 ) -> Result<Vec<u8>, Error> {
     let lockfile = NpmLockfile::load(contents)?;
     let pruned_lockfile = lockfile.subgraph(workspace_packages, packages)?;
-    let new_contents = serde_json::to_vec_pretty(&pruned_lockfile)?;
-
-    Ok(new_contents)
+    pruned_lockfile.encode()
 }
 
 #[cfg(test)]
@@ -377,6 +391,31 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_npm_encode_round_trip() -> Result<(), Error> {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock.json"))?;
+        let encoded = lockfile.encode()?;
+        let reloaded = NpmLockfile::load(&encoded)?;
+        assert_eq!(reloaded.encode()?, encoded);
+        assert_eq!(
+            reloaded.packages().collect::<Vec<_>>(),
+            lockfile.packages().collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_npm_packages_skips_root() -> Result<(), Error> {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock.json"))?;
+        let turbo = Package {
+            key: "node_modules/turbo".into(),
+            version: "1.5.5".into(),
+        };
+        assert!(lockfile.packages().all(|pkg| !pkg.key.is_empty()));
+        assert!(lockfile.packages().any(|pkg| pkg == turbo));
+        Ok(())
+    }
+
     #[test]
     fn test_npm_lockfile_serialization_stable() -> Result<(), Error> {
         let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock.json"))?;
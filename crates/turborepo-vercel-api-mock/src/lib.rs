@@ -1,9 +1,23 @@
-use std::net::SocketAddr;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use anyhow::Result;
-use axum::{routing::get, Json, Router};
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
 use turborepo_api_client::{
-    CachingStatus, CachingStatusResponse, Membership, Role, Team, TeamsResponse, User,
+    CachingStatus, CachingStatusResponse, DeviceCodeResponse, DeviceTokenError,
+    DeviceTokenErrorResponse, DeviceTokenResponse, Membership, Role, Team, TeamsResponse, User,
     UserResponse, VerificationResponse,
 };
 
@@ -18,10 +32,79 @@ pub const EXPECTED_TEAM_SLUG: &str = "expected_team_slug";
 pub const EXPECTED_TEAM_NAME: &str = "expected_team_name";
 pub const EXPECTED_TEAM_CREATED_AT: u64 = 0;
 
+/// A team the user owns but that has Remote Caching disabled
+pub const EXPECTED_DISABLED_TEAM_ID: &str = "expected_disabled_team_id";
+pub const EXPECTED_DISABLED_TEAM_SLUG: &str = "expected_disabled_team_slug";
+pub const EXPECTED_DISABLED_TEAM_NAME: &str = "expected_disabled_team_name";
+
+/// A team the user is only a member of, with Remote Caching disabled
+pub const EXPECTED_MEMBER_TEAM_ID: &str = "expected_member_team_id";
+pub const EXPECTED_MEMBER_TEAM_SLUG: &str = "expected_member_team_slug";
+pub const EXPECTED_MEMBER_TEAM_NAME: &str = "expected_member_team_name";
+
 pub const EXPECTED_SSO_TEAM_ID: &str = "expected_sso_team_id";
 pub const EXPECTED_SSO_TEAM_SLUG: &str = "expected_sso_team_slug";
 
+pub const EXPECTED_DEVICE_CODE: &str = "expected_device_code";
+pub const EXPECTED_USER_CODE: &str = "EXPE-CTED";
+/// Number of times the device token endpoint reports the authorization as
+/// pending before handing out the token
+pub const EXPECTED_PENDING_DEVICE_POLLS: usize = 2;
+
+fn expected_team(team_id: &str) -> Option<Team> {
+    let (id, slug, name, role) = match team_id {
+        EXPECTED_TEAM_ID => (
+            EXPECTED_TEAM_ID,
+            EXPECTED_TEAM_SLUG,
+            EXPECTED_TEAM_NAME,
+            Role::Owner,
+        ),
+        EXPECTED_DISABLED_TEAM_ID => (
+            EXPECTED_DISABLED_TEAM_ID,
+            EXPECTED_DISABLED_TEAM_SLUG,
+            EXPECTED_DISABLED_TEAM_NAME,
+            Role::Owner,
+        ),
+        EXPECTED_MEMBER_TEAM_ID => (
+            EXPECTED_MEMBER_TEAM_ID,
+            EXPECTED_MEMBER_TEAM_SLUG,
+            EXPECTED_MEMBER_TEAM_NAME,
+            Role::Member,
+        ),
+        _ => return None,
+    };
+
+    Some(Team {
+        id: id.to_string(),
+        slug: slug.to_string(),
+        name: name.to_string(),
+        created_at: EXPECTED_TEAM_CREATED_AT,
+        created: Default::default(),
+        membership: Membership::new(role),
+    })
+}
+
+fn device_token_response(device_code: Option<&String>, polls: &AtomicUsize) -> Response {
+    let error = if device_code.map(String::as_str) != Some(EXPECTED_DEVICE_CODE) {
+        DeviceTokenError::ExpiredToken
+    } else if polls.fetch_add(1, Ordering::SeqCst) < EXPECTED_PENDING_DEVICE_POLLS {
+        DeviceTokenError::AuthorizationPending
+    } else {
+        return Json(DeviceTokenResponse {
+            token: EXPECTED_TOKEN.to_string(),
+        })
+        .into_response();
+    };
+
+    (
+        StatusCode::BAD_REQUEST,
+        Json(DeviceTokenErrorResponse { error }),
+    )
+        .into_response()
+}
+
 pub async fn start_test_server(port: u16) -> Result<()> {
+    let device_polls = Arc::new(AtomicUsize::new(0));
     let app = Router::new()
         .route(
             "/v2/user",
@@ -52,14 +135,45 @@ pub async fn start_test_server(port: u16) -> Result<()> {
                 })
             }),
         )
+        .route(
+            "/v2/team",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                Json(params.get("teamId").and_then(|id| expected_team(id)))
+            }),
+        )
         .route(
             "/v8/artifacts/status",
-            get(|| async {
-                Json(CachingStatusResponse {
-                    status: CachingStatus::Enabled,
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                let status = match params.get("teamSlug").map(String::as_str) {
+                    Some(EXPECTED_DISABLED_TEAM_SLUG) | Some(EXPECTED_MEMBER_TEAM_SLUG) => {
+                        CachingStatus::Disabled
+                    }
+                    _ => CachingStatus::Enabled,
+                };
+                Json(CachingStatusResponse { status })
+            }),
+        )
+        .route(
+            "/registration/device/code",
+            post(move || async move {
+                Json(DeviceCodeResponse {
+                    device_code: EXPECTED_DEVICE_CODE.to_string(),
+                    user_code: EXPECTED_USER_CODE.to_string(),
+                    verification_uri: format!("http://localhost:{port}/device"),
+                    verification_uri_complete: None,
+                    expires_in: 60,
+                    // Poll as fast as possible to keep tests quick
+                    interval: Some(0),
                 })
             }),
         )
+        .route(
+            "/registration/device/token",
+            post(move |Query(params): Query<HashMap<String, String>>| {
+                let device_polls = device_polls.clone();
+                async move { device_token_response(params.get("deviceCode"), &device_polls) }
+            }),
+        )
         .route(
             "/registration/verify",
             get(|| async move {